@@ -0,0 +1,138 @@
+//! Archive of completed tasks
+//!
+//! `archive` moves completed tasks out of the todo list into a JSON data file
+//! of their own, `<data file>.archive.json`, so they stop cluttering `list`
+//! but can still be listed, searched and exported with `--archived`. Tasks
+//! keep their IDs, so `unarchive` can move them back; `purge` drops archived
+//! tasks for good.
+//!
+//! A task is only archived together with all of its subtasks, so a tree is
+//! never split between the two files. Archiving, unarchiving and purging are
+//! recorded in the undo journal with the changes to both files, so `undo`
+//! moves the tasks back.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use crate::models::{Task, TodoList};
+use crate::storage::JsonStorage;
+
+/// Location of the archive for a data file
+pub fn path_for(data_path: &Path) -> PathBuf {
+    let file_name = data_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    data_path.with_file_name(format!("{}.archive.json", file_name))
+}
+
+/// Storage for the archive of a data file, whatever backend the data file uses
+pub fn open(data_path: &Path) -> JsonStorage {
+    JsonStorage::new(path_for(data_path))
+}
+
+/// When a task was completed, for tasks completed before completion times were recorded the time it was created
+pub fn completed_at(task: &Task) -> DateTime<Local> {
+    task.completed_at.unwrap_or(task.created_at)
+}
+
+/// IDs of the completed tasks to archive, only those completed before `cutoff` if given
+///
+/// A task is left out unless all of its subtasks can be archived as well.
+pub fn select(todo_list: &TodoList, cutoff: Option<DateTime<Local>>) -> BTreeSet<u32> {
+    let qualifies = |task: &Task| task.completed && cutoff.is_none_or(|cutoff| completed_at(task) < cutoff);
+    todo_list
+        .get_all_tasks()
+        .iter()
+        .filter(|task| qualifies(task) && todo_list.get_descendants(task.id).into_iter().all(qualifies))
+        .map(|task| task.id)
+        .collect()
+}
+
+/// Move tasks from one list to another, keeping their IDs
+///
+/// The tasks left in `from` stop depending on the moved tasks, and moved tasks
+/// lose their parent and dependencies unless those are in `to` as well.
+pub fn move_tasks(from: &mut TodoList, to: &mut TodoList, ids: &BTreeSet<u32>) -> Result<()> {
+    for &id in ids {
+        if from.get_task(id).is_none() {
+            return Err(anyhow!("Task with ID {} not found", id));
+        }
+        if to.get_task(id).is_some() {
+            return Err(anyhow!("Task with ID {} is in both the todo list and the archive", id));
+        }
+    }
+
+    let moved: Vec<Task> = ids.iter().filter_map(|&id| from.discard_task(id)).collect();
+    let remaining: Vec<u32> = from.get_all_tasks().iter().map(|task| task.id).collect();
+    for id in remaining {
+        if let Some(task) = from.get_task_mut(id) {
+            task.depends_on.retain(|dep| !ids.contains(dep));
+        }
+    }
+
+    let known = |to: &TodoList, id: u32| ids.contains(&id) || to.get_task(id).is_some();
+    for mut task in moved {
+        if task.parent_id.is_some_and(|parent| !known(to, parent)) {
+            task.parent_id = None;
+        }
+        task.depends_on.retain(|&dep| known(to, dep));
+        if to.next_id() <= task.id {
+            to.set_next_id(task.id + 1);
+        }
+        to.restore_task(task);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_path_for() {
+        assert_eq!(path_for(Path::new("/data/todos.json")), Path::new("/data/todos.json.archive.json"));
+        assert_eq!(path_for(Path::new("tasks.db")), Path::new("tasks.db.archive.json"));
+    }
+
+    #[test]
+    fn test_select_and_move_keep_trees_together() {
+        let mut todo_list = TodoList::new();
+        let release = todo_list.add_task("Release 1.0".to_string());
+        let changelog = todo_list.add_task("Write changelog".to_string());
+        let tag = todo_list.add_task("Tag release".to_string());
+        let announce = todo_list.add_task("Announce release".to_string());
+        let old = todo_list.add_task("Renew domain".to_string());
+        todo_list.set_parent(changelog, Some(release)).unwrap();
+        todo_list.set_parent(tag, Some(release)).unwrap();
+        todo_list.add_dependency(announce, release).unwrap();
+        todo_list.mark_complete(changelog).unwrap();
+        todo_list.mark_complete(old).unwrap();
+        todo_list.get_task_mut(old).unwrap().completed_at = Some(Local::now() - Duration::days(60));
+
+        // The release has a pending subtask, so only the changelog goes
+        assert_eq!(select(&todo_list, None), BTreeSet::from([changelog, old]));
+        assert_eq!(select(&todo_list, Some(Local::now() - Duration::days(30))), BTreeSet::from([old]));
+
+        todo_list.mark_complete(release).unwrap();
+        let ids = select(&todo_list, None);
+        assert_eq!(ids, BTreeSet::from([release, changelog, tag, old]));
+
+        let mut archive = TodoList::new();
+        move_tasks(&mut todo_list, &mut archive, &ids).unwrap();
+        assert_eq!(todo_list.len(), 1);
+        assert!(todo_list.get_task(announce).unwrap().depends_on.is_empty());
+        assert_eq!(archive.get_task(tag).unwrap().parent_id, Some(release));
+        assert_eq!(archive.next_id(), old + 1);
+        assert!(move_tasks(&mut todo_list, &mut archive, &BTreeSet::from([release])).is_err());
+
+        // Moving a subtask back on its own makes it a top-level task
+        move_tasks(&mut archive, &mut todo_list, &BTreeSet::from([tag])).unwrap();
+        assert_eq!(todo_list.get_task(tag).unwrap().parent_id, None);
+        assert_eq!(todo_list.next_id(), old + 1);
+        assert_eq!(archive.get_descendants(release).len(), 1);
+    }
+}
@@ -0,0 +1,579 @@
+//! User configuration: defaults and behaviour read from a TOML file
+//!
+//! The file is `$XDG_CONFIG_HOME/rtodo/config.toml` (usually
+//! `~/.config/rtodo/config.toml`) unless `--config` or `$RTODO_CONFIG` names
+//! another one. Every key is optional, and a missing file means all defaults:
+//!
+//! ```toml
+//! default_priority = "high"        # priority of new tasks (medium)
+//! default_category = "inbox"       # category of new tasks (none)
+//! data_file = "~/Sync/tasks.json"  # data file when --file isn't given
+//! due_soon_days = 3                # how far ahead "due soon" looks (7)
+//! date_dialect = "uk"              # read 02/11 as 2 November (us: 11 February)
+//! default_sort = "urgency"         # sort field when --sort-by isn't given
+//! color = "never"                  # auto, always or never (auto)
+//! trash_days = 90                  # days removed tasks stay in the trash (30)
+//!
+//! color_high = "magenta"           # colour of high priority (red)
+//! color_overdue = "bright red"     # colour of overdue due dates (red)
+//! color_category = "#5f87af"       # colour of categories (green)
+//!
+//! [category_weights]               # extra urgency by category (none)
+//! work = 3
+//! someday = -5
+//! ```
+//!
+//! The other colour keys are `color_medium` (yellow), `color_low` (blue),
+//! `color_due_soon` (yellow, bold when due today) and `color_due` (blue).
+//! Colours are names such as `bright blue`, or `#rrggbb` hex values.
+//! `--trash-days` and `--category-weight` override the file; a weight given
+//! with the flag replaces the file's weight for that category only.
+//!
+//! `rtodo config set` rewrites the file, so comments in it are not kept.
+
+use anyhow::{anyhow, Result};
+use chrono::Duration;
+use colored::Color;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::Priority;
+use crate::urgency::Weights;
+
+/// Keys of the configuration file, in the order `config list` shows them
+pub const KEYS: [&str; 16] = [
+    "default_priority",
+    "default_category",
+    "data_file",
+    "due_soon_days",
+    "date_dialect",
+    "default_sort",
+    "trash_days",
+    "category_weights",
+    "color",
+    "color_high",
+    "color_medium",
+    "color_low",
+    "color_overdue",
+    "color_due_soon",
+    "color_due",
+    "color_category",
+];
+
+const DEFAULT_DUE_SOON_DAYS: u32 = 7;
+const DEFAULT_TRASH_DAYS: u32 = 30;
+
+/// How day and month are ordered in dates such as 02/11/2026
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DateDialect {
+    /// Month first: 02/11 is 11 February
+    Us,
+    /// Day first: 02/11 is 2 November
+    Uk,
+}
+
+impl From<DateDialect> for chrono_english::Dialect {
+    fn from(dialect: DateDialect) -> Self {
+        match dialect {
+            DateDialect::Us => chrono_english::Dialect::Us,
+            DateDialect::Uk => chrono_english::Dialect::Uk,
+        }
+    }
+}
+
+/// When output is coloured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    /// Colour on terminals, unless `NO_COLOR` is set
+    Auto,
+    /// Always colour, also when output is piped
+    Always,
+    /// Plain text only
+    Never,
+}
+
+/// Colours of task output, set with the `color_*` keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub high: Color,
+    pub medium: Color,
+    pub low: Color,
+    pub overdue: Color,
+    /// Due soon; tasks due today use it in bold
+    pub due_soon: Color,
+    /// Due later than the due-soon window
+    pub due: Color,
+    pub category: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            high: Color::Red,
+            medium: Color::Yellow,
+            low: Color::Blue,
+            overdue: Color::Red,
+            due_soon: Color::Yellow,
+            due: Color::Blue,
+            category: Color::Green,
+        }
+    }
+}
+
+impl Theme {
+    /// The colour of a priority
+    pub fn priority(&self, priority: &Priority) -> Color {
+        match priority {
+            Priority::High => self.high,
+            Priority::Medium => self.medium,
+            Priority::Low => self.low,
+        }
+    }
+}
+
+impl fmt::Display for DateDialect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DateDialect::Us => write!(f, "us"),
+            DateDialect::Uk => write!(f, "uk"),
+        }
+    }
+}
+
+impl fmt::Display for ColorChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ColorChoice::Auto => write!(f, "auto"),
+            ColorChoice::Always => write!(f, "always"),
+            ColorChoice::Never => write!(f, "never"),
+        }
+    }
+}
+
+/// Settings from the configuration file; unset keys keep the built-in defaults
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_priority: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_category: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_file: Option<PathBuf>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due_soon_days: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date_dialect: Option<DateDialect>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_sort: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trash_days: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<ColorChoice>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_high: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_medium: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_low: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_overdue: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_due_soon: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_due: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color_category: Option<String>,
+    /// Kept last: TOML writes tables after plain keys
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub category_weights: BTreeMap<String, f64>,
+}
+
+impl Config {
+    /// Location of the configuration file in the user's config directory
+    pub fn default_path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("rtodo").join("config.toml"))
+    }
+
+    /// Load a configuration file, returning the defaults if it doesn't exist
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let config = Self::read_from_file(path)?;
+        config.validate().map_err(|e| anyhow!("Invalid config {}: {}", path.display(), e))?;
+        Ok(config)
+    }
+
+    /// Load a configuration file without checking its values
+    ///
+    /// `config set` and `config unset` use this, so they can repair a file
+    /// holding a value every other command rejects.
+    pub fn read_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read config {}: {}", path.display(), e))?;
+        toml::from_str(&contents).map_err(|e| anyhow!("Failed to parse config {}: {}", path.display(), e))
+    }
+
+    /// Save the configuration, creating its directory if needed
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .map_err(|e| anyhow!("Failed to create config directory {}: {}", parent.display(), e))?;
+        }
+        let contents = toml::to_string(self).map_err(|e| anyhow!("Failed to serialize config: {}", e))?;
+        fs::write(path, contents).map_err(|e| anyhow!("Failed to write config {}: {}", path.display(), e))
+    }
+
+    /// Check values the TOML types alone don't rule out
+    fn validate(&self) -> Result<()> {
+        if let Some(priority) = &self.default_priority {
+            parse_priority(priority)?;
+        }
+        if let Some(category) = &self.default_category {
+            if category.trim().is_empty() {
+                return Err(anyhow!("default_category cannot be empty"));
+            }
+        }
+        if self.due_soon_days == Some(0) {
+            return Err(anyhow!("due_soon_days must be at least 1"));
+        }
+        for key in KEYS.iter().filter(|key| key.starts_with("color_")) {
+            if let Some(color) = self.get(key)? {
+                parse_color(&color).map_err(|e| anyhow!("invalid {}: {}", key, e))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Priority of new tasks
+    pub fn priority(&self) -> Priority {
+        self.default_priority
+            .as_deref()
+            .and_then(|priority| parse_priority(priority).ok())
+            .unwrap_or_default()
+    }
+
+    /// Data file to use when none is given, with a leading `~` expanded
+    pub fn data_file(&self) -> Option<PathBuf> {
+        let path = self.data_file.as_ref()?;
+        match (path.strip_prefix("~"), dirs::home_dir()) {
+            (Ok(rest), Some(home)) => Some(home.join(rest)),
+            _ => Some(path.clone()),
+        }
+    }
+
+    /// How far ahead a task counts as due soon
+    pub fn due_soon_window(&self) -> Duration {
+        Duration::days(self.due_soon_days.unwrap_or(DEFAULT_DUE_SOON_DAYS).into())
+    }
+
+    /// Days removed tasks stay in the trash; 0 keeps them
+    pub fn trash_days(&self) -> u32 {
+        self.trash_days.unwrap_or(DEFAULT_TRASH_DAYS)
+    }
+
+    /// Extra urgency for categories
+    pub fn weights(&self) -> Weights {
+        Weights::from_table(&self.category_weights)
+    }
+
+    /// Dialect for reading dates such as 02/11
+    pub fn dialect(&self) -> chrono_english::Dialect {
+        self.date_dialect.unwrap_or(DateDialect::Us).into()
+    }
+
+    /// Output colours, with the defaults for keys that aren't set
+    pub fn theme(&self) -> Theme {
+        let defaults = Theme::default();
+        let color = |value: &Option<String>, default: Color| {
+            value.as_deref().and_then(|value| parse_color(value).ok()).unwrap_or(default)
+        };
+        Theme {
+            high: color(&self.color_high, defaults.high),
+            medium: color(&self.color_medium, defaults.medium),
+            low: color(&self.color_low, defaults.low),
+            overdue: color(&self.color_overdue, defaults.overdue),
+            due_soon: color(&self.color_due_soon, defaults.due_soon),
+            due: color(&self.color_due, defaults.due),
+            category: color(&self.color_category, defaults.category),
+        }
+    }
+
+    /// The value set for a key, if any
+    pub fn get(&self, key: &str) -> Result<Option<String>> {
+        Ok(match check_key(key)? {
+            "default_priority" => self.default_priority.clone(),
+            "default_category" => self.default_category.clone(),
+            "data_file" => self.data_file.as_ref().map(|path| path.display().to_string()),
+            "due_soon_days" => self.due_soon_days.map(|days| days.to_string()),
+            "date_dialect" => self.date_dialect.map(|dialect| dialect.to_string()),
+            "default_sort" => self.default_sort.clone(),
+            "trash_days" => self.trash_days.map(|days| days.to_string()),
+            "category_weights" if self.category_weights.is_empty() => None,
+            "category_weights" => Some(
+                self.category_weights
+                    .iter()
+                    .map(|(category, weight)| format!("{}={}", category, weight))
+                    .collect::<Vec<_>>()
+                    .join(","),
+            ),
+            "color" => self.color.map(|color| color.to_string()),
+            "color_high" => self.color_high.clone(),
+            "color_medium" => self.color_medium.clone(),
+            "color_low" => self.color_low.clone(),
+            "color_overdue" => self.color_overdue.clone(),
+            "color_due_soon" => self.color_due_soon.clone(),
+            "color_due" => self.color_due.clone(),
+            _ => self.color_category.clone(),
+        })
+    }
+
+    /// The value a key has when it isn't set
+    pub fn default_value(key: &str) -> Result<Option<String>> {
+        Ok(match check_key(key)? {
+            "default_priority" => Some("medium".to_string()),
+            "due_soon_days" => Some(DEFAULT_DUE_SOON_DAYS.to_string()),
+            "date_dialect" => Some(DateDialect::Us.to_string()),
+            "trash_days" => Some(DEFAULT_TRASH_DAYS.to_string()),
+            "color" => Some(ColorChoice::Auto.to_string()),
+            "color_high" | "color_overdue" => Some("red".to_string()),
+            "color_medium" | "color_due_soon" => Some("yellow".to_string()),
+            "color_low" | "color_due" => Some("blue".to_string()),
+            "color_category" => Some("green".to_string()),
+            _ => None,
+        })
+    }
+
+    /// Set a key from its text form, checking the value
+    pub fn set(&mut self, key: &str, value: &str) -> Result<()> {
+        let value = value.trim();
+        let invalid = |expected: &str| anyhow!("Invalid value '{}' for {} (expected {})", value, key, expected);
+        match check_key(key)? {
+            "default_priority" => {
+                let priority = parse_priority(value).map_err(|_| invalid("high, medium or low"))?;
+                self.default_priority = Some(format!("{:?}", priority).to_lowercase());
+            }
+            "default_category" if value.is_empty() => return Err(invalid("a category name")),
+            "default_category" => self.default_category = Some(value.to_string()),
+            "data_file" if value.is_empty() => return Err(invalid("a file path")),
+            "data_file" => self.data_file = Some(PathBuf::from(value)),
+            "due_soon_days" => {
+                let days: u32 = value.parse().ok().filter(|&days| days > 0).ok_or_else(|| invalid("a number of days"))?;
+                self.due_soon_days = Some(days);
+            }
+            "date_dialect" => {
+                self.date_dialect = Some(match value.to_ascii_lowercase().as_str() {
+                    "us" => DateDialect::Us,
+                    "uk" => DateDialect::Uk,
+                    _ => return Err(invalid("us or uk")),
+                });
+            }
+            "default_sort" if value.is_empty() => return Err(invalid("a sort field")),
+            "default_sort" => self.default_sort = Some(value.to_ascii_lowercase()),
+            "trash_days" => self.trash_days = Some(value.parse().map_err(|_| invalid("a number of days"))?),
+            "category_weights" => {
+                let specs: Vec<&str> = value.split(',').filter(|spec| !spec.trim().is_empty()).collect();
+                let weights = Weights::parse(&specs)
+                    .ok()
+                    .filter(|_| !specs.is_empty())
+                    .ok_or_else(|| invalid("CATEGORY=WEIGHT pairs such as work=3,someday=-5"))?;
+                self.category_weights = weights.table().clone();
+            }
+            "color" => {
+                self.color = Some(match value.to_ascii_lowercase().as_str() {
+                    "auto" => ColorChoice::Auto,
+                    "always" => ColorChoice::Always,
+                    "never" | "none" => ColorChoice::Never,
+                    _ => return Err(invalid("auto, always or never")),
+                });
+            }
+            theme_key => {
+                parse_color(value).map_err(|_| invalid("a colour such as red, bright blue or #5f87af"))?;
+                let color = Some(value.to_ascii_lowercase().replace(['_', '-'], " "));
+                match theme_key {
+                    "color_high" => self.color_high = color,
+                    "color_medium" => self.color_medium = color,
+                    "color_low" => self.color_low = color,
+                    "color_overdue" => self.color_overdue = color,
+                    "color_due_soon" => self.color_due_soon = color,
+                    "color_due" => self.color_due = color,
+                    _ => self.color_category = color,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Clear a key, going back to its default; returns whether it was set
+    pub fn unset(&mut self, key: &str) -> Result<bool> {
+        let was_set = self.get(key)?.is_some();
+        match check_key(key)? {
+            "default_priority" => self.default_priority = None,
+            "default_category" => self.default_category = None,
+            "data_file" => self.data_file = None,
+            "due_soon_days" => self.due_soon_days = None,
+            "date_dialect" => self.date_dialect = None,
+            "default_sort" => self.default_sort = None,
+            "trash_days" => self.trash_days = None,
+            "category_weights" => self.category_weights.clear(),
+            "color" => self.color = None,
+            "color_high" => self.color_high = None,
+            "color_medium" => self.color_medium = None,
+            "color_low" => self.color_low = None,
+            "color_overdue" => self.color_overdue = None,
+            "color_due_soon" => self.color_due_soon = None,
+            "color_due" => self.color_due = None,
+            _ => self.color_category = None,
+        }
+        Ok(was_set)
+    }
+}
+
+fn check_key(key: &str) -> Result<&'static str> {
+    KEYS.iter()
+        .find(|known| **known == key)
+        .copied()
+        .ok_or_else(|| anyhow!("Unknown config key '{}' (expected one of: {})", key, KEYS.join(", ")))
+}
+
+fn parse_priority(value: &str) -> Result<Priority> {
+    match value.to_ascii_lowercase().as_str() {
+        "high" | "h" => Ok(Priority::High),
+        "medium" | "m" => Ok(Priority::Medium),
+        "low" | "l" => Ok(Priority::Low),
+        _ => Err(anyhow!("invalid default_priority '{}' (expected high, medium or low)", value)),
+    }
+}
+
+/// Parse a colour name such as "red" or "bright blue" (`_` and `-` may stand
+/// for the space), or a `#rrggbb` hex colour
+fn parse_color(value: &str) -> Result<Color> {
+    let invalid = || anyhow!("unknown colour '{}'", value);
+    if let Some(hex) = value.strip_prefix('#') {
+        let channel = |index: usize| hex.get(index..index + 2).and_then(|digits| u8::from_str_radix(digits, 16).ok());
+        return match (hex.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Color::TrueColor { r, g, b }),
+            _ => Err(invalid()),
+        };
+    }
+    value.replace(['_', '-'], " ").parse().map_err(|()| invalid())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_load_and_save() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("rtodo").join("config.toml");
+        assert_eq!(Config::load_from_file(&path).unwrap(), Config::default());
+
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "default_priority = \"High\"\ndue_soon_days = 3\ndate_dialect = \"uk\"\ndata_file = \"~/tasks.db\"\n").unwrap();
+        let config = Config::load_from_file(&path).unwrap();
+        assert_eq!(config.priority(), Priority::High);
+        assert_eq!(config.due_soon_window(), Duration::days(3));
+        assert_eq!(config.dialect(), chrono_english::Dialect::Uk);
+        assert_eq!(config.data_file(), dirs::home_dir().map(|home| home.join("tasks.db")));
+
+        let mut config = config;
+        config.set("color", "never").unwrap();
+        config.save_to_file(&path).unwrap();
+        assert_eq!(Config::load_from_file(&path).unwrap(), config);
+
+        fs::write(&path, "due_soon = 3\n").unwrap();
+        assert!(Config::load_from_file(&path).unwrap_err().to_string().contains("Failed to parse config"));
+        fs::write(&path, "default_priority = \"urgent\"\n").unwrap();
+        assert!(Config::load_from_file(&path).unwrap_err().to_string().contains("Invalid config"));
+        assert_eq!(Config::read_from_file(&path).unwrap().default_priority.as_deref(), Some("urgent"));
+    }
+
+    #[test]
+    fn test_get_set_unset() {
+        let mut config = Config::default();
+        assert_eq!(config.priority(), Priority::Medium);
+        assert_eq!(config.due_soon_window(), Duration::weeks(1));
+        assert_eq!(config.get("default_priority").unwrap(), None);
+        assert_eq!(Config::default_value("default_priority").unwrap().as_deref(), Some("medium"));
+
+        config.set("default_priority", "H").unwrap();
+        config.set("default_category", " inbox ").unwrap();
+        config.set("due_soon_days", "14").unwrap();
+        config.set("date_dialect", "UK").unwrap();
+        assert_eq!(config.get("default_priority").unwrap().as_deref(), Some("high"));
+        assert_eq!(config.get("default_category").unwrap().as_deref(), Some("inbox"));
+        assert_eq!(config.get("due_soon_days").unwrap().as_deref(), Some("14"));
+        assert_eq!(config.get("date_dialect").unwrap().as_deref(), Some("uk"));
+
+        assert!(config.unset("due_soon_days").unwrap());
+        assert!(!config.unset("due_soon_days").unwrap());
+        assert_eq!(config.due_soon_window(), Duration::weeks(1));
+
+        let error = |key: &str, value: &str| Config::default().set(key, value).unwrap_err().to_string();
+        assert!(error("due_soon_days", "0").contains("expected a number of days"));
+        assert!(error("date_dialect", "fr").contains("expected us or uk"));
+        assert!(error("color", "blue").contains("expected auto, always or never"));
+        assert!(error("colour", "never").starts_with("Unknown config key 'colour'"));
+    }
+
+    #[test]
+    fn test_trash_days_and_category_weights() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("config.toml");
+        fs::write(&path, "trash_days = 0\ncolor = \"never\"\n\n[category_weights]\nWork = 3\nsomeday = -5.5\n").unwrap();
+        let mut config = Config::load_from_file(&path).unwrap();
+        assert_eq!(config.trash_days(), 0);
+        assert_eq!((config.weights().category("work"), config.weights().category("someday")), (3.0, -5.5));
+
+        config.set("color_due", "cyan").unwrap();
+        config.save_to_file(&path).unwrap();
+        assert_eq!(Config::load_from_file(&path).unwrap(), config);
+
+        config.set("category_weights", "home=2, Errands=-1").unwrap();
+        assert_eq!(config.get("category_weights").unwrap().as_deref(), Some("errands=-1,home=2"));
+        assert_eq!(config.weights().category("work"), 0.0);
+        assert!(config.unset("trash_days").unwrap());
+        assert!(config.unset("category_weights").unwrap());
+        assert_eq!((config.trash_days(), config.get("category_weights").unwrap()), (30, None));
+
+        let error = |key: &str, value: &str| Config::default().set(key, value).unwrap_err().to_string();
+        assert!(error("trash_days", "-1").contains("expected a number of days"));
+        assert!(error("category_weights", "work").contains("expected CATEGORY=WEIGHT pairs"));
+        assert!(error("category_weights", "").contains("expected CATEGORY=WEIGHT pairs"));
+    }
+
+    #[test]
+    fn test_theme() {
+        let mut config = Config::default();
+        for key in KEYS.iter().filter(|key| key.starts_with("color_")) {
+            config.set(key, &Config::default_value(key).unwrap().unwrap()).unwrap();
+        }
+        assert_eq!(config.theme(), Theme::default());
+
+        config.set("color_high", "Bright_Magenta").unwrap();
+        config.set("color_category", "#5f87AF").unwrap();
+        assert_eq!(config.get("color_high").unwrap().as_deref(), Some("bright magenta"));
+        assert_eq!(config.theme().priority(&Priority::High), Color::BrightMagenta);
+        assert_eq!(config.theme().category, Color::TrueColor { r: 95, g: 135, b: 175 });
+        assert!(config.unset("color_high").unwrap());
+        assert_eq!(config.theme().high, Color::Red);
+
+        let error = |value: &str| Config::default().set("color_due", value).unwrap_err().to_string();
+        assert!(error("chartreuse").contains("expected a colour such as red"));
+        assert!(error("#5f87a").contains("expected a colour"));
+        let invalid = Config { color_overdue: Some("mauve".to_string()), ..Config::default() };
+        assert!(invalid.validate().unwrap_err().to_string().contains("invalid color_overdue: unknown colour 'mauve'"));
+    }
+}
@@ -0,0 +1,409 @@
+//! Editing tasks as text documents in `$EDITOR`
+//!
+//! A task is written as a few `field: value` lines, a `---` separator and the
+//! description as free text:
+//!
+//! ```text
+//! title: Write report
+//! due: 2024-03-08 14:30
+//! scheduled: 2024-03-04
+//! wait:
+//! category: work
+//! priority: high
+//! tags: q1 writing
+//! repeat: weekly:mon
+//! ---
+//! Outline is in the shared drive.
+//! ```
+//!
+//! Lines starting with `#` above the separator are comments. An empty field
+//! clears it (except the title, which is required). `due`, `scheduled` and
+//! `wait` take any date `--due` accepts, with or without a time, and `repeat`
+//! any `--repeat` rule.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, NaiveTime};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tempfile::{Builder, NamedTempFile};
+
+use crate::models::{normalize_tag, Priority, Recurrence, Task, TaskUpdate};
+
+const SEPARATOR: &str = "---";
+
+const FIELDS: [&str; 8] = ["title", "due", "scheduled", "wait", "category", "priority", "tags", "repeat"];
+
+const HELP: &str = "\
+# Edit the task, save and close the editor. Lines starting with '#' are ignored.
+# Leave a field empty to clear it. The description goes below the '---' line.
+";
+
+/// Write a task as an editable document
+pub fn render(task: &Task) -> String {
+    let tags: Vec<&str> = task.tags.iter().map(String::as_str).collect();
+    let mut document = HELP.to_string();
+    document.push_str(&format!("title: {}\n", task.title));
+    document.push_str(&format!("due: {}\n", task.due_date_string().unwrap_or_default()));
+    document.push_str(&format!("scheduled: {}\n", start_date_string(task.scheduled)));
+    document.push_str(&format!("wait: {}\n", start_date_string(task.wait)));
+    document.push_str(&format!("category: {}\n", task.category.as_deref().unwrap_or_default()));
+    document.push_str(&format!("priority: {}\n", format!("{:?}", task.priority).to_lowercase()));
+    document.push_str(&format!("tags: {}\n", tags.join(" ")));
+    document.push_str(&format!(
+        "repeat: {}\n",
+        task.recurrence.as_ref().map(|rule| rule.to_string()).unwrap_or_default()
+    ));
+    document.push_str(SEPARATOR);
+    document.push('\n');
+    if let Some(description) = &task.description {
+        document.push_str(description);
+        document.push('\n');
+    }
+    document
+}
+
+/// Write a scheduled or wait date, with the time unless it is the start of the day
+fn start_date_string(date: Option<DateTime<Local>>) -> String {
+    match date {
+        Some(date) if date.time() == NaiveTime::MIN => date.format("%Y-%m-%d").to_string(),
+        Some(date) => date.format("%Y-%m-%d %H:%M").to_string(),
+        None => String::new(),
+    }
+}
+
+/// Read an edited document back as the changes it makes to `task`
+///
+/// Only fields that differ from the task are set. Due dates are read with
+/// `parse_due`, which also tells whether the date has a time of day, and
+/// scheduled and wait dates with `parse_start`.
+pub fn parse<F, S>(document: &str, task: &Task, parse_due: F, parse_start: S) -> Result<TaskUpdate>
+where
+    F: Fn(&str) -> Result<(DateTime<Local>, bool)>,
+    S: Fn(&str) -> Result<DateTime<Local>>,
+{
+    let mut lines = document.lines().enumerate();
+    let mut fields: Vec<(usize, &str, &str)> = Vec::new();
+    let mut found_separator = false;
+    for (index, line) in lines.by_ref() {
+        let line = line.trim();
+        if line == SEPARATOR {
+            found_separator = true;
+            break;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| anyhow!("Line {}: expected 'field: value' or the '---' line", index + 1))?;
+        let name = name.trim();
+        if !FIELDS.contains(&name) {
+            return Err(anyhow!("Line {}: unknown field '{}' (expected one of: {})", index + 1, name, FIELDS.join(", ")));
+        }
+        if fields.iter().any(|(_, seen, _)| *seen == name) {
+            return Err(anyhow!("Line {}: field '{}' is given twice", index + 1, name));
+        }
+        fields.push((index + 1, name, value.trim()));
+    }
+    if !found_separator {
+        return Err(anyhow!("Missing the '---' line between the fields and the description"));
+    }
+
+    let body: Vec<&str> = lines.map(|(_, line)| line.trim_end()).collect();
+    let description = body.join("\n").trim_matches('\n').to_string();
+    let description = (!description.trim().is_empty()).then_some(description);
+
+    let mut update = TaskUpdate::new();
+    if description != task.description {
+        update = update.description(description);
+    }
+
+    let title = fields.iter().find(|(_, name, _)| *name == "title").map_or("", |(_, _, value)| value);
+    if title.is_empty() {
+        return Err(anyhow!("The title cannot be empty"));
+    }
+    if title != task.title {
+        update = update.title(title);
+    }
+
+    for &(line, name, value) in &fields {
+        let error = |e: anyhow::Error| anyhow!("Line {}: {}", line, e);
+        match name {
+            "due" if value != task.due_date_string().unwrap_or_default() => {
+                let (due_date, has_time) = if value.is_empty() {
+                    (None, false)
+                } else {
+                    let (due_date, has_time) = parse_due(value)
+                        .map_err(|_| anyhow!("Line {}: invalid due date '{}' (try YYYY-MM-DD or 'next friday 9am')", line, value))?;
+                    (Some(due_date), has_time)
+                };
+                update = update.due_date(due_date).due_has_time(has_time);
+            }
+            "scheduled" | "wait" => {
+                let current = if name == "scheduled" { task.scheduled } else { task.wait };
+                if value == start_date_string(current) {
+                    continue;
+                }
+                let date = if value.is_empty() {
+                    None
+                } else {
+                    Some(parse_start(value).map_err(|_| {
+                        anyhow!("Line {}: invalid {} date '{}' (try YYYY-MM-DD or 'next monday')", line, name, value)
+                    })?)
+                };
+                update = if name == "scheduled" { update.scheduled(date) } else { update.wait(date) };
+            }
+            "category" => {
+                let category = (!value.is_empty()).then(|| value.to_string());
+                if category != task.category {
+                    update = update.category(category);
+                }
+            }
+            "priority" => {
+                let priority = match value.to_ascii_lowercase().as_str() {
+                    "high" | "h" => Priority::High,
+                    "medium" | "m" | "" => Priority::Medium,
+                    "low" | "l" => Priority::Low,
+                    _ => return Err(anyhow!("Line {}: invalid priority '{}' (expected high, medium or low)", line, value)),
+                };
+                if priority != task.priority {
+                    update = update.priority(priority);
+                }
+            }
+            "tags" => {
+                let tags = value
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|tag| !tag.is_empty())
+                    .map(normalize_tag)
+                    .collect::<Result<BTreeSet<String>>>()
+                    .map_err(error)?;
+                if tags != task.tags {
+                    update = update.tags(tags);
+                }
+            }
+            "repeat" => {
+                let recurrence = if value.is_empty() { None } else { Some(value.parse::<Recurrence>().map_err(error)?) };
+                if recurrence != task.recurrence {
+                    update = update.recurrence(recurrence);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(update)
+}
+
+/// Open `document` in the user's editor and return the saved text
+///
+/// The editor is taken from `$VISUAL`, then `$EDITOR`, falling back to `vi`.
+/// It may include arguments, e.g. `code --wait`, quoted as in a shell when
+/// they contain spaces. A path to an existing program is used as it is, even
+/// if it contains spaces.
+pub fn edit(document: &str) -> Result<String> {
+    let editor = ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    let words = if Path::new(editor.trim()).is_file() {
+        vec![editor.trim().to_string()]
+    } else {
+        split_command(&editor)?
+    };
+    let (program, args) = words.split_first().ok_or_else(|| anyhow!("The editor command is empty"))?;
+
+    // The file is removed when it goes out of scope
+    let file = write_temp_file(Builder::new().prefix("rtodo-task-").suffix(".txt"), document)?;
+    run_editor(program, args, file.path())
+}
+
+/// Keep a document that couldn't be read back, returning where it was saved
+pub fn keep_rejected(id: u32, document: &str) -> Result<PathBuf> {
+    let file = write_temp_file(Builder::new().prefix(&format!("rtodo-task-{}-", id)).suffix(".rejected.txt"), document)?;
+    let (_, path) = file.keep().map_err(|e| anyhow!("Failed to keep {}: {}", e.file.path().display(), e.error))?;
+    Ok(path)
+}
+
+/// Write `document` to a new file in the temp directory that only the current
+/// user can read; the name is random and the file must not exist yet
+fn write_temp_file(builder: &mut Builder, document: &str) -> Result<NamedTempFile> {
+    let mut file = builder.tempfile().map_err(|e| anyhow!("Failed to create a temporary file: {}", e))?;
+    file.write_all(document.as_bytes())
+        .and_then(|()| file.flush())
+        .map_err(|e| anyhow!("Failed to write {}: {}", file.path().display(), e))?;
+    Ok(file)
+}
+
+/// Split a command into words like a shell: `'...'` and `"..."` quote spaces,
+/// and a backslash escapes the next character outside single quotes
+fn split_command(command: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote: Option<char> = None;
+    let mut chars = command.chars();
+    while let Some(ch) = chars.next() {
+        match (quote, ch) {
+            (Some(open), ch) if ch == open => quote = None,
+            (Some('\''), ch) => word.get_or_insert_with(String::new).push(ch),
+            (_, '\\') => {
+                let escaped = chars.next().ok_or_else(|| anyhow!("Editor command '{}' ends with a backslash", command))?;
+                word.get_or_insert_with(String::new).push(escaped);
+            }
+            (Some(_), ch) => word.get_or_insert_with(String::new).push(ch),
+            (None, '\'' | '"') => {
+                quote = Some(ch);
+                word.get_or_insert_with(String::new);
+            }
+            (None, ch) if ch.is_whitespace() => words.extend(word.take()),
+            (None, ch) => word.get_or_insert_with(String::new).push(ch),
+        }
+    }
+    if quote.is_some() {
+        return Err(anyhow!("Editor command '{}' has an unclosed quote", command));
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+fn run_editor(program: &str, args: &[String], path: &Path) -> Result<String> {
+    let status = Command::new(program)
+        .args(args)
+        .arg(path)
+        .status()
+        .map_err(|e| anyhow!("Failed to start editor '{}': {}", program, e))?;
+    if !status.success() {
+        return Err(anyhow!("Editor '{}' exited with {}; nothing was changed", program, status));
+    }
+    fs::read_to_string(path).map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, NaiveDateTime, TimeZone};
+
+    fn parse_due(text: &str) -> Result<(DateTime<Local>, bool)> {
+        if let Ok(date) = NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M") {
+            return Ok((Local.from_local_datetime(&date).unwrap(), true));
+        }
+        let date = NaiveDate::parse_from_str(text, "%Y-%m-%d")?;
+        Ok((Local.from_local_datetime(&date.and_hms_opt(23, 59, 59).unwrap()).unwrap(), false))
+    }
+
+    fn parse_start(text: &str) -> Result<DateTime<Local>> {
+        let (date, has_time) = parse_due(text)?;
+        Ok(if has_time { date } else { date.date_naive().and_hms_opt(0, 0, 0).unwrap().and_local_timezone(Local).unwrap() })
+    }
+
+    fn sample_task() -> Task {
+        let mut task = Task::with_details(
+            7,
+            "Write report".to_string(),
+            Some("Outline is in the shared drive.\n\n# Sections\n- intro".to_string()),
+            Some(Local.with_ymd_and_hms(2024, 3, 8, 14, 30, 0).unwrap()),
+            Some("work".to_string()),
+            Priority::High,
+        );
+        task.tags = ["q1", "writing"].map(String::from).into();
+        task.recurrence = Some("weekly:mon".parse().unwrap());
+        task.scheduled = Local.with_ymd_and_hms(2024, 3, 4, 0, 0, 0).earliest();
+        task
+    }
+
+    #[test]
+    fn test_unchanged_document_has_no_changes() {
+        let task = sample_task();
+        let document = render(&task);
+        assert!(document.contains("title: Write report\ndue: 2024-03-08\nscheduled: 2024-03-04\nwait: \ncategory: work\n"));
+        assert!(document.contains("tags: q1 writing\nrepeat: weekly:mon\n---\nOutline"));
+
+        // The due time survives because the date was not touched
+        let update = parse(&document, &task, parse_due, parse_start).unwrap();
+        assert!(update.title.is_none() && update.description.is_none() && update.due_date.is_none());
+        assert!(update.category.is_none() && update.priority.is_none() && update.tags.is_none());
+        assert!(update.recurrence.is_none() && update.scheduled.is_none() && update.wait.is_none());
+    }
+
+    #[test]
+    fn test_parse_changes() {
+        let task = sample_task();
+        let document = "title:  Write final report \n\
+                        # a comment\n\
+                        due: 2024-03-15\n\
+                        scheduled:\n\
+                        wait: 2024-03-11 09:00\n\
+                        category:\n\
+                        priority: low\n\
+                        tags: +q1, review\n\
+                        repeat:\n\
+                        ---\n\n\
+                        First line\n\
+                        Second line\n\n";
+        let update = parse(document, &task, parse_due, parse_start).unwrap();
+        assert_eq!(update.title.as_deref(), Some("Write final report"));
+        assert_eq!(update.due_date, Some(Some(parse_due("2024-03-15").unwrap().0)));
+        assert!(!update.due_has_time);
+        assert_eq!(update.scheduled, Some(None));
+        assert_eq!(update.wait, Some(Local.with_ymd_and_hms(2024, 3, 11, 9, 0, 0).earliest()));
+        assert_eq!(update.category, Some(None));
+        assert_eq!(update.priority, Some(Priority::Low));
+        assert_eq!(update.tags, Some(["q1", "review"].map(String::from).into()));
+        assert_eq!(update.recurrence, Some(None));
+        assert_eq!(update.description, Some(Some("First line\nSecond line".to_string())));
+
+        let document = render(&task).replace("due: 2024-03-08", "due: 2024-03-08 09:15");
+        let update = parse(&document, &task, parse_due, parse_start).unwrap();
+        assert_eq!(update.due_date, Some(Local.with_ymd_and_hms(2024, 3, 8, 9, 15, 0).earliest()));
+        assert!(update.due_has_time);
+
+        // Fields left out are unchanged
+        let update = parse("title: Write report\n---\n", &task, parse_due, parse_start).unwrap();
+        assert_eq!(update.description, Some(None));
+        assert!(update.due_date.is_none() && update.priority.is_none());
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let task = sample_task();
+        let error = |document: &str| parse(document, &task, parse_due, parse_start).unwrap_err().to_string();
+        assert!(error("title:\n---\n").contains("title cannot be empty"));
+        assert!(error("title: A\n").contains("Missing the '---' line"));
+        assert!(error("title: A\nowner: me\n---\n").starts_with("Line 2: unknown field 'owner'"));
+        assert!(error("title: A\ntitle: B\n---\n").contains("given twice"));
+        assert!(error("title: A\ndue: someday\n---\n").starts_with("Line 2: invalid due date 'someday'"));
+        assert!(error("title: A\nwait: someday\n---\n").starts_with("Line 2: invalid wait date 'someday'"));
+        assert!(error("title: A\npriority: urgent\n---\n").contains("invalid priority 'urgent'"));
+        assert!(error("title: A\nrepeat: hourly\n---\n").starts_with("Line 2:"));
+        assert!(error("title: A\njust text\n---\n").starts_with("Line 2: expected 'field: value'"));
+    }
+
+    #[test]
+    fn test_keep_rejected_uses_private_unique_files() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let first = keep_rejected(7, "title: A\n").unwrap();
+        let second = keep_rejected(7, "title: B\n").unwrap();
+        assert_ne!(first, second);
+        assert_eq!(fs::read_to_string(&first).unwrap(), "title: A\n");
+        assert_eq!(fs::metadata(&first).unwrap().permissions().mode() & 0o777, 0o600);
+        fs::remove_file(first).unwrap();
+        fs::remove_file(second).unwrap();
+    }
+
+    #[test]
+    fn test_split_command() {
+        let split = |command: &str| split_command(command).unwrap();
+        assert_eq!(split("vi"), ["vi"]);
+        assert_eq!(split("  code --wait "), ["code", "--wait"]);
+        assert_eq!(
+            split("\"/Applications/Sublime Text.app/bin/subl\" -w"),
+            ["/Applications/Sublime Text.app/bin/subl", "-w"]
+        );
+        assert_eq!(split("emacs -nw --eval '(setq x \"y\")'"), ["emacs", "-nw", "--eval", "(setq x \"y\")"]);
+        assert_eq!(split("/opt/my\\ editor/bin/ed ''"), ["/opt/my editor/bin/ed", ""]);
+        assert!(split_command("vim 'unclosed").is_err());
+    }
+}
@@ -0,0 +1,299 @@
+//! CSV with a header row, for spreadsheets
+//!
+//! Exports have one column per task field:
+//! `id,title,description,completed,priority,category,tags,due_date,created_at,completed_at,recurrence,parent_id,depends_on`.
+//! Dates are local times written as `YYYY-MM-DD HH:MM:SS`, tags and
+//! dependencies are separated by spaces, and recurrence uses the `--repeat`
+//! syntax.
+//!
+//! Imports read the columns named after the fields they fill (`title`,
+//! `description`, `completed`, `priority`, `category`, `tags`, `due_date` or
+//! `due`, `created_at`, `completed_at`, `recurrence`), matched without regard
+//! to case. Columns with other names are ignored unless mapped to a field with
+//! `FIELD=HEADER`, e.g. `title=Summary`. Only `title` is required. IDs,
+//! subtasks and dependencies are not imported; imported tasks get new IDs.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use std::collections::HashMap;
+
+use super::{end_of_day, has_time_of_day, start_of_day, Imported};
+use crate::models::{normalize_tag, Priority, Task};
+
+/// Fields that can be filled from a column, with the names (and header names) they go by
+const FIELDS: &[(&str, &[&str])] = &[
+    ("title", &["title"]),
+    ("description", &["description"]),
+    ("completed", &["completed"]),
+    ("priority", &["priority"]),
+    ("category", &["category"]),
+    ("tags", &["tags"]),
+    ("due_date", &["due_date", "due"]),
+    ("created_at", &["created_at"]),
+    ("completed_at", &["completed_at"]),
+    ("recurrence", &["recurrence"]),
+];
+
+const HEADER: [&str; 13] = [
+    "id",
+    "title",
+    "description",
+    "completed",
+    "priority",
+    "category",
+    "tags",
+    "due_date",
+    "created_at",
+    "completed_at",
+    "recurrence",
+    "parent_id",
+    "depends_on",
+];
+
+/// Render tasks as CSV with a header row
+pub fn export(tasks: &[&Task]) -> Result<String> {
+    let mut writer = ::csv::Writer::from_writer(Vec::new());
+    writer.write_record(HEADER)?;
+
+    let date = |date: Option<DateTime<Local>>| {
+        date.map(|date| date.format("%Y-%m-%d %H:%M:%S").to_string()).unwrap_or_default()
+    };
+    for task in tasks {
+        writer.write_record([
+            task.id.to_string(),
+            task.title.clone(),
+            task.description.clone().unwrap_or_default(),
+            task.completed.to_string(),
+            match task.priority {
+                Priority::Low => "low",
+                Priority::Medium => "medium",
+                Priority::High => "high",
+            }
+            .to_string(),
+            task.category.clone().unwrap_or_default(),
+            task.tags.iter().cloned().collect::<Vec<_>>().join(" "),
+            date(task.due_date),
+            date(Some(task.created_at)),
+            date(task.completed_at),
+            task.recurrence.as_ref().map(|rule| rule.to_string()).unwrap_or_default(),
+            task.parent_id.map(|id| id.to_string()).unwrap_or_default(),
+            task.depends_on.iter().map(|id| id.to_string()).collect::<Vec<_>>().join(" "),
+        ])?;
+    }
+
+    let bytes = writer.into_inner().map_err(|e| anyhow!("Failed to write CSV: {}", e))?;
+    Ok(String::from_utf8(bytes)?)
+}
+
+/// Parse CSV rows into new tasks
+///
+/// `columns` maps fields to headers as `FIELD=HEADER`, overriding the default names.
+pub fn import(contents: &str, columns: &[String]) -> Result<Imported> {
+    let mut reader = ::csv::ReaderBuilder::new()
+        .flexible(true)
+        .trim(::csv::Trim::All)
+        .from_reader(contents.as_bytes());
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|e| anyhow!("Failed to read CSV header: {}", e))?
+        .iter()
+        .map(str::to_string)
+        .collect();
+    let positions = column_positions(&headers, columns)?;
+
+    let mut imported = Imported::default();
+    for record in reader.records() {
+        let record = record.map_err(|e| anyhow!("Invalid CSV: {}", e))?;
+        let line = record.position().map_or(0, |position| position.line());
+        let value = |field: &str| {
+            positions
+                .get(field)
+                .and_then(|&index| record.get(index))
+                .filter(|value| !value.is_empty())
+        };
+        if record.iter().all(str::is_empty) {
+            continue;
+        }
+
+        let title = value("title").ok_or_else(|| anyhow!("Line {}: task has no title", line))?;
+        let mut task = Task::new(0, title.split_whitespace().collect::<Vec<_>>().join(" "));
+        task.description = value("description").map(str::to_string);
+        task.category = value("category").map(str::to_string);
+
+        if let Some(priority) = value("priority") {
+            task.priority = match priority.to_ascii_lowercase().as_str() {
+                "high" | "h" => Priority::High,
+                "medium" | "m" => Priority::Medium,
+                "low" | "l" => Priority::Low,
+                _ => return Err(anyhow!("Line {}: invalid priority '{}' (expected high, medium or low)", line, priority)),
+            };
+        }
+
+        if let Some(completed) = value("completed") {
+            task.completed = match completed.to_ascii_lowercase().as_str() {
+                "true" | "yes" | "y" | "x" | "1" | "done" => true,
+                "false" | "no" | "n" | "0" => false,
+                _ => return Err(anyhow!("Line {}: invalid completed value '{}' (expected true or false)", line, completed)),
+            };
+        }
+
+        for tag in value("tags").into_iter().flat_map(|tags| tags.split(|c: char| c == ',' || c.is_whitespace())) {
+            if !tag.is_empty() {
+                task.tags.insert(normalize_tag(tag).map_err(|e| anyhow!("Line {}: {}", line, e))?);
+            }
+        }
+
+        let date = |field: &str, date_only: fn(NaiveDate) -> DateTime<Local>| -> Result<Option<DateTime<Local>>> {
+            value(field)
+                .map(|text| {
+                    parse_date(text, date_only).ok_or_else(|| {
+                        anyhow!("Line {}: invalid {} '{}' (expected YYYY-MM-DD [HH:MM[:SS]])", line, field, text)
+                    })
+                })
+                .transpose()
+        };
+        task.due_date = date("due_date", end_of_day)?;
+        task.due_has_time = has_time_of_day(task.due_date);
+        if let Some(created_at) = date("created_at", start_of_day)? {
+            task.created_at = created_at;
+        }
+        if task.completed {
+            task.completed_at = date("completed_at", start_of_day)?;
+        }
+
+        if let Some(rule) = value("recurrence") {
+            match rule.parse() {
+                Ok(recurrence) if !task.completed => task.recurrence = Some(recurrence),
+                Ok(_) => {}
+                Err(e) => imported.warnings.push(format!("Line {}: {}; recurrence ignored", line, e)),
+            }
+        }
+
+        imported.tasks.push(task);
+    }
+    Ok(imported)
+}
+
+/// Find the column of each field, applying `FIELD=HEADER` mappings
+fn column_positions(headers: &[String], columns: &[String]) -> Result<HashMap<&'static str, usize>> {
+    let find = |name: &str| headers.iter().position(|header| header.eq_ignore_ascii_case(name));
+
+    let mut positions = HashMap::new();
+    for (field, names) in FIELDS {
+        if let Some(index) = names.iter().find_map(|name| find(name)) {
+            positions.insert(*field, index);
+        }
+    }
+
+    for mapping in columns {
+        let (field, header) = mapping
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Invalid column mapping '{}': expected FIELD=HEADER", mapping))?;
+        let field = FIELDS
+            .iter()
+            .find(|(_, names)| names.iter().any(|name| name.eq_ignore_ascii_case(field.trim())))
+            .map(|(name, _)| *name)
+            .ok_or_else(|| {
+                let names: Vec<&str> = FIELDS.iter().map(|(name, _)| *name).collect();
+                anyhow!("Unknown field '{}' in column mapping (expected one of: {})", field, names.join(", "))
+            })?;
+        let index = find(header.trim()).ok_or_else(|| {
+            anyhow!("Column '{}' not found in the CSV header ({})", header.trim(), headers.join(", "))
+        })?;
+        positions.insert(field, index);
+    }
+
+    if !positions.contains_key("title") {
+        return Err(anyhow!(
+            "No title column in the CSV header ({}). Map one with --column title=HEADER",
+            headers.join(", ")
+        ));
+    }
+    Ok(positions)
+}
+
+fn parse_date(text: &str, date_only: fn(NaiveDate) -> DateTime<Local>) -> Option<DateTime<Local>> {
+    if let Ok(date) = NaiveDate::parse_from_str(text, "%Y-%m-%d") {
+        return Some(date_only(date));
+    }
+    ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M:%S"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(text, format).ok())
+        .and_then(|naive| Local.from_local_datetime(&naive).earliest())
+        .or_else(|| DateTime::parse_from_rfc3339(text).ok().map(|date| date.with_timezone(&Local)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Recurrence, TaskUpdate, TodoList};
+
+    #[test]
+    fn test_export_quotes_and_round_trips() {
+        let mut todo_list = TodoList::new();
+        let due = Local.with_ymd_and_hms(2024, 3, 8, 23, 59, 59).unwrap();
+        let id = todo_list.add_task_with_details(
+            "Write report, part \"one\"".to_string(),
+            Some("Two\nlines".to_string()),
+            Some(due),
+            Some("work".to_string()),
+            Priority::High,
+        );
+        todo_list
+            .update_task(id, TaskUpdate::new().tags(["q1", "writing"]).recurrence(Some(Recurrence::Daily)))
+            .unwrap();
+        let second = todo_list.add_task("Send report".to_string());
+        todo_list.add_dependency(second, id).unwrap();
+        todo_list.mark_complete(second).unwrap();
+
+        let tasks: Vec<&Task> = todo_list.get_all_tasks().iter().collect();
+        let exported = export(&tasks).unwrap();
+        assert!(exported.starts_with("id,title,description,completed,priority,category,tags,due_date,"));
+        assert!(exported.contains("\"Write report, part \"\"one\"\"\""));
+        assert!(exported.contains(",q1 writing,2024-03-08 23:59:59,"));
+
+        let imported = import(&exported, &[]).unwrap();
+        assert!(imported.warnings.is_empty());
+        for (before, after) in tasks.iter().zip(&imported.tasks) {
+            assert_eq!(after.title, before.title);
+            assert_eq!(after.description, before.description);
+            assert_eq!(after.completed, before.completed);
+            assert_eq!(after.priority, before.priority);
+            assert_eq!(after.category, before.category);
+            assert_eq!(after.tags, before.tags);
+            assert_eq!(after.due_date, before.due_date);
+            assert_eq!(after.recurrence, before.recurrence);
+            assert_eq!(after.created_at.timestamp(), before.created_at.timestamp());
+            assert_eq!(after.completed_at.map(|d| d.timestamp()), before.completed_at.map(|d| d.timestamp()));
+        }
+    }
+
+    #[test]
+    fn test_import_with_column_mapping() {
+        let contents = "Summary,Owner,Deadline,Prio,Labels\n\
+            Order laptops,Sam,2024-06-01,H,\"hardware, q2\"\n\
+            ,,,,\n\
+            Book venue,Alex,2024-06-15 14:30,low,\n";
+
+        let error = import(contents, &[]).unwrap_err();
+        assert!(error.to_string().contains("--column title=HEADER"));
+
+        let columns = ["title=Summary", "due_date=deadline", "priority=Prio", "tags=Labels"].map(String::from);
+        let imported = import(contents, &columns).unwrap();
+        assert_eq!(imported.tasks.len(), 2);
+
+        let task = &imported.tasks[0];
+        assert_eq!(task.title, "Order laptops");
+        assert_eq!(task.priority, Priority::High);
+        assert_eq!(task.due_date, Some(end_of_day(NaiveDate::from_ymd_opt(2024, 6, 1).unwrap())));
+        assert!(task.has_tag("hardware") && task.has_tag("q2"));
+
+        let task = &imported.tasks[1];
+        assert_eq!(task.priority, Priority::Low);
+        assert_eq!(task.due_date, Local.with_ymd_and_hms(2024, 6, 15, 14, 30, 0).earliest());
+
+        assert!(import(contents, &["owner=Owner".to_string()]).is_err());
+        assert!(import(contents, &["title=Missing".to_string()]).is_err());
+        assert!(import("title,due\nTask,next week\n", &[]).unwrap_err().to_string().contains("Line 2"));
+    }
+}
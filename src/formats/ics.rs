@@ -0,0 +1,661 @@
+//! iCalendar task lists (RFC 5545 `VTODO` components)
+//!
+//! Each task becomes one `VTODO`:
+//!
+//! | Task field              | Property                                           |
+//! |-------------------------|----------------------------------------------------|
+//! | title, description      | `SUMMARY`, `DESCRIPTION`                           |
+//! | due date                | `DUE` (UTC)                                        |
+//! | priority                | `PRIORITY` 1 (high), 5 (medium), 9 (low)           |
+//! | category, tags          | `CATEGORIES`, category first                       |
+//! | completed, completed_at | `STATUS:COMPLETED`/`NEEDS-ACTION`, `COMPLETED`     |
+//! | created_at              | `CREATED`                                          |
+//! | modified_at, revision   | `LAST-MODIFIED`, `SEQUENCE`                        |
+//! | daily/weekly/monthly    | `RRULE`                                            |
+//!
+//! Imported tasks keep their UID; other tasks, including each new instance of
+//! a recurring task, get one derived from their ID and creation time. Either
+//! way exporting the same list twice gives the same UIDs, so calendar clients
+//! update tasks in place rather than duplicating them. `DTSTAMP` is the export
+//! time, and `SEQUENCE` counts the changes made to a task, so clients can tell
+//! an edited task from one they already have. Importing a file skips tasks
+//! whose UID belongs to a task already in the list.
+//!
+//! On import, `PRIORITY` 1-4 is high, 5 (or none) medium and 6-9 low; the first
+//! `CATEGORIES` value is the category and the rest are tags (spaces become
+//! `_`). Times with a `TZID` are read as local time. After-completion
+//! recurrence, subtasks and dependencies are not exported.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc, Weekday};
+use std::collections::HashSet;
+
+use super::{end_of_day, has_time_of_day, start_of_day, Imported};
+use crate::models::{Priority, Recurrence, Task, TodoList};
+
+/// Stable identifier for a task, the same on every export
+pub fn uid(task: &Task) -> String {
+    task.uuid
+        .clone()
+        .unwrap_or_else(|| format!("{}-{}@rtodo", task.id, task.created_at.timestamp()))
+}
+
+/// Render tasks as an iCalendar file with one `VTODO` per task
+pub fn export(tasks: &[&Task]) -> String {
+    export_at(tasks, Local::now())
+}
+
+/// Render tasks as `export` does, stamping them with `now` as the export time
+fn export_at(tasks: &[&Task], now: DateTime<Local>) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:-//rtodo//rtodo {}//EN", env!("CARGO_PKG_VERSION")),
+    ];
+
+    for task in tasks {
+        lines.push("BEGIN:VTODO".to_string());
+        lines.push(format!("UID:{}", uid(task)));
+        lines.push(format!("DTSTAMP:{}", format_datetime(now)));
+        lines.push(format!("CREATED:{}", format_datetime(task.created_at)));
+        if let Some(modified_at) = task.modified_at {
+            lines.push(format!("LAST-MODIFIED:{}", format_datetime(modified_at)));
+        }
+        lines.push(format!("SEQUENCE:{}", task.revision));
+        lines.push(format!("SUMMARY:{}", escape_text(&task.title)));
+        if let Some(description) = &task.description {
+            lines.push(format!("DESCRIPTION:{}", escape_text(description)));
+        }
+        if let Some(due_date) = task.due_date {
+            lines.push(format!("DUE:{}", format_datetime(due_date)));
+        }
+        lines.push(format!(
+            "PRIORITY:{}",
+            match task.priority {
+                Priority::High => 1,
+                Priority::Medium => 5,
+                Priority::Low => 9,
+            }
+        ));
+
+        let categories: Vec<String> = task
+            .category
+            .iter()
+            .chain(&task.tags)
+            .map(|value| escape_text(value))
+            .collect();
+        if !categories.is_empty() {
+            lines.push(format!("CATEGORIES:{}", categories.join(",")));
+        }
+
+        if task.completed {
+            lines.push("STATUS:COMPLETED".to_string());
+            if let Some(completed_at) = task.completed_at {
+                lines.push(format!("COMPLETED:{}", format_datetime(completed_at)));
+            }
+        } else {
+            lines.push("STATUS:NEEDS-ACTION".to_string());
+        }
+
+        if let Some(rule) = task.recurrence.as_ref().and_then(format_rrule) {
+            lines.push(format!("RRULE:{}", rule));
+        }
+        lines.push("END:VTODO".to_string());
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+/// Parse the `VTODO` components of an iCalendar file into new tasks
+///
+/// Other components (events, alarms, time zones) are ignored.
+pub fn import(contents: &str, existing: &TodoList) -> Result<Imported> {
+    let known: HashSet<String> = existing.get_all_tasks().iter().map(uid).collect();
+    let mut imported = Imported::default();
+    let mut todo: Option<(usize, Vec<Property>)> = None;
+    let mut nested = 0;
+    let mut saw_tzid = false;
+
+    for (line_number, line) in unfold(contents) {
+        let property = parse_property(&line)
+            .ok_or_else(|| anyhow!("Line {}: invalid content line '{}'", line_number, line))?;
+
+        match (property.name.as_str(), property.value.to_ascii_uppercase().as_str()) {
+            ("BEGIN", "VTODO") if todo.is_none() => todo = Some((line_number, Vec::new())),
+            ("BEGIN", _) if todo.is_some() => nested += 1,
+            ("END", "VTODO") if nested == 0 => {
+                let Some((start, properties)) = todo.take() else {
+                    return Err(anyhow!("Line {}: END:VTODO without BEGIN:VTODO", line_number));
+                };
+                saw_tzid |= properties.iter().any(|property| property.param("TZID").is_some());
+                if let Some(task) = parse_todo(&properties, start, &known, &mut imported.warnings)? {
+                    imported.tasks.push(task);
+                }
+            }
+            ("END", _) if todo.is_some() => nested -= 1,
+            _ => {
+                if let Some((_, properties)) = todo.as_mut() {
+                    if nested == 0 {
+                        properties.push(property);
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some((start, _)) = todo {
+        return Err(anyhow!("Line {}: VTODO is not closed with END:VTODO", start));
+    }
+    if saw_tzid {
+        imported.warnings.push("Times with a TZID were read as local time".to_string());
+    }
+    Ok(imported)
+}
+
+/// A content line: `NAME;PARAM=value:VALUE`
+#[derive(Debug)]
+struct Property {
+    name: String,
+    params: Vec<(String, String)>,
+    value: String,
+}
+
+impl Property {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Join folded lines, keeping the number of the line each one starts on
+fn unfold(contents: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some((_, previous))) => previous.push_str(continuation),
+            _ if line.is_empty() => {}
+            _ => lines.push((index + 1, line.to_string())),
+        }
+    }
+    lines
+}
+
+/// Split lines longer than 75 octets, as RFC 5545 requires
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut width = 0;
+    for ch in line.chars() {
+        if width + ch.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(ch);
+        width += ch.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+fn parse_property(line: &str) -> Option<Property> {
+    // Split on `;` and the first `:` outside quoted parameter values
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut value_start = None;
+    for (index, ch) in line.char_indices() {
+        match ch {
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                parts.push(&line[start..index]);
+                start = index + 1;
+            }
+            ':' if !quoted => {
+                parts.push(&line[start..index]);
+                value_start = Some(index + 1);
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    let value = line[value_start?..].to_string();
+    let mut parts = parts.into_iter();
+    let name = parts.next().filter(|name| !name.is_empty())?.to_ascii_uppercase();
+    let params = parts
+        .map(|param| {
+            let (key, value) = param.split_once('=').unwrap_or((param, ""));
+            (key.to_string(), value.trim_matches('"').to_string())
+        })
+        .collect();
+    Some(Property { name, params, value })
+}
+
+fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => {}
+            _ => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+fn unescape_text(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            unescaped.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+    unescaped
+}
+
+/// Split a list value on unescaped commas
+fn split_list(value: &str) -> Vec<String> {
+    let mut items = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+    for (index, ch) in value.char_indices() {
+        match ch {
+            '\\' if !escaped => escaped = true,
+            ',' if !escaped => {
+                items.push(unescape_text(&value[start..index]));
+                start = index + 1;
+            }
+            _ => escaped = false,
+        }
+    }
+    items.push(unescape_text(&value[start..]));
+    items
+}
+
+fn format_datetime(date: DateTime<Local>) -> String {
+    date.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Parse a DATE or DATE-TIME value; dates alone are turned into a time by `date_only`
+fn parse_datetime(property: &Property, date_only: fn(NaiveDate) -> DateTime<Local>) -> Option<DateTime<Local>> {
+    let value = property.value.trim();
+    if property.param("VALUE") == Some("DATE") || !value.contains('T') {
+        return NaiveDate::parse_from_str(value, "%Y%m%d").ok().map(date_only);
+    }
+    if let Some(utc) = value.strip_suffix('Z') {
+        let naive = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+        return Some(Utc.from_utc_datetime(&naive).with_timezone(&Local));
+    }
+    let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    Local.from_local_datetime(&naive).earliest()
+}
+
+fn weekday_code(weekday: &Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+fn format_rrule(recurrence: &Recurrence) -> Option<String> {
+    match recurrence {
+        Recurrence::Daily => Some("FREQ=DAILY".to_string()),
+        Recurrence::Weekly { weekdays } if weekdays.is_empty() => Some("FREQ=WEEKLY".to_string()),
+        Recurrence::Weekly { weekdays } => Some(format!(
+            "FREQ=WEEKLY;BYDAY={}",
+            weekdays.iter().map(weekday_code).collect::<Vec<_>>().join(",")
+        )),
+        Recurrence::Monthly { day } => Some(format!("FREQ=MONTHLY;BYMONTHDAY={}", day)),
+        Recurrence::AfterCompletion { .. } => None,
+    }
+}
+
+/// Recurrence for the RRULE forms rtodo supports: no INTERVAL, COUNT or UNTIL
+fn parse_rrule(value: &str, due_date: Option<DateTime<Local>>) -> Option<Recurrence> {
+    let mut freq = None;
+    let mut weekdays = Vec::new();
+    let mut month_day = None;
+    for part in value.split(';') {
+        let (key, value) = part.split_once('=')?;
+        match key.to_ascii_uppercase().as_str() {
+            "FREQ" => freq = Some(value.to_ascii_uppercase()),
+            "INTERVAL" if value == "1" => {}
+            "WKST" => {}
+            "BYDAY" => {
+                for code in value.split(',') {
+                    let weekday = [
+                        Weekday::Mon,
+                        Weekday::Tue,
+                        Weekday::Wed,
+                        Weekday::Thu,
+                        Weekday::Fri,
+                        Weekday::Sat,
+                        Weekday::Sun,
+                    ]
+                    .into_iter()
+                    .find(|weekday| code.eq_ignore_ascii_case(weekday_code(weekday)))?;
+                    weekdays.push(weekday);
+                }
+            }
+            "BYMONTHDAY" => month_day = Some(value.parse::<u32>().ok().filter(|day| (1..=31).contains(day))?),
+            _ => return None,
+        }
+    }
+
+    match freq?.as_str() {
+        "DAILY" if weekdays.is_empty() && month_day.is_none() => Some(Recurrence::Daily),
+        "WEEKLY" if month_day.is_none() => {
+            weekdays.sort_by_key(|weekday| weekday.num_days_from_monday());
+            weekdays.dedup();
+            Some(Recurrence::Weekly { weekdays })
+        }
+        "MONTHLY" if weekdays.is_empty() => {
+            use chrono::Datelike;
+            let day = month_day.or_else(|| due_date.map(|due| due.day())).unwrap_or(1);
+            Some(Recurrence::Monthly { day })
+        }
+        _ => None,
+    }
+}
+
+/// Build a task from the properties of one VTODO, or `None` if it is skipped
+fn parse_todo(
+    properties: &[Property],
+    line_number: usize,
+    known: &HashSet<String>,
+    warnings: &mut Vec<String>,
+) -> Result<Option<Task>> {
+    let mut task = Task::new(0, String::new());
+    let mut rrule = None;
+    let mut status = None;
+
+    for property in properties {
+        let value = property.value.as_str();
+        match property.name.as_str() {
+            "UID" if known.contains(value) => {
+                warnings.push(format!("Line {}: task {} is already in the list, skipped", line_number, value));
+                return Ok(None);
+            }
+            "UID" => task.uuid = Some(value.to_string()),
+            "SUMMARY" => task.title = unescape_text(value).split_whitespace().collect::<Vec<_>>().join(" "),
+            "DESCRIPTION" => task.description = Some(unescape_text(value)).filter(|text| !text.trim().is_empty()),
+            "DUE" => {
+                task.due_date = Some(
+                    parse_datetime(property, end_of_day)
+                        .ok_or_else(|| anyhow!("Line {}: invalid DUE '{}'", line_number, value))?,
+                );
+                task.due_has_time = has_time_of_day(task.due_date);
+            }
+            "CREATED" => {
+                task.created_at = parse_datetime(property, start_of_day)
+                    .ok_or_else(|| anyhow!("Line {}: invalid CREATED '{}'", line_number, value))?;
+            }
+            "LAST-MODIFIED" => {
+                task.modified_at = Some(
+                    parse_datetime(property, start_of_day)
+                        .ok_or_else(|| anyhow!("Line {}: invalid LAST-MODIFIED '{}'", line_number, value))?,
+                );
+            }
+            "SEQUENCE" => {
+                task.revision = value
+                    .trim()
+                    .parse()
+                    .map_err(|_| anyhow!("Line {}: invalid SEQUENCE '{}'", line_number, value))?;
+            }
+            "COMPLETED" => {
+                task.completed = true;
+                task.completed_at = Some(
+                    parse_datetime(property, start_of_day)
+                        .ok_or_else(|| anyhow!("Line {}: invalid COMPLETED '{}'", line_number, value))?,
+                );
+            }
+            "PRIORITY" => {
+                task.priority = match value.trim().parse::<u8>() {
+                    Ok(1..=4) => Priority::High,
+                    Ok(0) | Ok(5) => Priority::Medium,
+                    Ok(6..=9) => Priority::Low,
+                    _ => return Err(anyhow!("Line {}: invalid PRIORITY '{}'", line_number, value)),
+                };
+            }
+            "CATEGORIES" => {
+                for item in split_list(value) {
+                    let item = item.trim();
+                    if item.is_empty() {
+                        continue;
+                    }
+                    if task.category.is_none() {
+                        task.category = Some(item.to_string());
+                    } else {
+                        // Tags can't contain spaces
+                        task.tags.insert(item.split_whitespace().collect::<Vec<_>>().join("_"));
+                    }
+                }
+            }
+            "STATUS" => status = Some(value.to_ascii_uppercase()),
+            "RRULE" => rrule = Some(value),
+            _ => {}
+        }
+    }
+
+    if task.title.is_empty() {
+        return Err(anyhow!("Line {}: VTODO has no SUMMARY", line_number));
+    }
+
+    match status.as_deref() {
+        Some("COMPLETED") => task.completed = true,
+        Some("CANCELLED") => {
+            warnings.push(format!("Line {}: cancelled task '{}' skipped", line_number, task.title));
+            return Ok(None);
+        }
+        _ => {}
+    }
+
+    if let Some(rule) = rrule {
+        task.recurrence = parse_rrule(rule, task.due_date);
+        if task.recurrence.is_none() {
+            warnings.push(format!("Line {}: unsupported RRULE '{}' ignored", line_number, rule));
+        }
+    }
+
+    Ok(Some(task))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TaskUpdate;
+
+    fn sample_list() -> TodoList {
+        let mut todo_list = TodoList::new();
+        let due = Local.with_ymd_and_hms(2024, 5, 3, 17, 30, 0).unwrap();
+        let id = todo_list.add_task_with_details(
+            "Call the plumber; ask about the boiler, too".to_string(),
+            Some("Number is on the fridge\nBefore 6pm".to_string()),
+            Some(due),
+            Some("home".to_string()),
+            Priority::High,
+        );
+        todo_list
+            .update_task(id, TaskUpdate::new().tags(["house", "calls"]).recurrence(Some("weekly:mon,thu".parse().unwrap())))
+            .unwrap();
+        todo_list.add_task_with_details("Renew passport".to_string(), None, None, None, Priority::Low);
+        let id = todo_list.add_task_with_details("Pay rent".to_string(), None, Some(due), Some("bills".to_string()), Priority::Medium);
+        todo_list
+            .update_task(id, TaskUpdate::new().recurrence(Some(Recurrence::Monthly { day: 3 })))
+            .unwrap();
+        todo_list.mark_complete(2).unwrap();
+
+        let before = todo_list.clone();
+        todo_list.update_task(3, TaskUpdate::new().title("Pay the rent")).unwrap();
+        todo_list.stamp_changes(&before, Local.with_ymd_and_hms(2024, 5, 1, 9, 0, 0).unwrap());
+        todo_list
+    }
+
+    #[test]
+    fn test_export_vtodo() {
+        let todo_list = sample_list();
+        let tasks: Vec<&Task> = todo_list.get_all_tasks().iter().collect();
+        let now = Local.with_ymd_and_hms(2024, 5, 2, 12, 0, 0).unwrap();
+        let exported = export_at(&tasks, now);
+
+        assert!(exported.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(exported.ends_with("END:VCALENDAR\r\n"));
+        assert!(exported.lines().all(|line| line.len() <= 76));
+        assert_eq!(exported.matches("BEGIN:VTODO").count(), 3);
+
+        let unfolded: Vec<String> = unfold(&exported).into_iter().map(|(_, line)| line).collect();
+        let first = todo_list.get_task(1).unwrap();
+        assert!(unfolded.contains(&format!("UID:{}", uid(first))));
+        assert!(unfolded.contains(&"SUMMARY:Call the plumber\\; ask about the boiler\\, too".to_string()));
+        assert!(unfolded.contains(&"DESCRIPTION:Number is on the fridge\\nBefore 6pm".to_string()));
+        assert!(unfolded.contains(&format!("DUE:{}", format_datetime(first.due_date.unwrap()))));
+        assert!(unfolded.contains(&"PRIORITY:1".to_string()));
+        assert!(unfolded.contains(&"CATEGORIES:home,calls,house".to_string()));
+        assert!(unfolded.contains(&"RRULE:FREQ=WEEKLY;BYDAY=MO,TH".to_string()));
+        assert!(unfolded.contains(&"STATUS:COMPLETED".to_string()));
+
+        // Every task is stamped with the export time; only the edited one has a
+        // modification time and a sequence above zero
+        assert_eq!(unfolded.iter().filter(|line| **line == format!("DTSTAMP:{}", format_datetime(now))).count(), 3);
+        assert_eq!(unfolded.iter().filter(|line| line.starts_with("LAST-MODIFIED:")).count(), 1);
+        assert!(unfolded.contains(&"SEQUENCE:1".to_string()));
+        assert_eq!(unfolded.iter().filter(|line| **line == "SEQUENCE:0").count(), 2);
+
+        // UIDs don't change between exports
+        assert_eq!(export_at(&tasks, now), exported);
+    }
+
+    #[test]
+    fn test_round_trip_and_duplicate_uids() {
+        let original = sample_list();
+        let exported = export(&original.get_all_tasks().iter().collect::<Vec<_>>());
+
+        let imported = import(&exported, &TodoList::new()).unwrap();
+        assert!(imported.warnings.is_empty());
+        assert_eq!(imported.tasks.len(), original.len());
+        for (before, after) in original.get_all_tasks().iter().zip(&imported.tasks) {
+            assert_eq!(after.title, before.title);
+            assert_eq!(after.description, before.description);
+            assert_eq!(after.due_date, before.due_date);
+            assert_eq!(after.priority, before.priority);
+            assert_eq!(after.category, before.category);
+            assert_eq!(after.tags, before.tags);
+            assert_eq!(after.completed, before.completed);
+            assert_eq!(after.recurrence, before.recurrence);
+            assert_eq!(after.created_at.timestamp(), before.created_at.timestamp());
+            assert_eq!(after.completed_at.map(|d| d.timestamp()), before.completed_at.map(|d| d.timestamp()));
+            assert_eq!(after.modified_at.map(|d| d.timestamp()), before.modified_at.map(|d| d.timestamp()));
+            assert_eq!(after.revision, before.revision);
+        }
+
+        // Importing the export back into the same list adds nothing
+        let imported = import(&exported, &original).unwrap();
+        assert!(imported.tasks.is_empty());
+        assert_eq!(imported.warnings.len(), 3);
+    }
+
+    #[test]
+    fn test_recurring_instances_are_new_vtodos() {
+        let contents = "BEGIN:VCALENDAR\r\n\
+            BEGIN:VTODO\r\n\
+            UID:water-plants@example.com\r\n\
+            SUMMARY:Water plants\r\n\
+            DUE;VALUE=DATE:20240510\r\n\
+            RRULE:FREQ=DAILY\r\n\
+            SEQUENCE:4\r\n\
+            END:VTODO\r\n\
+            END:VCALENDAR\r\n";
+        let mut todo_list = TodoList::new();
+        for task in import(contents, &todo_list).unwrap().tasks {
+            todo_list.insert_task(task);
+        }
+        let next = todo_list.mark_complete(1).unwrap().expect("next instance");
+
+        let exported = export_at(&todo_list.get_all_tasks().iter().collect::<Vec<_>>(), Local::now());
+        let unfolded: Vec<String> = unfold(&exported).into_iter().map(|(_, line)| line).collect();
+        assert!(unfolded.contains(&"UID:water-plants@example.com".to_string()));
+        assert!(unfolded.contains(&format!("UID:{}", uid(todo_list.get_task(next).unwrap()))));
+        let values = |name: &str| -> Vec<&String> { unfolded.iter().filter(|line| line.starts_with(name)).collect() };
+        assert_eq!(values("UID:").into_iter().collect::<HashSet<_>>().len(), 2);
+        assert_eq!(values("SEQUENCE:"), ["SEQUENCE:4", "SEQUENCE:0"]);
+    }
+
+    #[test]
+    fn test_import_from_other_clients() {
+        let contents = "BEGIN:VCALENDAR\n\
+            PRODID:-//Other//EN\n\
+            BEGIN:VEVENT\n\
+            SUMMARY:Not a task\n\
+            END:VEVENT\n\
+            BEGIN:VTODO\n\
+            UID:abc-123\n\
+            SUMMARY:Submit the quarterly\n  report\n\
+            DUE;VALUE=DATE:20240610\n\
+            PRIORITY:7\n\
+            CATEGORIES:Work,Q2 Review\n\
+            RRULE:FREQ=MONTHLY\n\
+            X-APPLE-SORT-ORDER:12\n\
+            BEGIN:VALARM\n\
+            ACTION:DISPLAY\n\
+            SUMMARY:Reminder\n\
+            END:VALARM\n\
+            END:VTODO\n\
+            BEGIN:VTODO\n\
+            SUMMARY:Old idea\n\
+            STATUS:CANCELLED\n\
+            END:VTODO\n\
+            BEGIN:VTODO\n\
+            SUMMARY:Every other day\n\
+            DUE;TZID=Europe/Berlin:20240601T090000\n\
+            RRULE:FREQ=DAILY;INTERVAL=2\n\
+            END:VTODO\n\
+            END:VCALENDAR\n";
+
+        let imported = import(contents, &TodoList::new()).unwrap();
+        assert_eq!(imported.tasks.len(), 2);
+
+        let task = &imported.tasks[0];
+        assert_eq!(task.title, "Submit the quarterly report");
+        assert_eq!(task.due_date, Some(end_of_day(NaiveDate::from_ymd_opt(2024, 6, 10).unwrap())));
+        assert_eq!(task.priority, Priority::Low);
+        assert_eq!(task.category, Some("Work".to_string()));
+        assert!(task.has_tag("Q2_Review"));
+        assert_eq!(task.recurrence, Some(Recurrence::Monthly { day: 10 }));
+        assert_eq!(task.uuid.as_deref(), Some("abc-123"));
+
+        let task = &imported.tasks[1];
+        assert_eq!(task.due_date, Local.with_ymd_and_hms(2024, 6, 1, 9, 0, 0).earliest());
+        assert_eq!(task.recurrence, None);
+        assert_eq!(imported.warnings.len(), 3);
+
+        // Tasks from other clients are recognised by their UID on the next import
+        let mut todo_list = TodoList::new();
+        for task in imported.tasks {
+            todo_list.insert_task(task);
+        }
+        let again = import(contents, &todo_list).unwrap();
+        assert_eq!(again.tasks.len(), 1);
+        assert_eq!(again.tasks[0].title, "Every other day");
+
+        assert!(import("BEGIN:VTODO\nSUMMARY:Unclosed\n", &TodoList::new()).is_err());
+        assert!(import("BEGIN:VTODO\nSUMMARY:Bad\nDUE:tomorrow\nEND:VTODO\n", &TodoList::new()).is_err());
+    }
+}
@@ -0,0 +1,128 @@
+//! Markdown checklists for wiki pages and issues
+//!
+//! Tasks are grouped under a `##` heading per category (alphabetical, tasks
+//! without a category last) as GitHub task list items:
+//!
+//! ```text
+//! ## work
+//!
+//! - [ ] Write report (due 2024-03-08, high priority) +q1
+//!   Outline is in the shared drive
+//! - [x] Send invoices
+//! ```
+//!
+//! Descriptions follow their task as indented lines. This format is export only.
+
+use std::collections::BTreeMap;
+
+use crate::models::{Priority, Task};
+
+/// Heading for tasks without a category
+const UNCATEGORIZED: &str = "Uncategorized";
+
+/// Render tasks as checklists grouped by category, keeping their order within each group
+pub fn export(tasks: &[&Task]) -> String {
+    let mut groups: BTreeMap<&str, Vec<&Task>> = BTreeMap::new();
+    let mut uncategorized = Vec::new();
+    for task in tasks {
+        match &task.category {
+            Some(category) => groups.entry(category).or_default().push(task),
+            None => uncategorized.push(*task),
+        }
+    }
+
+    let sections = groups
+        .into_iter()
+        .chain((!uncategorized.is_empty()).then_some((UNCATEGORIZED, uncategorized)));
+    let mut output = String::new();
+    for (heading, tasks) in sections {
+        if !output.is_empty() {
+            output.push('\n');
+        }
+        output.push_str(&format!("## {}\n\n", escape(heading)));
+        for task in tasks {
+            output.push_str(&format_item(task));
+        }
+    }
+    output
+}
+
+fn format_item(task: &Task) -> String {
+    let mut details = Vec::new();
+    if let Some(due_date) = task.due_date {
+        details.push(format!("due {}", due_date.format("%Y-%m-%d")));
+    }
+    match task.priority {
+        Priority::High => details.push("high priority".to_string()),
+        Priority::Low => details.push("low priority".to_string()),
+        Priority::Medium => {}
+    }
+    if let Some(rule) = &task.recurrence {
+        details.push(format!("repeats {}", rule));
+    }
+
+    let mut line = format!("- [{}] {}", if task.completed { "x" } else { " " }, escape(&task.title));
+    if !details.is_empty() {
+        line.push_str(&format!(" ({})", details.join(", ")));
+    }
+    for tag in &task.tags {
+        line.push_str(&format!(" +{}", escape(tag)));
+    }
+    line.push('\n');
+
+    for description_line in task.description.iter().flat_map(|description| description.lines()) {
+        if !description_line.trim().is_empty() {
+            line.push_str(&format!("  {}\n", escape(description_line.trim())));
+        }
+    }
+    line
+}
+
+/// Escape characters that Markdown would otherwise interpret
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        if matches!(ch, '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|') {
+            escaped.push('\\');
+        }
+        escaped.push(ch);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{TaskUpdate, TodoList};
+    use chrono::{Local, TimeZone};
+
+    #[test]
+    fn test_export_groups_by_category() {
+        let mut todo_list = TodoList::new();
+        let due = Local.with_ymd_and_hms(2024, 3, 8, 23, 59, 59).unwrap();
+        todo_list.add_task_with_details("Buy milk".to_string(), None, None, None, Priority::Medium);
+        let id = todo_list.add_task_with_details(
+            "Write report".to_string(),
+            Some("Outline is in the *shared* drive".to_string()),
+            Some(due),
+            Some("work".to_string()),
+            Priority::High,
+        );
+        todo_list.update_task(id, TaskUpdate::new().tags(["q1"])).unwrap();
+        todo_list.add_task_with_details("Send invoices".to_string(), None, None, Some("admin".to_string()), Priority::Low);
+        todo_list.mark_complete(3).unwrap();
+
+        let tasks: Vec<&Task> = todo_list.get_all_tasks().iter().collect();
+        assert_eq!(
+            export(&tasks),
+            "## admin\n\n\
+             - [x] Send invoices (low priority)\n\
+             \n## work\n\n\
+             - [ ] Write report (due 2024-03-08, high priority) +q1\n\
+             \x20 Outline is in the \\*shared\\* drive\n\
+             \n## Uncategorized\n\n\
+             - [ ] Buy milk\n"
+        );
+        assert_eq!(export(&[]), "");
+    }
+}
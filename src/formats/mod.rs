@@ -0,0 +1,96 @@
+//! Import and export of tasks in formats used by other tools
+//!
+//! Each format lives in its own submodule with an `export` function that turns
+//! tasks into text and an `import` function that parses text into new tasks.
+//! Imported tasks don't have an ID yet; `TodoList::insert_task` assigns one.
+//! Formats that identify tasks across tools can instead return changes to
+//! tasks already in the list.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Timelike};
+
+use crate::models::{Task, TodoList};
+
+pub mod csv;
+pub mod ics;
+pub mod markdown;
+pub mod taskwarrior;
+pub mod todotxt;
+
+/// Supported import/export formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// todo.txt, one task per line
+    TodoTxt,
+    /// iCalendar VTODO entries
+    Ics,
+    /// Taskwarrior `task export` JSON
+    Taskwarrior,
+    /// CSV with a header row
+    Csv,
+    /// Markdown checklists grouped by category (export only)
+    Markdown,
+}
+
+/// Tasks parsed from an import file
+#[derive(Debug, Default)]
+pub struct Imported {
+    /// New tasks to add
+    pub tasks: Vec<Task>,
+    /// Existing tasks (with their IDs) whose fields changed in the other tool
+    pub updated: Vec<Task>,
+    /// Problems that didn't stop the import, e.g. fields rtodo can't represent
+    pub warnings: Vec<String>,
+}
+
+/// Render tasks in the given format
+pub fn export(format: Format, tasks: &[&Task]) -> Result<String> {
+    match format {
+        Format::TodoTxt => Ok(todotxt::export(tasks)),
+        Format::Ics => Ok(ics::export(tasks)),
+        Format::Taskwarrior => taskwarrior::export(tasks),
+        Format::Csv => csv::export(tasks),
+        Format::Markdown => Ok(markdown::export(tasks)),
+    }
+}
+
+/// Parse tasks from the given format
+///
+/// Formats that identify tasks recognise the ones already in `existing`.
+/// `columns` holds `FIELD=HEADER` mappings for CSV files.
+pub fn import(format: Format, contents: &str, existing: &TodoList, columns: &[String]) -> Result<Imported> {
+    if !columns.is_empty() && format != Format::Csv {
+        return Err(anyhow!("Column mappings only apply to CSV imports"));
+    }
+
+    match format {
+        Format::TodoTxt => todotxt::import(contents),
+        Format::Ics => ics::import(contents, existing),
+        Format::Taskwarrior => taskwarrior::import(contents, existing),
+        Format::Csv => csv::import(contents, columns),
+        Format::Markdown => Err(anyhow!("Markdown can only be exported")),
+    }
+}
+
+/// Due dates without a time are due at the end of the day, like `--due`
+fn end_of_day(date: NaiveDate) -> DateTime<Local> {
+    let naive = date.and_hms_opt(23, 59, 59).expect("valid time");
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .unwrap_or_else(|| Local.from_utc_datetime(&naive))
+}
+
+/// Whether an imported due date has a time of day; date-only ones are due at 23:59:59
+fn has_time_of_day(due_date: Option<DateTime<Local>>) -> bool {
+    due_date.is_some_and(|due| (due.hour(), due.minute(), due.second()) != (23, 59, 59))
+}
+
+/// Creation and completion dates without a time are taken as the start of the day
+fn start_of_day(date: NaiveDate) -> DateTime<Local> {
+    let naive = date.and_hms_opt(0, 0, 0).expect("valid time");
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .unwrap_or_else(|| Local.from_utc_datetime(&naive))
+}
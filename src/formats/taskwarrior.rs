@@ -0,0 +1,483 @@
+//! Taskwarrior JSON, as written by `task export` and read by `task import`
+//!
+//! | Task field              | Taskwarrior attribute                       |
+//! |-------------------------|---------------------------------------------|
+//! | title                   | `description`                               |
+//! | description             | `annotations`, one per line                 |
+//! | completed, completed_at | `status` (`pending`/`completed`), `end`     |
+//! | created_at              | `entry`                                     |
+//! | modified_at             | `modified`                                  |
+//! | due date                | `due`                                       |
+//! | scheduled, wait         | `scheduled`, `wait`                         |
+//! | category                | `project`                                   |
+//! | tags                    | `tags`                                      |
+//! | priority                | `priority` `H`, `M`, `L` (none is medium)   |
+//!
+//! Every task has a UUID. Imported tasks remember theirs, and tasks created in
+//! rtodo get one derived from their ID and creation time, so it is the same on
+//! every export. So do tasks whose imported identifier isn't a UUID, such as
+//! an iCalendar UID like `abc-123@example.com`, and each new instance of a
+//! recurring task. Importing a task whose UUID is already in the list updates
+//! that task instead of adding a duplicate.
+//!
+//! Deleted tasks are skipped. For recurring tasks only the next pending
+//! instance is imported, carrying the recurrence if rtodo supports it; the
+//! template and other instances are skipped. Recurrence, subtasks and
+//! dependencies are not exported.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Local, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use super::{has_time_of_day, Imported};
+use crate::models::{normalize_tag, Priority, Recurrence, Task, TodoList};
+
+/// Namespace for UUIDs derived from rtodo task IDs
+const NAMESPACE: Uuid = Uuid::from_u128(0x5c0f_3a4e_9b1d_4d6e_a7f2_3e8c_1b09_d4a7);
+
+/// The task's UUID: the imported one if it is a UUID, or one derived from its ID and creation time
+pub fn uuid(task: &Task) -> String {
+    match task.uuid.as_deref().filter(|uuid| Uuid::parse_str(uuid).is_ok()) {
+        Some(uuid) => uuid.to_string(),
+        None => {
+            let name = format!("{}-{}", task.id, task.created_at.timestamp());
+            Uuid::new_v5(&NAMESPACE, name.as_bytes()).to_string()
+        }
+    }
+}
+
+/// A task in Taskwarrior's export format; other attributes are ignored
+#[derive(Debug, Serialize, Deserialize)]
+struct TwTask {
+    uuid: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default = "pending")]
+    status: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    entry: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    modified: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    end: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    due: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scheduled: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    wait: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    project: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    priority: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    annotations: Vec<Annotation>,
+    #[serde(default, skip_serializing)]
+    recur: Option<String>,
+    /// UUID of the recurrence template, for instances of a recurring task
+    #[serde(default, skip_serializing)]
+    parent: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Annotation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    entry: Option<String>,
+    description: String,
+}
+
+fn pending() -> String {
+    "pending".to_string()
+}
+
+/// Render tasks as a JSON array with one task per line, like `task export`
+pub fn export(tasks: &[&Task]) -> Result<String> {
+    let lines = tasks
+        .iter()
+        .map(|task| serde_json::to_string(&to_taskwarrior(task)))
+        .collect::<serde_json::Result<Vec<_>>>()
+        .map_err(|e| anyhow!("Failed to serialize tasks: {}", e))?;
+    Ok(format!("[\n{}\n]\n", lines.join(",\n")))
+}
+
+fn to_taskwarrior(task: &Task) -> TwTask {
+    let end = task.completed_at.unwrap_or(task.created_at);
+    let modified = task.modified_at.unwrap_or(end);
+    TwTask {
+        uuid: uuid(task),
+        description: task.title.clone(),
+        status: if task.completed { "completed" } else { "pending" }.to_string(),
+        entry: Some(format_date(task.created_at)),
+        modified: Some(format_date(modified)),
+        // Taskwarrior requires an end date on completed tasks
+        end: task.completed.then(|| format_date(end)),
+        due: task.due_date.map(format_date),
+        scheduled: task.scheduled.map(format_date),
+        wait: task.wait.map(format_date),
+        project: task.category.clone(),
+        tags: task.tags.iter().cloned().collect(),
+        priority: Some(
+            match task.priority {
+                Priority::High => "H",
+                Priority::Medium => "M",
+                Priority::Low => "L",
+            }
+            .to_string(),
+        ),
+        annotations: task
+            .description
+            .iter()
+            .flat_map(|description| description.lines())
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| Annotation {
+                entry: Some(format_date(task.created_at)),
+                description: line.to_string(),
+            })
+            .collect(),
+        recur: None,
+        parent: None,
+    }
+}
+
+/// Parse `task export` output: a JSON array, or one object per line from older versions
+///
+/// Tasks whose UUID is already in `existing` are returned in `Imported::updated`.
+pub fn import(contents: &str, existing: &TodoList) -> Result<Imported> {
+    let values: Vec<Value> = if contents.trim_start().starts_with('[') {
+        serde_json::from_str(contents).map_err(|e| anyhow!("Invalid Taskwarrior JSON: {}", e))?
+    } else {
+        contents
+            .lines()
+            .enumerate()
+            .map(|(index, line)| (index, line.trim().trim_end_matches(',')))
+            .filter(|(_, line)| !line.is_empty())
+            .map(|(index, line)| {
+                serde_json::from_str(line).map_err(|e| anyhow!("Line {}: invalid Taskwarrior JSON: {}", index + 1, e))
+            })
+            .collect::<Result<_>>()?
+    };
+
+    let mut tw_tasks = Vec::with_capacity(values.len());
+    for (index, value) in values.into_iter().enumerate() {
+        let tw_task: TwTask = serde_json::from_value(value)
+            .map_err(|e| anyhow!("Task {}: {}", index + 1, e))?;
+        tw_tasks.push(tw_task);
+    }
+
+    // Only the next pending instance of each recurring task is imported
+    let mut next_instances: HashMap<&str, &TwTask> = HashMap::new();
+    for tw_task in &tw_tasks {
+        if let (Some(parent), "pending" | "waiting") = (&tw_task.parent, tw_task.status.as_str()) {
+            let next = next_instances.entry(parent).or_insert(tw_task);
+            if tw_task.due < next.due {
+                *next = tw_task;
+            }
+        }
+    }
+
+    let known: HashMap<String, &Task> = existing.get_all_tasks().iter().map(|task| (uuid(task), task)).collect();
+    let mut seen = HashSet::new();
+    let mut imported = Imported::default();
+    let mut skipped = 0;
+
+    for tw_task in &tw_tasks {
+        let is_next_instance = tw_task
+            .parent
+            .as_deref()
+            .and_then(|parent| next_instances.get(parent))
+            .is_some_and(|next| std::ptr::eq(*next, tw_task));
+        let skip = match tw_task.status.as_str() {
+            "deleted" | "recurring" => true,
+            "pending" | "waiting" => tw_task.parent.is_some() && !is_next_instance,
+            _ => false,
+        };
+        if skip || !seen.insert(tw_task.uuid.as_str()) {
+            skipped += 1;
+            continue;
+        }
+
+        match known.get(&tw_task.uuid) {
+            Some(task) => {
+                let mut updated = (*task).clone();
+                apply(tw_task, &mut updated, &mut imported.warnings)?;
+                if updated.completed {
+                    // Only the pending instance of a series carries the rule
+                    updated.recurrence = None;
+                }
+                if &updated != *task {
+                    imported.updated.push(updated);
+                }
+            }
+            None => {
+                let mut task = Task::new(0, String::new());
+                apply(tw_task, &mut task, &mut imported.warnings)?;
+                if let (Some(rule), false) = (&tw_task.recur, task.completed) {
+                    task.recurrence = parse_recur(rule, task.due_date);
+                    if task.recurrence.is_none() {
+                        imported.warnings.push(format!(
+                            "Task '{}': unsupported recurrence '{}' ignored",
+                            task.title, rule
+                        ));
+                    }
+                }
+                imported.tasks.push(task);
+            }
+        }
+    }
+
+    if skipped > 0 {
+        imported.warnings.push(format!(
+            "Skipped {} deleted, duplicate or recurrence template tasks",
+            skipped
+        ));
+    }
+    Ok(imported)
+}
+
+/// Copy the mapped Taskwarrior attributes onto a task
+fn apply(tw_task: &TwTask, task: &mut Task, warnings: &mut Vec<String>) -> Result<()> {
+    let title = tw_task.description.split_whitespace().collect::<Vec<_>>().join(" ");
+    if title.is_empty() {
+        return Err(anyhow!("Task {} has no description", tw_task.uuid));
+    }
+    let date = |field: &str, value: &Option<String>| -> Result<Option<DateTime<Local>>> {
+        value
+            .as_deref()
+            .map(|value| {
+                parse_date(value).ok_or_else(|| anyhow!("Task '{}': invalid {} date '{}'", title, field, value))
+            })
+            .transpose()
+    };
+
+    // Taskwarrior dates have no sub-second part, so keep ours when they match
+    let unless_same = |current: Option<DateTime<Local>>, new: Option<DateTime<Local>>| {
+        if current.map(|date| date.timestamp()) == new.map(|date| date.timestamp()) {
+            current
+        } else {
+            new
+        }
+    };
+
+    if uuid(task) != tw_task.uuid {
+        task.uuid = Some(tw_task.uuid.clone());
+    }
+    task.description = Some(
+        tw_task
+            .annotations
+            .iter()
+            .map(|annotation| annotation.description.as_str())
+            .collect::<Vec<_>>()
+            .join("\n"),
+    )
+    .filter(|description| !description.is_empty());
+    if let Some(entry) = unless_same(Some(task.created_at), date("entry", &tw_task.entry)?) {
+        task.created_at = entry;
+    }
+    task.due_date = unless_same(task.due_date, date("due", &tw_task.due)?);
+    task.due_has_time = has_time_of_day(task.due_date);
+    task.scheduled = unless_same(task.scheduled, date("scheduled", &tw_task.scheduled)?);
+    task.wait = unless_same(task.wait, date("wait", &tw_task.wait)?);
+    task.completed = tw_task.status == "completed";
+    task.completed_at = if task.completed {
+        unless_same(task.completed_at, date("end", &tw_task.end)?).or(task.completed_at)
+    } else {
+        None
+    };
+    task.category = tw_task.project.clone();
+    task.priority = match tw_task.priority.as_deref() {
+        Some("H") => Priority::High,
+        Some("L") => Priority::Low,
+        Some("M") | None => Priority::Medium,
+        Some(other) => {
+            warnings.push(format!("Task '{}': unknown priority '{}', using medium", title, other));
+            Priority::Medium
+        }
+    };
+    task.tags.clear();
+    for tag in &tw_task.tags {
+        match normalize_tag(tag) {
+            Ok(tag) => {
+                task.tags.insert(tag);
+            }
+            Err(e) => warnings.push(format!("Task '{}': {}", title, e)),
+        }
+    }
+    task.title = title;
+    Ok(())
+}
+
+fn format_date(date: DateTime<Local>) -> String {
+    date.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Parse Taskwarrior's `YYYYMMDDTHHMMSSZ` dates, or RFC 3339 as written by some tools
+fn parse_date(value: &str) -> Option<DateTime<Local>> {
+    if let Ok(naive) = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%SZ") {
+        return Some(Utc.from_utc_datetime(&naive).with_timezone(&Local));
+    }
+    DateTime::parse_from_rfc3339(value).ok().map(|date| date.with_timezone(&Local))
+}
+
+/// Map the Taskwarrior recurrence periods rtodo supports
+fn parse_recur(rule: &str, due_date: Option<DateTime<Local>>) -> Option<Recurrence> {
+    match rule {
+        "daily" | "day" | "1d" | "1day" | "1days" => Some(Recurrence::Daily),
+        "weekly" | "week" | "1w" | "1wk" | "1wks" | "1week" | "1weeks" | "7d" | "7days" => {
+            Some(Recurrence::Weekly { weekdays: Vec::new() })
+        }
+        "monthly" | "month" | "1mo" | "1mos" | "1month" | "1months" => Some(Recurrence::Monthly {
+            day: due_date.map_or(1, |due| due.day()),
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"[
+{"id":1,"description":"Call the plumber","entry":"20240428T080000Z","modified":"20240428T080000Z","project":"home","status":"pending","tags":["house","phone"],"priority":"H","due":"20240503T160000Z","wait":"20240501T080000Z","uuid":"2c6f5b8e-4d3a-4f6e-9a1b-0c2d3e4f5a6b","urgency":9.2,"annotations":[{"entry":"20240428T080500Z","description":"Number is on the fridge"},{"entry":"20240429T080500Z","description":"Ask about the boiler"}]},
+{"id":0,"description":"File taxes","end":"20240502T100000Z","entry":"20240401T080000Z","status":"completed","uuid":"7a1e3f90-2b4c-4d5e-8f60-718293a4b5c6"},
+{"id":0,"description":"Old idea","entry":"20240101T080000Z","status":"deleted","uuid":"0b1c2d3e-4f50-4617-8293-a4b5c6d7e8f9"},
+{"id":0,"description":"Water plants","entry":"20240101T080000Z","status":"recurring","recur":"weekly","due":"20240105T180000Z","uuid":"d1e2f3a4-b5c6-4d7e-8f90-a1b2c3d4e5f6"},
+{"id":2,"description":"Water plants","entry":"20240101T080000Z","status":"pending","recur":"weekly","due":"20240517T180000Z","parent":"d1e2f3a4-b5c6-4d7e-8f90-a1b2c3d4e5f6","uuid":"e1e2f3a4-b5c6-4d7e-8f90-a1b2c3d4e5f6"},
+{"id":3,"description":"Water plants","entry":"20240101T080000Z","status":"pending","recur":"weekly","due":"20240510T180000Z","parent":"d1e2f3a4-b5c6-4d7e-8f90-a1b2c3d4e5f6","uuid":"f1e2f3a4-b5c6-4d7e-8f90-a1b2c3d4e5f6"}
+]"#;
+
+    #[test]
+    fn test_import_task_export() {
+        let imported = import(SAMPLE, &TodoList::new()).unwrap();
+        assert_eq!(imported.tasks.len(), 3);
+        assert!(imported.updated.is_empty());
+
+        let task = &imported.tasks[0];
+        assert_eq!(task.title, "Call the plumber");
+        assert_eq!(task.description, Some("Number is on the fridge\nAsk about the boiler".to_string()));
+        assert_eq!(task.category, Some("home".to_string()));
+        assert!(task.has_tag("house") && task.has_tag("phone"));
+        assert_eq!(task.priority, Priority::High);
+        assert_eq!(task.due_date, Some(Utc.with_ymd_and_hms(2024, 5, 3, 16, 0, 0).unwrap().with_timezone(&Local)));
+        assert_eq!(task.wait, Some(Utc.with_ymd_and_hms(2024, 5, 1, 8, 0, 0).unwrap().with_timezone(&Local)));
+        assert_eq!(task.uuid.as_deref(), Some("2c6f5b8e-4d3a-4f6e-9a1b-0c2d3e4f5a6b"));
+        assert!(!task.completed);
+
+        let task = &imported.tasks[1];
+        assert!(task.completed);
+        assert_eq!(task.completed_at, Some(Utc.with_ymd_and_hms(2024, 5, 2, 10, 0, 0).unwrap().with_timezone(&Local)));
+        assert_eq!(task.priority, Priority::Medium);
+
+        // The earliest pending instance stands in for the recurring task
+        let task = &imported.tasks[2];
+        assert_eq!(task.uuid.as_deref(), Some("f1e2f3a4-b5c6-4d7e-8f90-a1b2c3d4e5f6"));
+        assert_eq!(task.recurrence, Some(Recurrence::Weekly { weekdays: Vec::new() }));
+        assert_eq!(imported.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_repeated_import_updates_instead_of_duplicating() {
+        let mut todo_list = TodoList::new();
+        for task in import(SAMPLE, &todo_list).unwrap().tasks {
+            todo_list.insert_task(task);
+        }
+
+        let again = import(SAMPLE, &todo_list).unwrap();
+        assert!(again.tasks.is_empty());
+        assert!(again.updated.is_empty());
+
+        // The plumber was called in Taskwarrior in the meantime
+        let changed = SAMPLE.replacen(
+            r#""status":"pending","tags":["house","phone"]"#,
+            r#""status":"completed","end":"20240502T120000Z","tags":["house","phone"]"#,
+            1,
+        );
+        let again = import(&changed, &todo_list).unwrap();
+        assert!(again.tasks.is_empty());
+        assert_eq!(again.updated.len(), 1);
+        assert_eq!(again.updated[0].id, 1);
+        assert!(again.updated[0].completed);
+    }
+
+    #[test]
+    fn test_export_round_trip() {
+        let mut todo_list = TodoList::new();
+        let id = todo_list.add_task_with_details(
+            "Renew passport".to_string(),
+            Some("Photos first\nThen the form".to_string()),
+            Some(Local.with_ymd_and_hms(2024, 7, 1, 23, 59, 59).unwrap()),
+            Some("errands".to_string()),
+            Priority::Low,
+        );
+        todo_list.get_task_mut(id).unwrap().tags.insert("travel".to_string());
+        todo_list.add_task("Plain".to_string());
+        todo_list.mark_complete(2).unwrap();
+
+        let tasks: Vec<&Task> = todo_list.get_all_tasks().iter().collect();
+        let exported = export(&tasks).unwrap();
+        assert_eq!(exported, export(&tasks).unwrap());
+        assert_eq!(exported.lines().count(), 4);
+
+        let values: Vec<Value> = serde_json::from_str(&exported).unwrap();
+        assert_eq!(values[0]["uuid"], uuid(tasks[0]));
+        assert_eq!(values[0]["project"], "errands");
+        assert_eq!(values[0]["priority"], "L");
+        assert_eq!(values[0]["annotations"].as_array().unwrap().len(), 2);
+        assert_eq!(values[1]["status"], "completed");
+        assert!(values[1]["end"].is_string());
+
+        // Into a fresh list everything comes back; into the same list nothing changes
+        let imported = import(&exported, &TodoList::new()).unwrap();
+        for (before, after) in tasks.iter().zip(&imported.tasks) {
+            assert_eq!(after.title, before.title);
+            assert_eq!(after.description, before.description);
+            assert_eq!(after.due_date, before.due_date);
+            assert_eq!(after.category, before.category);
+            assert_eq!(after.tags, before.tags);
+            assert_eq!(after.priority, before.priority);
+            assert_eq!(after.completed, before.completed);
+            assert_eq!(after.uuid, Some(uuid(before)));
+        }
+        let imported = import(&exported, &todo_list).unwrap();
+        assert!(imported.tasks.is_empty());
+        assert!(imported.updated.is_empty());
+    }
+
+    #[test]
+    fn test_recurring_instances_have_distinct_uuids() {
+        let mut todo_list = TodoList::new();
+        for task in import(SAMPLE, &todo_list).unwrap().tasks {
+            todo_list.insert_task(task);
+        }
+        let watering = todo_list.get_all_tasks().iter().find(|task| task.recurrence.is_some()).unwrap().id;
+        todo_list.mark_complete(watering).unwrap().expect("next instance");
+
+        let series: Vec<&Task> = todo_list.get_series_tasks(watering);
+        assert_eq!(series.len(), 2);
+        let exported = export(&series).unwrap();
+        let values: Vec<Value> = serde_json::from_str(&exported).unwrap();
+        assert_ne!(values[0]["uuid"], values[1]["uuid"]);
+        assert_eq!(values[0]["uuid"], "f1e2f3a4-b5c6-4d7e-8f90-a1b2c3d4e5f6");
+
+        // Both instances are recognised on the next import instead of merging into one
+        let again = import(&exported, &todo_list).unwrap();
+        assert!(again.tasks.is_empty());
+        assert!(again.updated.is_empty());
+    }
+
+    #[test]
+    fn test_uuid_of_task_with_ical_uid() {
+        let mut task = Task::new(3, "Book flights".to_string());
+        let derived = uuid(&task);
+        task.uuid = Some("abc-123@example.com".to_string());
+        assert_eq!(uuid(&task), derived);
+        assert!(Uuid::parse_str(&derived).is_ok());
+
+        task.uuid = Some("2c6f5b8e-4d3a-4f6e-9a1b-0c2d3e4f5a6b".to_string());
+        assert_eq!(uuid(&task), "2c6f5b8e-4d3a-4f6e-9a1b-0c2d3e4f5a6b");
+    }
+}
@@ -0,0 +1,371 @@
+//! todo.txt format (<https://github.com/todotxt/todo.txt>)
+//!
+//! Each task is one line:
+//!
+//! ```text
+//! x 2024-05-02 2024-04-28 Call plumber @home +house due:2024-05-03 pri:A
+//! (B) 2024-04-30 Water plants @home rec:3d
+//! ```
+//!
+//! # Mapping
+//!
+//! - priority `(A)`, `(B)`, `(C)` is High, Medium, Low (`(D)`-`(Z)` import as Low);
+//!   completed tasks keep their priority as `pri:X`, as most todo.txt apps do
+//! - the first `@context` is the category; further contexts and `+projects` are tags
+//! - `due:YYYY-MM-DD` is the due date and `x YYYY-MM-DD` the completion date
+//! - `rec:` uses the common recurrence extension: `+1d`, `+1w` and `+1m` repeat
+//!   from the due date, `Nd`/`Nw` repeat N days/weeks after completion; a
+//!   monthly rule imports with the day of its due date
+//!
+//! Descriptions, subtasks, dependencies and weekly rules on specific weekdays
+//! have no todo.txt equivalent and are not exported. A context can't contain
+//! spaces, so spaces in categories are written as `%20` (and `%` as `%25`);
+//! everything else, `_` included, is kept as it is.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Local, NaiveDate};
+
+use super::{end_of_day, start_of_day, Imported};
+use crate::models::{normalize_tag, Priority, Recurrence, Task};
+
+/// Render tasks as todo.txt lines
+pub fn export(tasks: &[&Task]) -> String {
+    tasks
+        .iter()
+        .map(|task| format!("{}\n", format_task(task)))
+        .collect()
+}
+
+/// Parse todo.txt lines into new tasks; blank lines are skipped
+pub fn import(contents: &str) -> Result<Imported> {
+    let mut imported = Imported::default();
+    for (index, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let task = parse_line(line, index + 1, &mut imported.warnings)?;
+        imported.tasks.push(task);
+    }
+    Ok(imported)
+}
+
+fn priority_letter(priority: &Priority) -> char {
+    match priority {
+        Priority::High => 'A',
+        Priority::Medium => 'B',
+        Priority::Low => 'C',
+    }
+}
+
+fn priority_from_letter(letter: char) -> Option<Priority> {
+    match letter {
+        'A' => Some(Priority::High),
+        'B' => Some(Priority::Medium),
+        'C'..='Z' => Some(Priority::Low),
+        _ => None,
+    }
+}
+
+fn format_date(date: DateTime<Local>) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
+
+fn parse_date(word: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(word, "%Y-%m-%d").ok()
+}
+
+fn format_task(task: &Task) -> String {
+    let mut words = Vec::new();
+
+    if task.completed {
+        words.push("x".to_string());
+        // A creation date is only recognised after a completion date
+        if let Some(completed_at) = task.completed_at {
+            words.push(format_date(completed_at));
+            words.push(format_date(task.created_at));
+        }
+    } else {
+        words.push(format!("({})", priority_letter(&task.priority)));
+        words.push(format_date(task.created_at));
+    }
+
+    words.extend(task.title.split_whitespace().map(str::to_string));
+
+    if let Some(category) = &task.category {
+        words.push(format!("@{}", escape_context(category)));
+    }
+    words.extend(task.tags.iter().map(|tag| format!("+{}", tag)));
+    if let Some(due_date) = task.due_date {
+        words.push(format!("due:{}", format_date(due_date)));
+    }
+    if let Some(rule) = task.recurrence.as_ref().and_then(format_recurrence) {
+        words.push(format!("rec:{}", rule));
+    }
+    if task.completed {
+        words.push(format!("pri:{}", priority_letter(&task.priority)));
+    }
+
+    words.join(" ")
+}
+
+fn format_recurrence(recurrence: &Recurrence) -> Option<String> {
+    match recurrence {
+        Recurrence::Daily => Some("+1d".to_string()),
+        Recurrence::Weekly { weekdays } if weekdays.is_empty() => Some("+1w".to_string()),
+        Recurrence::Weekly { .. } => None,
+        Recurrence::Monthly { .. } => Some("+1m".to_string()),
+        Recurrence::AfterCompletion { days } => Some(format!("{}d", days)),
+    }
+}
+
+/// Recurrence as written in a `rec:` value, resolved once the due date is known
+enum RecurrenceSpec {
+    Daily,
+    Weekly,
+    Monthly,
+    After(u32),
+}
+
+fn parse_recurrence(value: &str) -> Option<RecurrenceSpec> {
+    let (strict, rule) = match value.strip_prefix('+') {
+        Some(rule) => (true, rule),
+        None => (false, value),
+    };
+    let unit = rule.chars().last()?;
+    let count: u32 = rule[..rule.len() - unit.len_utf8()].parse().ok()?;
+
+    match (strict, count, unit) {
+        (true, 1, 'd') => Some(RecurrenceSpec::Daily),
+        (true, 1, 'w') => Some(RecurrenceSpec::Weekly),
+        (true, 1, 'm') => Some(RecurrenceSpec::Monthly),
+        (false, n, 'd') if n > 0 => Some(RecurrenceSpec::After(n)),
+        (false, n, 'w') if n > 0 => Some(RecurrenceSpec::After(n * 7)),
+        _ => None,
+    }
+}
+
+/// Write a category as a context, escaping the characters a context can't hold
+fn escape_context(category: &str) -> String {
+    category.split_whitespace().collect::<Vec<_>>().join(" ").replace('%', "%25").replace(' ', "%20")
+}
+
+/// Undo `escape_context`; other `%` sequences are left alone
+fn unescape_context(context: &str) -> String {
+    context.replace("%20", " ").replace("%25", "%")
+}
+
+fn parse_line(line: &str, line_number: usize, warnings: &mut Vec<String>) -> Result<Task> {
+    let mut words = line.split_whitespace().peekable();
+    let mut task = Task::new(0, String::new());
+    let mut priority = None;
+    let mut recurrence = None;
+
+    if words.peek() == Some(&"x") {
+        words.next();
+        task.completed = true;
+        if let Some(date) = words.peek().and_then(|word| parse_date(word)) {
+            words.next();
+            task.completed_at = Some(start_of_day(date));
+        }
+    }
+
+    if let Some(letter) = words.peek().and_then(|word| {
+        let letter = word.strip_prefix('(')?.strip_suffix(')')?;
+        let mut chars = letter.chars();
+        match (chars.next(), chars.next()) {
+            (Some(letter), None) => priority_from_letter(letter),
+            _ => None,
+        }
+    }) {
+        words.next();
+        priority = Some(letter);
+    }
+
+    if let Some(date) = words.peek().and_then(|word| parse_date(word)) {
+        words.next();
+        task.created_at = start_of_day(date);
+    }
+
+    let mut title = Vec::new();
+    for word in words {
+        if let Some(context) = word.strip_prefix('@').filter(|context| !context.is_empty()) {
+            if task.category.is_none() {
+                task.category = Some(unescape_context(context));
+            } else {
+                task.tags.insert(context.to_string());
+            }
+            continue;
+        }
+
+        if let Some(project) = word.strip_prefix('+').filter(|project| !project.is_empty()) {
+            if let Ok(tag) = normalize_tag(project) {
+                task.tags.insert(tag);
+                continue;
+            }
+        }
+
+        match word.split_once(':') {
+            Some(("due", value)) => {
+                let date = parse_date(value)
+                    .ok_or_else(|| anyhow!("Line {}: invalid due date '{}' (expected YYYY-MM-DD)", line_number, value))?;
+                task.due_date = Some(end_of_day(date));
+            }
+            Some(("rec", value)) => match parse_recurrence(value) {
+                Some(spec) => recurrence = Some(spec),
+                None => warnings.push(format!("Line {}: unsupported recurrence 'rec:{}' ignored", line_number, value)),
+            },
+            Some(("pri", value)) => {
+                let mut chars = value.chars();
+                match (chars.next().and_then(priority_from_letter), chars.next()) {
+                    (Some(pri), None) => priority = Some(pri),
+                    _ => warnings.push(format!("Line {}: invalid priority 'pri:{}' ignored", line_number, value)),
+                }
+            }
+            _ => title.push(word),
+        }
+    }
+
+    if title.is_empty() {
+        return Err(anyhow!("Line {}: task has no text", line_number));
+    }
+    task.title = title.join(" ");
+    task.priority = priority.unwrap_or_default();
+    task.recurrence = recurrence.map(|spec| match spec {
+        RecurrenceSpec::Daily => Recurrence::Daily,
+        RecurrenceSpec::Weekly => Recurrence::Weekly { weekdays: Vec::new() },
+        RecurrenceSpec::Monthly => Recurrence::Monthly {
+            day: task.due_date.map_or(1, |due| due.day()),
+        },
+        RecurrenceSpec::After(days) => Recurrence::AfterCompletion { days },
+    });
+
+    Ok(task)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{TaskUpdate, TodoList};
+    use chrono::{Duration, TimeZone};
+
+    fn date(year: i32, month: u32, day: u32) -> DateTime<Local> {
+        end_of_day(NaiveDate::from_ymd_opt(year, month, day).unwrap())
+    }
+
+    #[test]
+    fn test_parse_full_line() {
+        let imported = import(
+            "x 2024-05-02 2024-04-28 Call the plumber @home @phone +house due:2024-05-03 pri:A\n\n\
+             (C) 2024-04-30 Water plants rec:3d\n\
+             Meet at 10:30 @phone_calls rec:+1m due:2024-06-15\n",
+        )
+        .unwrap();
+        assert_eq!(imported.tasks.len(), 3);
+        assert!(imported.warnings.is_empty());
+
+        let task = &imported.tasks[0];
+        assert_eq!(task.title, "Call the plumber");
+        assert!(task.completed);
+        assert_eq!(task.completed_at.unwrap().date_naive(), NaiveDate::from_ymd_opt(2024, 5, 2).unwrap());
+        assert_eq!(task.created_at.date_naive(), NaiveDate::from_ymd_opt(2024, 4, 28).unwrap());
+        assert_eq!(task.category, Some("home".to_string()));
+        assert!(task.has_tag("phone") && task.has_tag("house"));
+        assert_eq!(task.due_date, Some(date(2024, 5, 3)));
+        assert_eq!(task.priority, Priority::High);
+
+        let task = &imported.tasks[1];
+        assert_eq!(task.priority, Priority::Low);
+        assert_eq!(task.recurrence, Some(Recurrence::AfterCompletion { days: 3 }));
+
+        // Unknown key:value words stay in the title
+        let task = &imported.tasks[2];
+        assert_eq!(task.title, "Meet at 10:30");
+        assert_eq!(task.category, Some("phone_calls".to_string()));
+        assert_eq!(task.priority, Priority::Medium);
+        assert_eq!(task.recurrence, Some(Recurrence::Monthly { day: 15 }));
+    }
+
+    #[test]
+    fn test_import_errors_and_warnings() {
+        let error = import("Fine task\n(A) Broken due:tomorrow\n").unwrap_err();
+        assert!(error.to_string().contains("Line 2"));
+
+        assert!(import("(A) @home +tag\n").is_err());
+
+        let imported = import("Stretch rec:+2d\n").unwrap();
+        assert_eq!(imported.tasks[0].recurrence, None);
+        assert_eq!(imported.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_export_line() {
+        let mut todo_list = TodoList::new();
+        let id = todo_list.add_task_with_details(
+            "Renew passport".to_string(),
+            Some("Not exported".to_string()),
+            Some(date(2024, 7, 1)),
+            Some("errands and admin".to_string()),
+            Priority::High,
+        );
+        todo_list
+            .update_task(id, TaskUpdate::new().tags(["travel"]).recurrence(Some(Recurrence::Daily)))
+            .unwrap();
+        let created = format_date(todo_list.get_task(id).unwrap().created_at);
+
+        let tasks: Vec<&Task> = todo_list.get_all_tasks().iter().collect();
+        assert_eq!(
+            export(&tasks),
+            format!("(A) {} Renew passport @errands%20and%20admin +travel due:2024-07-01 rec:+1d\n", created)
+        );
+    }
+
+    #[test]
+    fn test_round_trip_preserves_representable_fields() {
+        let mut original = TodoList::new();
+        let created = Local.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap();
+        let specs = [
+            ("Plain task", None, Some("50% done"), Priority::Medium, vec![], None),
+            ("Write report", Some(date(2024, 3, 8)), Some("home office"), Priority::High, vec!["q1", "writing"], None),
+            ("Daily standup", Some(date(2024, 3, 4)), Some("work"), Priority::Low, vec![], Some(Recurrence::Daily)),
+            ("Pay rent", Some(date(2024, 3, 28)), None, Priority::High, vec!["bills"], Some(Recurrence::Monthly { day: 28 })),
+            ("Water plants", None, Some("home_garden"), Priority::Medium, vec![], Some(Recurrence::AfterCompletion { days: 3 })),
+            ("Weekly review", Some(date(2024, 3, 1)), None, Priority::Low, vec![], Some(Recurrence::Weekly { weekdays: vec![] })),
+        ];
+        for (title, due, category, priority, tags, recurrence) in specs {
+            let id = original.add_task_with_details(title.to_string(), None, due, category.map(str::to_string), priority);
+            original
+                .update_task(id, TaskUpdate::new().tags(tags).recurrence(recurrence))
+                .unwrap();
+            original.get_task_mut(id).unwrap().created_at = created;
+        }
+        original.mark_complete(2).unwrap();
+        original.get_task_mut(2).unwrap().completed_at = Some(created + Duration::days(5));
+        original.get_task_mut(1).unwrap().completed = true;
+
+        let exported = export(&original.get_all_tasks().iter().collect::<Vec<_>>());
+        let mut reimported = TodoList::new();
+        for task in import(&exported).unwrap().tasks {
+            reimported.insert_task(task);
+        }
+
+        // Times of day are not part of todo.txt, so compare dates
+        let day = |date: Option<DateTime<Local>>| date.map(|d| d.date_naive());
+        assert_eq!(reimported.len(), original.len());
+        for (before, after) in original.get_all_tasks().iter().zip(reimported.get_all_tasks()) {
+            assert_eq!(after.title, before.title);
+            assert_eq!(after.completed, before.completed);
+            assert_eq!(after.priority, before.priority);
+            assert_eq!(after.category, before.category);
+            assert_eq!(after.tags, before.tags);
+            assert_eq!(after.due_date, before.due_date);
+            assert_eq!(after.recurrence, before.recurrence);
+            assert_eq!(day(after.completed_at), day(before.completed_at));
+            if before.completed_at.is_some() || !before.completed {
+                assert_eq!(after.created_at.date_naive(), before.created_at.date_naive());
+            }
+        }
+
+        // Exporting the re-imported list gives the same file
+        assert_eq!(export(&reimported.get_all_tasks().iter().collect::<Vec<_>>()), exported);
+    }
+}
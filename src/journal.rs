@@ -0,0 +1,497 @@
+//! Operation journal for undo and redo
+//!
+//! Every command that changes the todo list is recorded as an `Operation`
+//! holding the state of each affected task before and after the command,
+//! and the entries it added to or took out of the trash. Undoing an operation
+//! puts the "before" states back and moves it onto the redo stack; redoing it
+//! applies the "after" states again. Recording a new operation clears the redo
+//! stack. Operations that move tasks into or out of the archive (or purge it)
+//! record the archive's task states the same way, so undo and redo move the
+//! tasks between the two files.
+//!
+//! The journal is kept next to the data file as `<data file>.journal`, so it
+//! works the same way for every storage backend.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::models::{Task, TodoList, TrashedTask};
+
+/// Maximum number of operations kept for undo
+pub const JOURNAL_LIMIT: usize = 100;
+
+/// State of one task before and after an operation
+///
+/// `before` is `None` for tasks the operation created and `after` is `None`
+/// for tasks it removed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskChange {
+    pub id: u32,
+    pub before: Option<Task>,
+    pub after: Option<Task>,
+}
+
+/// A recorded command and the task changes it made
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Operation {
+    /// The command line that made the change, without global options
+    pub command: String,
+    pub timestamp: DateTime<Local>,
+    pub next_id_before: u32,
+    pub next_id_after: u32,
+    pub changes: Vec<TaskChange>,
+    /// Entries the operation put into the trash
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trashed: Vec<TrashedTask>,
+    /// Entries the operation took out of the trash
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub untrashed: Vec<TrashedTask>,
+    /// Changes the operation made to the archive
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub archive_changes: Vec<TaskChange>,
+}
+
+/// Changes to each task that differs between two states of a list
+fn task_changes(before: &TodoList, after: &TodoList) -> Vec<TaskChange> {
+    let ids: BTreeSet<u32> = before
+        .get_all_tasks()
+        .iter()
+        .chain(after.get_all_tasks())
+        .map(|task| task.id)
+        .collect();
+
+    ids.into_iter()
+        .filter_map(|id| {
+            let old = before.get_task(id);
+            let new = after.get_task(id);
+            (old != new).then(|| TaskChange {
+                id,
+                before: old.cloned(),
+                after: new.cloned(),
+            })
+        })
+        .collect()
+}
+
+impl Operation {
+    /// Describe the changes between two states of a todo list
+    ///
+    /// Returns `None` if the command didn't change anything.
+    pub fn between(command: &str, before: &TodoList, after: &TodoList) -> Option<Self> {
+        Self::between_with_archive(command, before, after, &TodoList::new(), &TodoList::new())
+    }
+
+    /// Describe the changes a command made to both the todo list and the archive
+    ///
+    /// Returns `None` if the command didn't change anything.
+    pub fn between_with_archive(
+        command: &str,
+        before: &TodoList,
+        after: &TodoList,
+        archive_before: &TodoList,
+        archive_after: &TodoList,
+    ) -> Option<Self> {
+        let changes = task_changes(before, after);
+        let archive_changes = task_changes(archive_before, archive_after);
+
+        let missing_from = |list: &TodoList, other: &TodoList| -> Vec<TrashedTask> {
+            list.trash().iter().filter(|entry| !other.trash().contains(entry)).cloned().collect()
+        };
+        let trashed = missing_from(after, before);
+        let untrashed = missing_from(before, after);
+
+        if changes.is_empty()
+            && archive_changes.is_empty()
+            && trashed.is_empty()
+            && untrashed.is_empty()
+            && before.next_id() == after.next_id()
+        {
+            return None;
+        }
+
+        Some(Self {
+            command: command.to_string(),
+            timestamp: Local::now(),
+            next_id_before: before.next_id(),
+            next_id_after: after.next_id(),
+            changes,
+            trashed,
+            untrashed,
+            archive_changes,
+        })
+    }
+
+    /// How many tasks the operation changed, in the todo list or the archive
+    pub fn task_count(&self) -> usize {
+        let ids: BTreeSet<u32> = self.changes.iter().chain(&self.archive_changes).map(|change| change.id).collect();
+        ids.len()
+    }
+
+    /// The change the operation made to a task in the archive, if any
+    pub fn archive_change(&self, id: u32) -> Option<&TaskChange> {
+        self.archive_changes.iter().find(|change| change.id == id)
+    }
+
+    /// Put back the task states from before the operation
+    pub fn revert(&self, todo_list: &mut TodoList, archive: &mut TodoList) -> Result<()> {
+        fn states(change: &TaskChange) -> (u32, &Option<Task>, &Option<Task>) {
+            (change.id, &change.after, &change.before)
+        }
+        self.check(todo_list, self.changes.iter().map(states), "undo", "todo list")?;
+        self.check(archive, self.archive_changes.iter().map(states), "undo", "archive")?;
+        Self::apply(todo_list, self.changes.iter().map(states));
+        Self::apply(archive, self.archive_changes.iter().map(states));
+        Self::move_trash_entries(todo_list, &self.trashed, &self.untrashed);
+        todo_list.set_next_id(self.next_id_before);
+        Ok(())
+    }
+
+    /// Apply the task states from after the operation again
+    pub fn reapply(&self, todo_list: &mut TodoList, archive: &mut TodoList) -> Result<()> {
+        fn states(change: &TaskChange) -> (u32, &Option<Task>, &Option<Task>) {
+            (change.id, &change.before, &change.after)
+        }
+        self.check(todo_list, self.changes.iter().map(states), "redo", "todo list")?;
+        self.check(archive, self.archive_changes.iter().map(states), "redo", "archive")?;
+        Self::apply(todo_list, self.changes.iter().map(states));
+        Self::apply(archive, self.archive_changes.iter().map(states));
+        Self::move_trash_entries(todo_list, &self.untrashed, &self.trashed);
+        todo_list.set_next_id(self.next_id_after);
+        Ok(())
+    }
+
+    /// Take `remove` out of the trash and put `add` back in
+    ///
+    /// Entries may have expired or been emptied from the trash since, so
+    /// missing ones are skipped rather than blocking the undo.
+    fn move_trash_entries(todo_list: &mut TodoList, remove: &[TrashedTask], add: &[TrashedTask]) {
+        for entry in remove {
+            todo_list.discard_trash_entry(entry);
+        }
+        for entry in add {
+            todo_list.restore_trash_entry(entry.clone());
+        }
+    }
+
+    /// Check that every task in `list` is still in its expected state
+    ///
+    /// Change stamps aside, a task that differs was changed by a command the
+    /// journal no longer covers or outside rtodo, so it is never silently
+    /// overwritten.
+    fn check<'a, I>(&self, list: &TodoList, states: I, action: &str, name: &str) -> Result<()>
+    where
+        I: Iterator<Item = (u32, &'a Option<Task>, &'a Option<Task>)>,
+    {
+        for (id, expected, _) in states {
+            let problem = match (list.get_task(id), expected) {
+                (Some(current), Some(expected)) if current.same_content(expected) => continue,
+                (None, None) => continue,
+                (Some(_), Some(_)) => format!("task {} has been changed since then", id),
+                (Some(_), None) => format!("task {} is in the {} again", id, name),
+                (None, Some(_)) => format!("task {} is no longer in the {}", id, name),
+            };
+            return Err(anyhow!("Cannot {} '{}': {}", action, self.command, problem));
+        }
+        Ok(())
+    }
+
+    /// Move tasks in `list` to their target states
+    fn apply<'a, I>(list: &mut TodoList, states: I)
+    where
+        I: Iterator<Item = (u32, &'a Option<Task>, &'a Option<Task>)>,
+    {
+        for (id, _, target) in states {
+            match target {
+                Some(task) => list.restore_task(task.clone()),
+                None => {
+                    list.discard_task(id);
+                }
+            }
+        }
+    }
+}
+
+/// Undo and redo stacks, most recent operation last
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Journal {
+    undo: Vec<Operation>,
+    redo: Vec<Operation>,
+}
+
+impl Journal {
+    /// Location of the journal for a data file
+    pub fn path_for(data_path: &Path) -> PathBuf {
+        let file_name = data_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        data_path.with_file_name(format!("{}.journal", file_name))
+    }
+
+    /// Load a journal, returning an empty one if it doesn't exist yet
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read journal {}: {}", path.display(), e))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| anyhow!("Failed to parse journal {}: {}", path.display(), e))
+    }
+
+    /// Save the journal, replacing the file atomically
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let json_data = serde_json::to_string(self)
+            .map_err(|e| anyhow!("Failed to serialize journal: {}", e))?;
+
+        let temp_path = path.with_extension("journal.tmp");
+        fs::write(&temp_path, json_data)
+            .map_err(|e| anyhow!("Failed to write to temporary file {}: {}", temp_path.display(), e))?;
+        fs::rename(&temp_path, path)
+            .map_err(|e| anyhow!("Failed to move temporary file to final location {}: {}", path.display(), e))?;
+        Ok(())
+    }
+
+    /// Record a new operation, dropping the oldest ones beyond `JOURNAL_LIMIT`
+    ///
+    /// Anything that was undone can no longer be redone afterwards.
+    pub fn record(&mut self, operation: Operation) {
+        self.undo.push(operation);
+        self.redo.clear();
+        if self.undo.len() > JOURNAL_LIMIT {
+            let excess = self.undo.len() - JOURNAL_LIMIT;
+            self.undo.drain(..excess);
+        }
+    }
+
+    /// Operations that can be undone, oldest first
+    pub fn undo_stack(&self) -> &[Operation] {
+        &self.undo
+    }
+
+    /// Operations that can be redone, most recently undone last
+    pub fn redo_stack(&self) -> &[Operation] {
+        &self.redo
+    }
+
+    /// Undo the most recent operation on `todo_list`
+    ///
+    /// Returns the operation that was undone, or `None` if there is nothing to undo.
+    pub fn undo(&mut self, todo_list: &mut TodoList, archive: &mut TodoList) -> Result<Option<&Operation>> {
+        let Some(operation) = self.undo.pop() else {
+            return Ok(None);
+        };
+        if let Err(e) = operation.revert(todo_list, archive) {
+            self.undo.push(operation);
+            return Err(e);
+        }
+        self.redo.push(operation);
+        Ok(self.redo.last())
+    }
+
+    /// Redo the most recently undone operation on `todo_list`
+    ///
+    /// Returns the operation that was redone, or `None` if there is nothing to redo.
+    pub fn redo(&mut self, todo_list: &mut TodoList, archive: &mut TodoList) -> Result<Option<&Operation>> {
+        let Some(operation) = self.redo.pop() else {
+            return Ok(None);
+        };
+        if let Err(e) = operation.reapply(todo_list, archive) {
+            self.redo.push(operation);
+            return Err(e);
+        }
+        self.undo.push(operation);
+        Ok(self.undo.last())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::archive;
+    use crate::models::TaskUpdate;
+    use tempfile::TempDir;
+
+    fn journaled<F>(journal: &mut Journal, todo_list: &mut TodoList, command: &str, change: F)
+    where
+        F: FnOnce(&mut TodoList),
+    {
+        let before = todo_list.clone();
+        change(todo_list);
+        if let Some(operation) = Operation::between(command, &before, todo_list) {
+            journal.record(operation);
+        }
+    }
+
+    #[test]
+    fn test_between_detects_added_changed_and_removed_tasks() {
+        let mut before = TodoList::new();
+        before.add_task("Keep".to_string());
+        before.add_task("Change".to_string());
+        before.add_task("Remove".to_string());
+
+        let mut after = before.clone();
+        after.update_task(2, TaskUpdate::new().title("Changed")).unwrap();
+        after.remove_task(3);
+        after.add_task("New".to_string());
+
+        let operation = Operation::between("several", &before, &after).unwrap();
+        let ids: Vec<u32> = operation.changes.iter().map(|change| change.id).collect();
+        assert_eq!(ids, vec![2, 3, 4]);
+        assert!(operation.changes[1].after.is_none());
+        assert!(operation.changes[2].before.is_none());
+        assert_eq!(operation.next_id_before, 4);
+        assert_eq!(operation.next_id_after, 5);
+
+        assert!(Operation::between("noop", &before, &before.clone()).is_none());
+    }
+
+    #[test]
+    fn test_undo_and_redo_multiple_operations() {
+        let mut journal = Journal::default();
+        let mut todo_list = TodoList::new();
+        let mut archive = TodoList::new();
+
+        journaled(&mut journal, &mut todo_list, "add Parent", |list| {
+            list.add_task("Parent".to_string());
+        });
+        journaled(&mut journal, &mut todo_list, "add Child", |list| {
+            let id = list.add_task("Child".to_string());
+            list.set_parent(id, Some(1)).unwrap();
+        });
+        journaled(&mut journal, &mut todo_list, "remove 1", |list| {
+            list.remove_task(1);
+        });
+        assert_eq!(todo_list.get_task(2).unwrap().parent_id, None);
+
+        // Undoing the removal also restores the child's parent link
+        let undone = journal.undo(&mut todo_list, &mut archive).unwrap().unwrap();
+        assert_eq!(undone.command, "remove 1");
+        assert_eq!(todo_list.get_task(2).unwrap().parent_id, Some(1));
+
+        journal.undo(&mut todo_list, &mut archive).unwrap();
+        journal.undo(&mut todo_list, &mut archive).unwrap();
+        assert!(todo_list.is_empty());
+        assert_eq!(todo_list.next_id(), 1);
+        assert!(journal.undo(&mut todo_list, &mut archive).unwrap().is_none());
+
+        journal.redo(&mut todo_list, &mut archive).unwrap();
+        journal.redo(&mut todo_list, &mut archive).unwrap();
+        assert_eq!(todo_list.len(), 2);
+        assert_eq!(todo_list.next_id(), 3);
+        assert_eq!(journal.redo_stack().len(), 1);
+
+        // A new operation makes the remaining redo impossible
+        journaled(&mut journal, &mut todo_list, "complete 2", |list| {
+            list.mark_complete(2).unwrap();
+        });
+        assert!(journal.redo_stack().is_empty());
+        assert!(journal.redo(&mut todo_list, &mut archive).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_undo_and_redo_keep_the_trash_in_step() {
+        let mut journal = Journal::default();
+        let mut todo_list = TodoList::new();
+        let mut archive = TodoList::new();
+        todo_list.add_task("Task".to_string());
+
+        journaled(&mut journal, &mut todo_list, "remove 1", |list| {
+            list.trash_task(1);
+        });
+        journal.undo(&mut todo_list, &mut archive).unwrap();
+        assert!(todo_list.get_task(1).is_some());
+        assert!(todo_list.trash().is_empty());
+        journal.redo(&mut todo_list, &mut archive).unwrap();
+        assert!(todo_list.get_task(1).is_none());
+        assert_eq!(todo_list.trash().len(), 1);
+
+        journaled(&mut journal, &mut todo_list, "trash restore 1", |list| {
+            list.restore_from_trash(1).unwrap();
+        });
+        journal.undo(&mut todo_list, &mut archive).unwrap();
+        assert!(todo_list.get_task(1).is_none());
+        assert_eq!(todo_list.trash().len(), 1);
+
+        // Entries emptied from the trash since don't block undoing the removal
+        todo_list.empty_trash(None);
+        journal.undo(&mut todo_list, &mut archive).unwrap();
+        assert!(todo_list.get_task(1).is_some());
+    }
+
+    #[test]
+    fn test_undo_and_redo_move_tasks_between_list_and_archive() {
+        let mut journal = Journal::default();
+        let mut todo_list = TodoList::new();
+        let mut archive = TodoList::new();
+        todo_list.add_task("Done".to_string());
+        todo_list.add_task("Open".to_string());
+        todo_list.mark_complete(1).unwrap();
+
+        let (before, archive_before) = (todo_list.clone(), archive.clone());
+        let moved = archive::select(&todo_list, None);
+        archive::move_tasks(&mut todo_list, &mut archive, &moved).unwrap();
+        let operation =
+            Operation::between_with_archive("archive", &before, &todo_list, &archive_before, &archive).unwrap();
+        assert_eq!(operation.task_count(), 1);
+        assert!(operation.archive_change(1).is_some());
+        journal.record(operation);
+
+        journal.undo(&mut todo_list, &mut archive).unwrap();
+        assert!(todo_list.get_task(1).is_some_and(|task| task.completed));
+        assert!(archive.is_empty());
+        journal.redo(&mut todo_list, &mut archive).unwrap();
+        assert!(todo_list.get_task(1).is_none());
+        assert_eq!(archive.len(), 1);
+
+        // A task purged from the archive since can't be moved back
+        archive.remove_task(1);
+        let error = journal.undo(&mut todo_list, &mut archive).unwrap_err();
+        assert!(error.to_string().contains("task 1 is no longer in the archive"));
+        assert!(todo_list.get_task(1).is_none());
+    }
+
+    #[test]
+    fn test_undo_refuses_when_task_changed_elsewhere() {
+        let mut journal = Journal::default();
+        let mut todo_list = TodoList::new();
+        journaled(&mut journal, &mut todo_list, "add Task", |list| {
+            list.add_task("Task".to_string());
+        });
+
+        todo_list.update_task(1, TaskUpdate::new().title("Edited by hand")).unwrap();
+        let error = journal.undo(&mut todo_list, &mut TodoList::new()).unwrap_err();
+        assert!(error.to_string().contains("task 1 has been changed since then"));
+        assert_eq!(journal.undo_stack().len(), 1);
+        assert_eq!(todo_list.get_task(1).unwrap().title, "Edited by hand");
+    }
+
+    #[test]
+    fn test_journal_limit_and_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let path = Journal::path_for(&dir.path().join("tasks.json"));
+        assert!(path.ends_with("tasks.json.journal"));
+
+        let mut journal = Journal::default();
+        let mut todo_list = TodoList::new();
+        for i in 0..JOURNAL_LIMIT + 5 {
+            journaled(&mut journal, &mut todo_list, &format!("add {}", i), |list| {
+                list.add_task(format!("Task {}", i));
+            });
+        }
+        assert_eq!(journal.undo_stack().len(), JOURNAL_LIMIT);
+        assert_eq!(journal.undo_stack()[0].command, "add 5");
+
+        journal.save_to_file(&path).unwrap();
+        let loaded = Journal::load_from_file(&path).unwrap();
+        assert_eq!(loaded.undo_stack(), journal.undo_stack());
+        assert!(Journal::load_from_file(dir.path().join("missing")).unwrap().undo_stack().is_empty());
+    }
+}
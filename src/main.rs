@@ -7,34 +7,75 @@
 //! # Features
 //!
 //! - Add todos with optional due dates, categories, and priorities
-//! - List todos with various filtering options
+//! - List todos with various filtering options and filter expressions
+//! - Saved views that store list filters under a name
+//! - Interactive full-screen task list (`rtodo tui`)
 //! - Search todos by text content with regex support
 //! - Mark todos as complete or incomplete
-//! - Edit existing todos
-//! - Organize todos by categories
+//! - Bulk changes on ID lists, ranges and filter expressions, confirmed once
+//! - Edit existing todos, with flags or as a document in `$EDITOR`
+//! - Organize todos by categories and tags
 //! - View todos by due dates (today, overdue)
-//! - Natural language date parsing ("tomorrow", "next Friday")
+//! - Scheduled and wait dates, with an "available today" view
+//! - Urgency scores and `rtodo next` to pick what to work on now
+//! - Natural language date parsing ("tomorrow", "next Friday 9am")
+//! - Recurring tasks that respawn with a shifted due date when completed
+//! - Subtasks with tree-shaped listing and progress roll-up
+//! - Task dependencies with blocked/ready filtering
+//! - JSON (default) or SQLite storage, with migration between the two
+//! - Undo and redo of changes through an operation journal
+//! - Trash bin for removed tasks, with restore and automatic expiry
+//! - Archive of completed tasks, with unarchive and purge
+//! - Import and export in todo.txt, iCalendar (VTODO), Taskwarrior and CSV formats
+//! - Markdown checklists for reports
 //! - Colorized terminal output for better readability
+//! - TOML configuration file for defaults and behaviour (`rtodo config`)
+//! - JSON and JSON Lines output for scripts (`--output json|jsonl`)
 //!
 //! # Usage
 //!
 //! ```bash
 //! rtodo add "Buy groceries" --due tomorrow --category personal
 //! rtodo list --pending --category work
+//! rtodo list 'category:work and (priority:high or due<friday)'
 //! rtodo complete 1
 //! rtodo search "project" --regex
 //! ```
 
-use anyhow::Result;
-use chrono::{DateTime, Local, NaiveDate, TimeZone};
-use chrono_english;
-use clap::{Parser, Subcommand, ValueEnum};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Timelike};
+use chrono_english::Dialect;
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use colored::*;
-use std::io::{self, Write};
-use std::path::PathBuf;
-
+use regex::Regex;
+use std::collections::{BTreeSet, HashSet};
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+mod archive;
+mod config;
+mod editor;
+mod formats;
+mod journal;
 mod models;
-use models::{Priority, TaskUpdate, TodoList};
+mod output;
+mod query;
+mod schema;
+mod storage;
+mod tui;
+mod urgency;
+mod views;
+use config::{ColorChoice, Config};
+use journal::{Journal, Operation};
+use models::{extract_tags, normalize_tag, Priority, Recurrence, TaskUpdate, TodoList, TrashedTask};
+use output::{
+    CategoryRecord, ErrorRecord, HistoryRecord, OutputFormat, RecurringRecord, SummaryRecord, TagRecord, TaskRecord,
+    TrashRecord, ViewRecord,
+};
+use query::{Condition, Query, Status};
+use storage::{Backend, ExternalChange, JsonStorage, StatusFilter, Storage, StorageLock, TaskFilter};
+use urgency::{Urgency, Weights};
+use views::Views;
 
 /// Main CLI structure for parsing command line arguments
 ///
@@ -43,16 +84,38 @@ use models::{Priority, TaskUpdate, TodoList};
 /// as well as the subcommand to execute.
 #[derive(Parser)]
 #[command(name = "rtodo")]
-#[command(about = "A simple and efficient todo list CLI written in Rust")]
+#[command(about = "A simple and efficient todo list CLI written in Rust", long_about = None)]
 #[command(version, author)]
 struct Cli {
     /// Enable verbose output
     #[arg(short, long, global = true)]
     verbose: bool,
 
-    /// Custom config file path
+    /// Data file path (default: data_file from the config file, otherwise ~/.todo-cli/tasks.json)
     #[arg(short = 'f', long = "file", global = true)]
-    config_file: Option<PathBuf>,
+    data_file: Option<PathBuf>,
+
+    /// Configuration file (default: ~/.config/rtodo/config.toml)
+    #[arg(long, global = true, env = "RTODO_CONFIG", value_name = "PATH")]
+    config: Option<PathBuf>,
+
+    /// Storage backend (default: inferred from the file extension, otherwise json)
+    #[arg(long, value_enum, global = true)]
+    storage: Option<StorageArg>,
+
+    /// Output format for listing commands
+    #[arg(long, value_enum, global = true, default_value = "text")]
+    output: OutputArg,
+
+    /// Days removed tasks stay in the trash before they are deleted for good (0 keeps them;
+    /// default: the trash_days config key, otherwise 30)
+    #[arg(long, global = true, env = "RTODO_TRASH_DAYS", value_name = "DAYS")]
+    trash_days: Option<u32>,
+
+    /// Extra urgency for a category, as CATEGORY=WEIGHT (comma-separated or repeated);
+    /// overrides the category_weights config key for that category
+    #[arg(long, global = true, env = "RTODO_CATEGORY_WEIGHTS", value_delimiter = ',', value_name = "CATEGORY=WEIGHT")]
+    category_weight: Vec<String>,
 
     #[command(subcommand)]
     command: Option<Commands>,
@@ -82,6 +145,79 @@ impl From<PriorityArg> for Priority {
     }
 }
 
+/// Storage backends for command line argument parsing
+///
+/// Maps to the `Backend` enum used by the storage layer.
+#[derive(Clone, Copy, ValueEnum)]
+enum StorageArg {
+    /// Single JSON file (default)
+    Json,
+    /// SQLite database
+    Sqlite,
+}
+
+impl From<StorageArg> for Backend {
+    fn from(arg: StorageArg) -> Self {
+        match arg {
+            StorageArg::Json => Backend::Json,
+            StorageArg::Sqlite => Backend::Sqlite,
+        }
+    }
+}
+
+/// Output formats for command line argument parsing
+///
+/// Maps to the `OutputFormat` enum used by the output module.
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputArg {
+    /// Coloured human-readable text
+    Text,
+    /// A single JSON document
+    Json,
+    /// One JSON object per line
+    Jsonl,
+}
+
+impl From<OutputArg> for OutputFormat {
+    fn from(arg: OutputArg) -> Self {
+        match arg {
+            OutputArg::Text => OutputFormat::Text,
+            OutputArg::Json => OutputFormat::Json,
+            OutputArg::Jsonl => OutputFormat::Jsonl,
+        }
+    }
+}
+
+/// Import/export formats for command line argument parsing
+///
+/// Maps to the `Format` enum used by the formats module.
+#[derive(Clone, Copy, ValueEnum)]
+enum FormatArg {
+    /// todo.txt, one task per line
+    #[value(name = "todotxt")]
+    TodoTxt,
+    /// iCalendar VTODO entries (.ics)
+    Ics,
+    /// Taskwarrior JSON (`task export` / `task import`)
+    Taskwarrior,
+    /// CSV with a header row
+    Csv,
+    /// Markdown checklists grouped by category (export only)
+    Markdown,
+}
+
+impl From<FormatArg> for formats::Format {
+    fn from(arg: FormatArg) -> Self {
+        match arg {
+            FormatArg::TodoTxt => formats::Format::TodoTxt,
+            FormatArg::Ics => formats::Format::Ics,
+            FormatArg::Taskwarrior => formats::Format::Taskwarrior,
+            FormatArg::Csv => formats::Format::Csv,
+            FormatArg::Markdown => formats::Format::Markdown,
+        }
+    }
+}
+
 /// Fields available for sorting tasks
 ///
 /// This enum defines the different fields by which tasks can be sorted
@@ -92,57 +228,149 @@ enum SortField {
     Created,
     /// Sort by due date (tasks without due dates appear last)
     Due,
+    /// Sort by scheduled date (tasks without one appear last)
+    Scheduled,
+    /// Sort by wait date (tasks without one appear last)
+    Wait,
     /// Sort by priority (High -> Medium -> Low)
     Priority,
     /// Sort by task title (alphabetical)
     Title,
+    /// Sort by completion date (tasks that aren't completed appear last)
+    Completed,
+    /// Sort by urgency score (most urgent first)
+    Urgency,
+}
+
+/// Arguments of `list`, which saved views store and parse again when shown
+#[derive(Parser)]
+#[command(name = "list")]
+struct ListArgs {
+    /// Filter expression, e.g. 'category:work and (priority:high or due<friday)'
+    #[arg(value_name = "QUERY")]
+    query: Vec<String>,
+    #[command(flatten)]
+    filters: ListFilters,
+}
+
+/// The tasks a command acts on: IDs and ranges, or a filter expression
+#[derive(Args)]
+struct TaskSelection {
+    /// Task ID, or a list of IDs and ranges such as 1,4,7-12
+    #[arg(value_name = "IDS")]
+    ids: Option<String>,
+    /// Act on the tasks matching a filter expression instead of IDs
+    #[arg(long = "where", value_name = "QUERY")]
+    filter: Option<String>,
+    /// Don't ask before changing several tasks (or removing one)
+    #[arg(long)]
+    confirm: bool,
+}
+
+/// Filter and sort options shared by `list` and `export`
+#[derive(Args)]
+struct ListFilters {
+    /// Show only completed items
+    #[arg(short, long, conflicts_with = "pending")]
+    completed: bool,
+    /// Show only pending items
+    #[arg(short, long, conflicts_with = "completed")]
+    pending: bool,
+    /// Filter by category
+    #[arg(short = 'C', long)]
+    category: Option<String>,
+    /// Filter by priority
+    #[arg(short = 'P', long, value_enum)]
+    priority: Option<PriorityArg>,
+    /// Only show tasks with this tag (can be repeated; all must match)
+    #[arg(short = 't', long)]
+    tag: Vec<String>,
+    /// Hide tasks with this tag (can be repeated)
+    #[arg(long)]
+    exclude_tag: Vec<String>,
+    /// Show overdue tasks only
+    #[arg(short, long)]
+    overdue: bool,
+    /// Show tasks due within a week
+    #[arg(short = 'd', long)]
+    due_soon: bool,
+    /// Show only tasks blocked by open dependencies
+    #[arg(short = 'b', long, conflicts_with = "ready")]
+    blocked: bool,
+    /// Show only pending tasks whose dependencies are all completed
+    #[arg(long, conflicts_with = "blocked")]
+    ready: bool,
+    /// Show only tasks hidden until their wait date (hidden from `list` otherwise)
+    #[arg(short = 'w', long, conflicts_with = "completed")]
+    waiting: bool,
+    /// Sort tasks by field
+    #[arg(short = 's', long, value_enum)]
+    sort_by: Option<SortField>,
+    /// Reverse sort order (descending)
+    #[arg(short = 'r', long)]
+    reverse: bool,
+    /// Show archived tasks instead of the todo list
+    #[arg(long)]
+    archived: bool,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// Add a new todo item
     Add {
-        /// The todo item title
-        title: String,
+        /// The todo item title (words written as +tag become tags)
+        #[arg(required_unless_present = "editor")]
+        title: Option<String>,
+        /// Tags to attach, written as +tag
+        tags: Vec<String>,
         /// Optional description
         #[arg(short, long)]
         description: Option<String>,
         /// Optional due date (YYYY-MM-DD format)
         #[arg(short = 'D', long)]
         due: Option<String>,
+        /// Date to start working on the task
+        #[arg(long)]
+        scheduled: Option<String>,
+        /// Hide the task from `list` until this date
+        #[arg(long)]
+        wait: Option<String>,
         /// Optional category
         #[arg(short, long)]
         category: Option<String>,
-        /// Task priority
-        #[arg(short, long, value_enum, default_value = "medium")]
-        priority: PriorityArg,
+        /// Task priority (default: medium, or default_priority from the config file)
+        #[arg(short, long, value_enum)]
+        priority: Option<PriorityArg>,
+        /// Repeat the task (daily, weekly[:mon,thu], monthly:<day>, after:<days>)
+        #[arg(short = 'R', long)]
+        repeat: Option<String>,
+        /// Make this a subtask of the task with the given ID
+        #[arg(long)]
+        parent: Option<u32>,
+        /// IDs of tasks that must be completed first (comma-separated or repeated)
+        #[arg(long, value_delimiter = ',')]
+        depends_on: Vec<u32>,
+        /// Write the task in $EDITOR, starting from the other options
+        #[arg(long)]
+        editor: bool,
     },
     /// List all todo items
     List {
-        /// Show only completed items
-        #[arg(short, long, conflicts_with = "pending")]
-        completed: bool,
-        /// Show only pending items
-        #[arg(short, long, conflicts_with = "completed")]
-        pending: bool,
-        /// Filter by category
-        #[arg(short = 'C', long)]
-        category: Option<String>,
-        /// Filter by priority
-        #[arg(short = 'P', long, value_enum)]
-        priority: Option<PriorityArg>,
-        /// Show overdue tasks only
-        #[arg(short, long)]
-        overdue: bool,
-        /// Show tasks due within a week
-        #[arg(short = 'd', long)]
-        due_soon: bool,
-        /// Sort tasks by field
-        #[arg(short = 's', long, value_enum)]
-        sort_by: Option<SortField>,
-        /// Reverse sort order (descending)
-        #[arg(short = 'r', long)]
-        reverse: bool,
+        #[command(flatten)]
+        args: ListArgs,
+    },
+    /// Show a saved view, or manage saved views
+    #[command(args_conflicts_with_subcommands = true)]
+    View {
+        /// Name of the view to show (default: list the saved views)
+        name: Option<String>,
+        #[command(subcommand)]
+        action: Option<ViewAction>,
+    },
+    /// Show, restore or delete removed tasks
+    Trash {
+        #[command(subcommand)]
+        action: Option<TrashAction>,
     },
     /// Search for todo items by text
     Search {
@@ -166,44 +394,58 @@ enum Commands {
         /// Filter by priority
         #[arg(short = 'P', long, value_enum)]
         priority: Option<PriorityArg>,
+        /// Only show tasks with this tag (can be repeated; all must match)
+        #[arg(short = 't', long)]
+        tag: Vec<String>,
+        /// Hide tasks with this tag (can be repeated)
+        #[arg(long)]
+        exclude_tag: Vec<String>,
         /// Show overdue tasks only
         #[arg(short, long)]
         overdue: bool,
         /// Show tasks due within a week
         #[arg(short = 'd', long)]
         due_soon: bool,
+        /// Only show tasks matching a filter expression
+        #[arg(long = "where", value_name = "QUERY")]
+        filter: Option<String>,
         /// Sort tasks by field
         #[arg(short = 's', long, value_enum)]
         sort_by: Option<SortField>,
         /// Reverse sort order (descending)
         #[arg(short = 'r', long)]
         reverse: bool,
+        /// Search archived tasks instead of the todo list
+        #[arg(long)]
+        archived: bool,
     },
     /// Mark a todo item as completed
     Complete {
-        /// The ID of the todo item to complete
-        id: Option<u32>,
+        #[command(flatten)]
+        selection: TaskSelection,
         /// Complete all pending tasks
-        #[arg(long, conflicts_with = "id")]
+        #[arg(long, conflicts_with_all = ["ids", "filter"])]
         all: bool,
     },
     /// Mark a todo item as incomplete
     Incomplete {
-        /// The ID of the todo item to mark as incomplete
-        id: u32,
+        #[command(flatten)]
+        selection: TaskSelection,
     },
-    /// Remove a todo item
+    /// Move todo items to the trash
     Remove {
-        /// The ID of the todo item to remove
-        id: u32,
-        /// Confirm destructive operation
-        #[arg(long)]
-        confirm: bool,
+        #[command(flatten)]
+        selection: TaskSelection,
     },
     /// Edit an existing todo item
     Edit {
-        /// The ID of the todo item to edit
-        id: u32,
+        #[command(flatten)]
+        selection: TaskSelection,
+        /// Tags to add, written as +tag
+        tags: Vec<String>,
+        /// Remove a tag (can be repeated)
+        #[arg(long)]
+        untag: Vec<String>,
         /// New title
         #[arg(short, long)]
         title: Option<String>,
@@ -213,15 +455,36 @@ enum Commands {
         /// New due date (YYYY-MM-DD format, use 'none' to clear)
         #[arg(short = 'D', long)]
         due: Option<String>,
+        /// New scheduled date (use 'none' to clear)
+        #[arg(long)]
+        scheduled: Option<String>,
+        /// New wait date (use 'none' to clear)
+        #[arg(long)]
+        wait: Option<String>,
         /// New category (use 'none' to clear)
         #[arg(short, long)]
         category: Option<String>,
         /// New priority
         #[arg(short, long, value_enum)]
         priority: Option<PriorityArg>,
+        /// New recurrence rule (use 'none' to stop repeating)
+        #[arg(short = 'R', long)]
+        repeat: Option<String>,
+        /// New parent task ID (use 'none' to make it a top-level task)
+        #[arg(long)]
+        parent: Option<String>,
+        /// Add dependencies on other tasks (comma-separated or repeated)
+        #[arg(long, value_delimiter = ',')]
+        depends_on: Vec<u32>,
+        /// Remove dependencies on other tasks (comma-separated or repeated)
+        #[arg(long, value_delimiter = ',')]
+        remove_depends_on: Vec<u32>,
         /// Mark as incomplete
         #[arg(long)]
         incomplete: bool,
+        /// Edit the task as a document in $EDITOR, after applying the other options
+        #[arg(long)]
+        editor: bool,
     },
     /// List all categories with task counts
     Categories,
@@ -232,6 +495,116 @@ enum Commands {
         /// New category name
         new_name: String,
     },
+    /// List all tags with task counts
+    Tags,
+    /// Rename a tag across all tasks
+    RenameTag {
+        /// Current tag name
+        old_name: String,
+        /// New tag name
+        new_name: String,
+    },
+    /// Report tasks completed within a date window (default: the last 7 days)
+    Done {
+        /// Start of the window, inclusive (YYYY-MM-DD or natural language)
+        #[arg(long)]
+        since: Option<String>,
+        /// End of the window, inclusive (YYYY-MM-DD or natural language)
+        #[arg(long)]
+        until: Option<String>,
+    },
+    /// List recurring task series
+    Recurring,
+    /// Stop a recurring task series (completed instances are kept)
+    StopRecurring {
+        /// The ID of any task in the series
+        id: u32,
+    },
+    /// Move completed tasks into the archive
+    Archive {
+        /// Only archive tasks completed more than this many days ago
+        #[arg(long, value_name = "DAYS")]
+        older_than: Option<u32>,
+        /// Show what would be archived without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Move an archived task and its subtasks back into the todo list
+    Unarchive {
+        /// The ID of the archived task
+        id: u32,
+    },
+    /// Permanently delete archived tasks completed before a date
+    Purge {
+        /// Delete tasks completed before this date (YYYY-MM-DD or natural language)
+        #[arg(long)]
+        before: String,
+        /// Don't ask before deleting
+        #[arg(long)]
+        confirm: bool,
+    },
+    /// Copy all tasks into another storage backend
+    MigrateStorage {
+        /// Backend to migrate to
+        #[arg(long, value_enum)]
+        to: StorageArg,
+        /// Destination path (default: the current file with the new backend's extension)
+        #[arg(long)]
+        dest: Option<PathBuf>,
+        /// Overwrite a destination that already contains tasks
+        #[arg(long)]
+        force: bool,
+    },
+    /// Add tasks from a file written by another tool
+    Import {
+        /// File to import ("-" reads standard input)
+        path: PathBuf,
+        /// Format of the file
+        #[arg(long, value_enum)]
+        format: FormatArg,
+        /// Read a task field from a differently named CSV column (can be repeated)
+        #[arg(long = "column", value_name = "FIELD=HEADER")]
+        columns: Vec<String>,
+    },
+    /// Write tasks in a format other tools can read
+    Export {
+        /// Output format
+        #[arg(long, value_enum)]
+        format: FormatArg,
+        /// File to write (default: standard output)
+        path: Option<PathBuf>,
+        /// Only export tasks matching a filter expression
+        #[arg(long = "where", value_name = "QUERY")]
+        filter: Option<String>,
+        #[command(flatten)]
+        filters: ListFilters,
+    },
+    /// Undo the most recent changes
+    Undo {
+        /// Number of operations to undo
+        #[arg(default_value = "1")]
+        steps: usize,
+        /// Show what would be undone without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Redo changes that were undone
+    Redo {
+        /// Number of operations to redo
+        #[arg(default_value = "1")]
+        steps: usize,
+        /// Show what would be redone without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Browse and change tasks in an interactive full-screen list
+    Tui,
+    /// Show recent changes that can be undone
+    History {
+        /// Maximum number of operations to show
+        #[arg(short = 'n', long, default_value = "10")]
+        limit: usize,
+    },
     /// Show tasks due today
     DueToday {
         /// Sort tasks by field
@@ -250,6 +623,88 @@ enum Commands {
         #[arg(short = 'r', long)]
         reverse: bool,
     },
+    /// Show tasks that can be worked on today: not waiting, blocked or scheduled later
+    Available {
+        /// Sort tasks by field
+        #[arg(short = 's', long, value_enum)]
+        sort_by: Option<SortField>,
+        /// Reverse sort order (descending)
+        #[arg(short = 'r', long)]
+        reverse: bool,
+    },
+    /// Show the most urgent tasks that can be worked on now
+    Next {
+        /// Number of tasks to show
+        #[arg(default_value = "5")]
+        count: usize,
+    },
+    /// Show or change settings in the configuration file
+    Config {
+        #[command(subcommand)]
+        action: Option<ConfigAction>,
+    },
+}
+
+/// Actions of the `view` command
+#[derive(Subcommand)]
+enum ViewAction {
+    /// Save `list` options and query under a name, replacing any view with that name
+    Save {
+        /// Name of the view
+        name: String,
+        /// Options and query as given to `list`, e.g. --pending -C work --sort-by due
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true, value_name = "LIST ARGS")]
+        args: Vec<String>,
+    },
+    /// List saved views
+    List,
+    /// Delete a saved view
+    Delete {
+        /// Name of the view
+        name: String,
+    },
+}
+
+/// Actions of the `trash` command
+#[derive(Subcommand)]
+enum TrashAction {
+    /// List removed tasks, most recently removed first (default)
+    List,
+    /// Move a removed task back into the todo list
+    Restore {
+        /// The ID the task had when it was removed
+        id: u32,
+    },
+    /// Permanently delete all removed tasks
+    Empty {
+        /// Don't ask before deleting
+        #[arg(long)]
+        confirm: bool,
+    },
+}
+
+/// Actions of the `config` command
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// List every setting with its value (default)
+    List,
+    /// Show the value of a setting
+    Get {
+        /// The setting, e.g. default_priority
+        key: String,
+    },
+    /// Change a setting
+    Set {
+        /// The setting, e.g. default_priority
+        key: String,
+        /// The new value
+        value: String,
+    },
+    /// Go back to the default value of a setting
+    Unset {
+        /// The setting, e.g. default_priority
+        key: String,
+    },
 }
 
 /// Parse a date string using natural language or ISO format
@@ -258,11 +713,13 @@ enum Commands {
 /// 1. Natural language parsing using chrono-english (e.g., "tomorrow", "next Friday")
 /// 2. ISO format parsing (YYYY-MM-DD)
 ///
-/// All parsed dates are set to end of day (23:59:59) for consistency in due date handling.
+/// The time of day is dropped: dates are set to end of day (23:59:59), see
+/// `parse_due` for dates that may carry a time.
 ///
 /// # Arguments
 ///
 /// * `date_str` - The date string to parse
+/// * `dialect` - Whether 02/11 is read month first (US) or day first (UK)
 ///
 /// # Returns
 ///
@@ -272,22 +729,73 @@ enum Commands {
 /// # Examples
 ///
 /// ```
-/// let tomorrow = parse_date("tomorrow")?;
-/// let specific = parse_date("2024-12-31")?;
-/// let natural = parse_date("next Friday")?;
+/// let tomorrow = parse_date("tomorrow", Dialect::Us)?;
+/// let specific = parse_date("2024-12-31", Dialect::Us)?;
+/// let natural = parse_date("next Friday", Dialect::Us)?;
+/// ```
+fn parse_date(date_str: &str, dialect: Dialect) -> Result<DateTime<Local>> {
+    let (date, _) = parse_due(date_str, dialect)?;
+    Ok(end_of_day(date))
+}
+
+/// Parse a due date, keeping the time of day if one is given
+///
+/// Accepts everything `parse_date` does, plus times such as "tomorrow 15:00",
+/// "friday at 9am", "2026-11-02T09:30" or "in 3 hours". Dates without a time
+/// are set to end of day (23:59:59).
+///
+/// # Returns
+///
+/// * `Ok((DateTime<Local>, bool))` - The due date and whether it has a time of day
+/// * `Err(anyhow::Error)` - The date could not be parsed
+///
+/// # Examples
+///
+/// ```
+/// let (due, has_time) = parse_due("tomorrow 15:00", Dialect::Us)?;  // 15:00 tomorrow, true
+/// let (due, has_time) = parse_due("2024-12-31", Dialect::Us)?;      // 23:59:59, false
 /// ```
-fn parse_date(date_str: &str) -> Result<DateTime<Local>> {
+fn parse_due(date_str: &str, dialect: Dialect) -> Result<(DateTime<Local>, bool)> {
+    let trimmed = date_str.trim();
+    let text = trimmed.strip_prefix("in ").unwrap_or(trimmed).replace(" at ", " ");
+    let has_time = Regex::new(r"(?i)\d:\d\d|\d\s*[ap]\.?m\b|\d\s*(hours?|hrs?|minutes?|mins?)\b")
+        .expect("valid regex")
+        .is_match(&text);
+
     // First try natural language parsing
-    if let Ok(parsed) = chrono_english::parse_date_string(date_str, Local::now(), chrono_english::Dialect::Us) {
-        // Set time to end of day (23:59:59) for consistency
-        let end_of_day = parsed.date_naive().and_hms_opt(23, 59, 59).unwrap();
-        return Ok(Local.from_local_datetime(&end_of_day).unwrap());
+    let parsed = match chrono_english::parse_date_string(&text, Local::now(), dialect) {
+        Ok(parsed) => parsed,
+        // Fallback to the original YYYY-MM-DD format
+        Err(_) => {
+            let naive_date = NaiveDate::parse_from_str(trimmed, "%Y-%m-%d")?;
+            let naive_datetime = naive_date.and_hms_opt(23, 59, 59).unwrap();
+            return Ok((Local.from_local_datetime(&naive_datetime).unwrap(), false));
+        }
+    };
+    if has_time {
+        // Relative times like "3 hours" count from now, so drop the seconds
+        Ok((parsed.with_second(0).and_then(|date| date.with_nanosecond(0)).unwrap_or(parsed), true))
+    } else {
+        Ok((end_of_day(parsed), false))
     }
+}
 
-    // Fallback to the original YYYY-MM-DD format
-    let naive_date = NaiveDate::parse_from_str(date_str, "%Y-%m-%d")?;
-    let naive_datetime = naive_date.and_hms_opt(23, 59, 59).unwrap();
-    Ok(Local.from_local_datetime(&naive_datetime).unwrap())
+/// Parse a scheduled or wait date, which starts at the beginning of its day unless a time is given
+fn parse_start(date_str: &str, dialect: Dialect) -> Result<DateTime<Local>> {
+    let (date, has_time) = parse_due(date_str, dialect)?;
+    Ok(if has_time { date } else { start_of_day(date) })
+}
+
+/// Get the end (23:59:59) of the day containing a date
+fn end_of_day(date: DateTime<Local>) -> DateTime<Local> {
+    let end = date.date_naive().and_hms_opt(23, 59, 59).unwrap();
+    Local.from_local_datetime(&end).earliest().unwrap_or(date)
+}
+
+/// Get the start (00:00:00) of the day containing a date
+fn start_of_day(date: DateTime<Local>) -> DateTime<Local> {
+    let midnight = date.date_naive().and_hms_opt(0, 0, 0).unwrap();
+    Local.from_local_datetime(&midnight).earliest().unwrap_or(date)
 }
 
 /// Highlight search query matches in text with colored output
@@ -314,7 +822,6 @@ fn parse_date(date_str: &str) -> Result<DateTime<Local>> {
 /// // Returns "Buy" with yellow background + "groceries" in normal colors
 /// ```
 fn highlight_text(text: &str, query: &str, case_insensitive: bool, use_regex: bool) -> String {
-    use regex::Regex;
     use colored::*;
 
     if use_regex {
@@ -369,13 +876,10 @@ fn highlight_text(text: &str, query: &str, case_insensitive: bool, use_regex: bo
     text.to_string()
 }
 
-fn print_task_with_highlight(task: &models::Task, verbose: bool, query: &str, case_insensitive: bool, use_regex: bool) {
+fn print_task_with_highlight(task: &models::Task, verbose: bool, query: &str, case_insensitive: bool, use_regex: bool, config: &Config) {
     let status_icon = if task.completed { "✓".green() } else { "○".yellow() };
-    let priority_color = match task.priority {
-        Priority::High => "red",
-        Priority::Medium => "yellow",
-        Priority::Low => "blue",
-    };
+    let theme = config.theme();
+    let priority_color = theme.priority(&task.priority);
 
     print!("{} [{}] ", status_icon, task.id.to_string().cyan());
 
@@ -384,20 +888,28 @@ fn print_task_with_highlight(task: &models::Task, verbose: bool, query: &str, ca
     print!("{}", highlighted_title.bold());
 
     if let Some(category) = &task.category {
-        print!(" {}", format!("#{}", category).green());
+        print!(" {}", format!("#{}", category).color(theme.category));
+    }
+
+    for tag in &task.tags {
+        print!(" {}", format!("+{}", tag).bright_blue());
+    }
+
+    if task.is_recurring() {
+        print!(" {}", "↻".magenta());
     }
 
     // Show time until due in the main line
     if let Some(due_date) = task.due_date {
-        let time_until = format_time_until_due(due_date);
+        let time_until = format_time_until_due(due_date, task.due_has_time);
         if task.is_overdue() {
-            print!(" ({})", time_until.red());
+            print!(" ({})", time_until.color(theme.overdue));
         } else if task.is_due_today() {
-            print!(" ({})", time_until.yellow().bold());
-        } else if task.is_due_soon() {
-            print!(" ({})", time_until.yellow());
+            print!(" ({})", time_until.color(theme.due_soon).bold());
+        } else if task.is_due_soon(config.due_soon_window()) {
+            print!(" ({})", time_until.color(theme.due_soon));
         } else {
-            print!(" ({})", time_until.blue());
+            print!(" ({})", time_until.color(theme.due));
         }
     }
 
@@ -409,17 +921,20 @@ fn print_task_with_highlight(task: &models::Task, verbose: bool, query: &str, ca
             let highlighted_desc = highlight_text(description, query, case_insensitive, use_regex);
             println!("    {}", highlighted_desc.dimmed());
         }
+        if let Some(recurrence) = &task.recurrence {
+            println!("    {}: {}", "Repeats".magenta(), recurrence.to_string().magenta());
+        }
         if let Some(due_date) = task.due_date {
-            let due_str = due_date.format("%Y-%m-%d").to_string();
-            let time_until = format_time_until_due(due_date);
+            let due_str = task.due_date_string().unwrap_or_default();
+            let time_until = format_time_until_due(due_date, task.due_has_time);
             if task.is_overdue() {
-                println!("    {}: {} ({})", "Due".red(), due_str.red(), time_until.red());
+                println!("    {}: {} ({})", "Due".color(theme.overdue), due_str.color(theme.overdue), time_until.color(theme.overdue));
             } else if task.is_due_today() {
-                println!("    {}: {} ({})", "Due".yellow().bold(), due_str.yellow().bold(), time_until.yellow().bold());
-            } else if task.is_due_soon() {
-                println!("    {}: {} ({})", "Due".yellow(), due_str.yellow(), time_until.yellow());
+                println!("    {}: {} ({})", "Due".color(theme.due_soon).bold(), due_str.color(theme.due_soon).bold(), time_until.color(theme.due_soon).bold());
+            } else if task.is_due_soon(config.due_soon_window()) {
+                println!("    {}: {} ({})", "Due".color(theme.due_soon), due_str.color(theme.due_soon), time_until.color(theme.due_soon));
             } else {
-                println!("    {}: {} ({})", "Due".blue(), due_str.blue(), time_until.blue());
+                println!("    {}: {} ({})", "Due".color(theme.due), due_str.color(theme.due), time_until.color(theme.due));
             }
         }
     }
@@ -427,15 +942,18 @@ fn print_task_with_highlight(task: &models::Task, verbose: bool, query: &str, ca
 
 /// Sort a vector of task references by the specified field
 ///
-/// This function sorts tasks by different criteria (creation date, due date, priority, or title).
-/// For due date sorting, tasks without due dates appear after tasks with due dates.
-/// For priority sorting, the order is High -> Medium -> Low when not reversed.
+/// This function sorts tasks by different criteria (creation date, due date, priority, title,
+/// completion date or urgency). For due date and completion date sorting, tasks without the date
+/// appear after tasks with it.
+/// For priority sorting, the order is High -> Medium -> Low when not reversed, and for
+/// urgency the most urgent task comes first.
 ///
 /// # Arguments
 ///
 /// * `tasks` - Vector of task references to sort
 /// * `sort_by` - Optional field to sort by (None means no sorting)
 /// * `reverse` - Whether to reverse the sort order (descending instead of ascending)
+/// * `weights` - Category weights used for the urgency score
 ///
 /// # Returns
 ///
@@ -444,25 +962,22 @@ fn print_task_with_highlight(task: &models::Task, verbose: bool, query: &str, ca
 /// # Examples
 ///
 /// ```
-/// let sorted = sort_tasks(tasks, Some(SortField::Priority), false);
+/// let sorted = sort_tasks(tasks, Some(SortField::Priority), false, &weights);
 /// // Returns tasks sorted High -> Medium -> Low priority
 ///
-/// let reverse_sorted = sort_tasks(tasks, Some(SortField::Due), true);
+/// let reverse_sorted = sort_tasks(tasks, Some(SortField::Due), true, &weights);
 /// // Returns tasks sorted by due date, latest first
 /// ```
-fn sort_tasks(mut tasks: Vec<&models::Task>, sort_by: Option<SortField>, reverse: bool) -> Vec<&models::Task> {
+fn sort_tasks<'a>(mut tasks: Vec<&'a models::Task>, sort_by: Option<SortField>, reverse: bool, weights: &Weights) -> Vec<&'a models::Task> {
     if let Some(field) = sort_by {
+        let now = Local::now();
+        let urgency = |task: &models::Task| Urgency::of(task, weights, now).total();
         tasks.sort_by(|a, b| {
             let ordering = match field {
                 SortField::Created => a.created_at.cmp(&b.created_at),
-                SortField::Due => {
-                    match (a.due_date, b.due_date) {
-                        (Some(a_due), Some(b_due)) => a_due.cmp(&b_due),
-                        (Some(_), None) => std::cmp::Ordering::Less,
-                        (None, Some(_)) => std::cmp::Ordering::Greater,
-                        (None, None) => std::cmp::Ordering::Equal,
-                    }
-                }
+                SortField::Due => compare_dates(a.due_date, b.due_date),
+                SortField::Scheduled => compare_dates(a.scheduled, b.scheduled),
+                SortField::Wait => compare_dates(a.wait, b.wait),
                 SortField::Priority => {
                     // High = 0, Medium = 1, Low = 2 for ascending priority order
                     let a_priority = match a.priority {
@@ -478,6 +993,8 @@ fn sort_tasks(mut tasks: Vec<&models::Task>, sort_by: Option<SortField>, reverse
                     a_priority.cmp(&b_priority)
                 }
                 SortField::Title => a.title.cmp(&b.title),
+                SortField::Completed => compare_dates(a.completed_at, b.completed_at),
+                SortField::Urgency => urgency(b).total_cmp(&urgency(a)),
             };
 
             if reverse {
@@ -490,6 +1007,225 @@ fn sort_tasks(mut tasks: Vec<&models::Task>, sort_by: Option<SortField>, reverse
     tasks
 }
 
+/// The sort field set as `default_sort` in the config file
+fn configured_sort(config: &Config) -> Result<Option<SortField>> {
+    let Some(name) = config.default_sort.as_deref() else {
+        return Ok(None);
+    };
+    SortField::from_str(name, true).map(Some).map_err(|_| {
+        let names: Vec<String> = SortField::value_variants()
+            .iter()
+            .filter_map(|field| field.to_possible_value())
+            .map(|value| value.get_name().to_string())
+            .collect();
+        anyhow!("Invalid default_sort '{}' in the config file (expected one of: {})", name, names.join(", "))
+    })
+}
+
+/// Order optional dates, earliest first and missing ones last
+fn compare_dates(a: Option<DateTime<Local>>, b: Option<DateTime<Local>>) -> std::cmp::Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    }
+}
+
+impl TaskSelection {
+    /// The task ID, if exactly one task is given by its ID
+    fn single_id(&self) -> Option<u32> {
+        match (&self.ids, &self.filter) {
+            (Some(ids), None) => ids.trim().parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.ids.is_none() && self.filter.is_none()
+    }
+
+    /// IDs of the selected tasks in ascending order
+    ///
+    /// IDs given one by one must exist, while ranges leave out IDs that
+    /// have no task (e.g. removed ones).
+    fn resolve(&self, todo_list: &TodoList, config: &Config) -> Result<Vec<u32>> {
+        match (&self.ids, &self.filter) {
+            (Some(_), Some(_)) => Err(anyhow!("Give either task IDs or --where, not both")),
+            (None, None) => Err(anyhow!("Specify task IDs (e.g. 1,4,7-12) or --where QUERY")),
+            (Some(spec), None) => {
+                let mut ids = BTreeSet::new();
+                for part in spec.split(',').map(str::trim) {
+                    let parse = |id: &str| {
+                        id.trim().parse::<u32>().map_err(|_| anyhow!("Invalid task ID '{}' in '{}'", id.trim(), spec))
+                    };
+                    match part.split_once('-') {
+                        Some((first, last)) => {
+                            let (first, last) = (parse(first)?, parse(last)?);
+                            if first > last {
+                                return Err(anyhow!("Invalid range '{}': the first ID is larger than the last", part));
+                            }
+                            let in_range = todo_list.get_all_tasks().iter().map(|task| task.id);
+                            ids.extend(in_range.filter(|id| (first..=last).contains(id)));
+                        }
+                        None => {
+                            let id = parse(part)?;
+                            if todo_list.get_task(id).is_none() {
+                                return Err(anyhow!("Task with ID {} not found", id));
+                            }
+                            ids.insert(id);
+                        }
+                    }
+                }
+                Ok(ids.into_iter().collect())
+            }
+            (None, Some(filter)) => {
+                let query = parse_query(filter, config.dialect())?.ok_or_else(|| anyhow!("The --where query is empty"))?;
+                Ok(todo_list.get_all_tasks().iter()
+                    .filter(|task| query.matches(task, todo_list, config.due_soon_window()))
+                    .map(|task| task.id)
+                    .collect())
+            }
+        }
+    }
+}
+
+impl ListArgs {
+    /// Parse the arguments saved in a view
+    fn from_saved(args: &[String]) -> Result<Self> {
+        Self::try_parse_from(std::iter::once("list").chain(args.iter().map(String::as_str)))
+            .map_err(|e| {
+                let message = e.to_string();
+                let first_line = message.lines().next().unwrap_or_default();
+                anyhow!("Invalid list arguments: {}", first_line.trim_start_matches("error: "))
+            })
+    }
+
+    /// Print the selected tasks under `heading`
+    #[allow(clippy::too_many_arguments)]
+    fn print(
+        self,
+        heading: &str,
+        storage: &dyn Storage,
+        todo_list: &TodoList,
+        weights: &Weights,
+        config: &Config,
+        format: OutputFormat,
+        verbose: bool,
+    ) -> Result<()> {
+        let query = parse_query(&self.query.join(" "), config.dialect())?;
+        let archive;
+        let (storage, todo_list): (&dyn Storage, &TodoList) = if self.filters.archived {
+            archive = load_archive(storage)?;
+            (&archive.0, &archive.1)
+        } else {
+            (storage, todo_list)
+        };
+        let show_waiting = self.filters.waiting || query.as_ref().is_some_and(|query| query.mentions(Status::Waiting));
+        let mut sorted_tasks = self.filters.select(storage, todo_list, query.as_ref(), weights, config)?;
+        if !show_waiting {
+            sorted_tasks.retain(|task| !task.is_waiting());
+        }
+
+        if format != OutputFormat::Text {
+            return output::print_tasks(format, &sorted_tasks, todo_list);
+        }
+        if sorted_tasks.is_empty() {
+            println!("{}", "No tasks found.".dimmed());
+        } else {
+            println!("{} ({} tasks):", heading.cyan().bold(), sorted_tasks.len());
+            print_task_tree(todo_list, &sorted_tasks, verbose, config);
+        }
+        Ok(())
+    }
+}
+
+impl ListFilters {
+    /// Select the matching tasks in display order
+    ///
+    /// The storage backend answers the indexable filters; the dependency
+    /// filters and the query expression are applied in memory afterwards.
+    fn select<'a>(
+        self,
+        storage: &dyn Storage,
+        todo_list: &'a TodoList,
+        query: Option<&Query>,
+        weights: &Weights,
+        config: &Config,
+    ) -> Result<Vec<&'a models::Task>> {
+        let status = if self.completed {
+            StatusFilter::Completed
+        } else if self.pending {
+            StatusFilter::Pending
+        } else if self.overdue {
+            StatusFilter::Overdue
+        } else if self.due_soon {
+            StatusFilter::DueSoon(config.due_soon_window())
+        } else {
+            StatusFilter::All
+        };
+        let strip = |tags: Vec<String>| -> Vec<String> {
+            tags.iter().map(|tag| tag.trim_start_matches('+').to_string()).collect()
+        };
+        let filter = TaskFilter {
+            status,
+            category: self.category,
+            priority: self.priority.map(Into::into),
+            tags: strip(self.tag),
+            exclude_tags: strip(self.exclude_tag),
+        };
+
+        let matching: HashSet<u32> = storage.query(todo_list, &filter)?.into_iter().collect();
+        let filtered_tasks: Vec<&models::Task> = todo_list.get_all_tasks().iter()
+            .filter(|task| matching.contains(&task.id))
+            .filter(|task| {
+                if self.blocked {
+                    task.is_blocked(todo_list)
+                } else if self.ready {
+                    !task.completed && !task.is_blocked(todo_list)
+                } else {
+                    true
+                }
+            })
+            .filter(|task| !self.waiting || task.is_waiting())
+            .filter(|task| query.is_none_or(|query| query.matches(task, todo_list, config.due_soon_window())))
+            .collect();
+
+        let sort_by = self.sort_by.or(configured_sort(config)?);
+        Ok(sort_tasks(filtered_tasks, sort_by, self.reverse, weights))
+    }
+}
+
+/// The archive's storage and tasks, for commands run with `--archived`
+fn load_archive(storage: &dyn Storage) -> Result<(JsonStorage, TodoList)> {
+    let archive = archive::open(storage.path());
+    let archived = archive.load()?;
+    Ok((archive, archived))
+}
+
+/// Save the todo list and, if it changed, the archive
+///
+/// The file that gains tasks is written first, so a failed save never loses
+/// tasks that were moved between the two.
+fn save_with_archive(
+    storage: &dyn Storage,
+    todo_list: &TodoList,
+    archive_before: &TodoList,
+    archived: &TodoList,
+) -> Result<()> {
+    let archive_storage = archive::open(storage.path());
+    let archive_changed = archived.get_all_tasks() != archive_before.get_all_tasks();
+    let archive_gains = archived.get_all_tasks().iter().any(|task| archive_before.get_task(task.id).is_none());
+    if archive_gains {
+        archive_storage.save(archived)?;
+    }
+    storage.save(todo_list)?;
+    if archive_changed && !archive_gains {
+        archive_storage.save(archived)?;
+    }
+    Ok(())
+}
+
 /// Prompt the user for confirmation of a potentially destructive action
 ///
 /// This function displays a message and waits for user input to confirm or deny
@@ -523,28 +1259,103 @@ fn confirm_action(message: &str) -> bool {
     matches!(input.trim().to_lowercase().as_str(), "y" | "yes")
 }
 
-fn load_todo_list(config_file: Option<PathBuf>) -> Result<TodoList> {
-    match config_file {
-        Some(path) => TodoList::load_from_file(path),
-        None => TodoList::load(),
+/// List the tasks a command is about to change and ask once before going ahead
+///
+/// The question is skipped if the user already confirmed with `--confirm`.
+fn confirm_selection(todo_list: &TodoList, task_ids: &[u32], action: &str, confirmed: bool) -> bool {
+    println!("Found {} task(s):", task_ids.len());
+    for task in task_ids.iter().filter_map(|id| todo_list.get_task(*id)) {
+        println!("  - [{}] {}", task.id, task.title);
+    }
+    confirmed || confirm_action(&format!("{} {} task(s)?", action, task_ids.len()))
+}
+
+/// Parse `+tag` command line arguments into tag names
+fn parse_tag_args(args: &[String]) -> Result<Vec<String>> {
+    args.iter()
+        .map(|arg| {
+            if !arg.starts_with('+') {
+                return Err(anyhow!("Unexpected argument '{}': tags must be written as +tag", arg));
+            }
+            normalize_tag(arg)
+        })
+        .collect()
+}
+
+/// Let the user edit a task as a document in their editor and apply the changes
+///
+/// If the document can't be read back, the edited text is kept in a file so
+/// the work isn't lost.
+fn edit_in_editor(todo_list: &mut TodoList, id: u32, lock: &StorageLock, dialect: Dialect) -> Result<()> {
+    let task = todo_list.get_task(id)
+        .cloned()
+        .ok_or_else(|| anyhow!("Task with ID {} not found", id))?;
+    // Other commands may run while the editor is open; saving checks they didn't change the file
+    lock.release();
+    let document = editor::edit(&editor::render(&task));
+    lock.reacquire()?;
+    let document = document?;
+    let update = editor::parse(&document, &task, |text| parse_due(text, dialect), |text| parse_start(text, dialect)).map_err(|e| {
+        match editor::keep_rejected(id, &document) {
+            Ok(kept) => anyhow!("{}. Your edit was saved to {}", e, kept.display()),
+            Err(_) => e,
+        }
+    })?;
+    todo_list.update_task(id, update)
+}
+
+/// Warn on stderr when a task about to be completed still has open dependencies
+fn warn_open_dependencies(todo_list: &TodoList, task_id: u32) {
+    let open_dependencies: Vec<String> = todo_list.get_open_dependencies(task_id)
+        .iter()
+        .map(|dep| format!("[{}] {}", dep.id, dep.title))
+        .collect();
+    if !open_dependencies.is_empty() {
+        eprintln!("{}: task {} depends on tasks that are still open: {}",
+            "Warning".yellow(),
+            task_id,
+            open_dependencies.join(", ")
+        );
     }
 }
 
-fn save_todo_list(todo_list: &TodoList, config_file: Option<PathBuf>) -> Result<()> {
-    match config_file {
-        Some(path) => todo_list.save_to_file(path),
-        None => todo_list.save(),
+/// Parse a filter expression from the command line; `None` if it is blank
+fn parse_query(input: &str, dialect: Dialect) -> Result<Option<Query>> {
+    if input.trim().is_empty() {
+        return Ok(None);
     }
+    Query::parse(input, dialect).map(Some).map_err(|e| anyhow!("{}", e))
+}
+
+/// Open the storage selected by the global `--storage` and `--file` options
+///
+/// Without `--storage`, the backend is inferred from the file extension
+/// (`.db`, `.sqlite` and `.sqlite3` use SQLite); without `--file`, the
+/// backend's default location is used.
+fn open_storage(storage: Option<StorageArg>, data_file: Option<PathBuf>) -> Result<Box<dyn Storage>> {
+    let backend = match (storage, &data_file) {
+        (Some(arg), _) => arg.into(),
+        (None, Some(path)) => Backend::from_path(path),
+        (None, None) => Backend::Json,
+    };
+    let path = match data_file {
+        Some(path) => path,
+        None => backend.default_path()?,
+    };
+    Ok(storage::open(backend, path))
 }
 
 /// Format a human-readable string describing time until or since due date
 ///
 /// This function calculates the time difference between now and a due date,
 /// returning a formatted string that describes the relationship in natural language.
+/// Due dates with a time of day are described in hours or minutes when they are
+/// less than a day away, and otherwise include the time they are due at.
 ///
 /// # Arguments
 ///
 /// * `due_date` - The due date to compare against current time
+/// * `has_time` - Whether the due date has a time of day
 ///
 /// # Returns
 ///
@@ -555,21 +1366,39 @@ fn save_todo_list(todo_list: &TodoList, config_file: Option<PathBuf>) -> Result<
 /// ```
 /// // If today is 2024-09-21:
 /// let due_today = Local::now().date_naive().and_hms_opt(23, 59, 59).unwrap();
-/// assert_eq!(format_time_until_due(Local.from_local_datetime(&due_today).unwrap()), "due today");
+/// assert_eq!(format_time_until_due(Local.from_local_datetime(&due_today).unwrap(), false), "due today");
 ///
 /// let due_tomorrow = due_today + Duration::days(1);
-/// assert_eq!(format_time_until_due(Local.from_local_datetime(&due_tomorrow).unwrap()), "due tomorrow");
+/// assert_eq!(format_time_until_due(Local.from_local_datetime(&due_tomorrow).unwrap(), false), "due tomorrow");
+///
+/// assert_eq!(format_time_until_due(Local::now() + Duration::minutes(185), true), "due in 3 hours");
 /// ```
-fn format_time_until_due(due_date: DateTime<Local>) -> String {
+fn format_time_until_due(due_date: DateTime<Local>, has_time: bool) -> String {
     let now = Local::now();
     let duration = due_date.signed_duration_since(now);
 
+    if has_time && duration.num_days() == 0 {
+        let count = |amount: i64, unit: &str| format!("{} {}{}", amount, unit, if amount == 1 { "" } else { "s" });
+        let minutes = (duration.num_seconds() + 30).div_euclid(60);
+        let amount = if minutes.abs() < 60 {
+            count(minutes.abs(), "minute")
+        } else {
+            count((minutes.abs() + 30) / 60, "hour")
+        };
+        return match minutes {
+            0 => "due now".to_string(),
+            minutes if minutes > 0 => format!("due in {}", amount),
+            _ => format!("{} overdue", amount),
+        };
+    }
+
+    let at = if has_time { format!(" at {}", due_date.format("%H:%M")) } else { String::new() };
     if duration.num_days() == 0 {
-        "due today".to_string()
+        format!("due today{}", at)
     } else if duration.num_days() == 1 {
-        "due tomorrow".to_string()
+        format!("due tomorrow{}", at)
     } else if duration.num_days() > 0 {
-        format!("due in {} days", duration.num_days())
+        format!("due in {} days{}", duration.num_days(), at)
     } else if duration.num_days() == -1 {
         "1 day overdue".to_string()
     } else {
@@ -577,32 +1406,86 @@ fn format_time_until_due(due_date: DateTime<Local>) -> String {
     }
 }
 
-fn print_task(task: &models::Task, verbose: bool) {
+/// Format an urgency score with the components that add to it, e.g. `14.3 = priority 6.0 + due 8.3`
+fn format_urgency(urgency: &Urgency) -> String {
+    let parts: Vec<String> = urgency
+        .components()
+        .iter()
+        .filter(|(_, value)| value.abs() >= 0.05)
+        .map(|(name, value)| format!("{} {:.1}", name, value))
+        .collect();
+    if parts.is_empty() {
+        format!("{:.1}", urgency.total())
+    } else {
+        format!("{:.1} = {}", urgency.total(), parts.join(" + "))
+    }
+}
+
+/// Format a scheduled or wait date, with the time unless it is the start of the day
+fn format_start(date: DateTime<Local>) -> String {
+    if date == start_of_day(date) {
+        date.format("%Y-%m-%d").to_string()
+    } else {
+        date.format("%Y-%m-%d %H:%M").to_string()
+    }
+}
+
+fn print_task(task: &models::Task, verbose: bool, todo_list: &TodoList, config: &Config) {
+    print_task_indented(task, verbose, 0, todo_list, config);
+}
+
+/// Print a single task nested `depth` levels deep
+///
+/// The todo list is used to show information that depends on other tasks,
+/// such as subtask progress and open dependencies.
+fn print_task_indented(task: &models::Task, verbose: bool, depth: usize, todo_list: &TodoList, config: &Config) {
+    let indent = "    ".repeat(depth);
     let status_icon = if task.completed { "✓".green() } else { "○".yellow() };
-    let priority_color = match task.priority {
-        Priority::High => "red",
-        Priority::Medium => "yellow",
-        Priority::Low => "blue",
-    };
+    let theme = config.theme();
+    let priority_color = theme.priority(&task.priority);
 
-    print!("{} [{}] ", status_icon, task.id.to_string().cyan());
+    print!("{}{} [{}] ", indent, status_icon, task.id.to_string().cyan());
     print!("{}", task.title.bold());
 
     if let Some(category) = &task.category {
-        print!(" {}", format!("#{}", category).green());
+        print!(" {}", format!("#{}", category).color(theme.category));
     }
 
-    // Show time until due in the main line
-    if let Some(due_date) = task.due_date {
-        let time_until = format_time_until_due(due_date);
+    for tag in &task.tags {
+        print!(" {}", format!("+{}", tag).bright_blue());
+    }
+
+    if task.is_recurring() {
+        print!(" {}", "↻".magenta());
+    }
+
+    if let Some((done, total)) = todo_list.subtask_progress(task.id) {
+        print!(" {}", format!("({}/{} subtasks done)", done, total).cyan());
+    }
+
+    if task.is_blocked(todo_list) {
+        let open_ids: Vec<String> = todo_list.get_open_dependencies(task.id)
+            .iter()
+            .map(|dep| dep.id.to_string())
+            .collect();
+        print!(" {}", format!("(blocked by {})", open_ids.join(", ")).red());
+    }
+
+    if task.is_waiting() {
+        print!(" {}", format!("(waiting until {})", task.wait.map(format_start).unwrap_or_default()).dimmed());
+    }
+
+    // Show time until due in the main line
+    if let Some(due_date) = task.due_date {
+        let time_until = format_time_until_due(due_date, task.due_has_time);
         if task.is_overdue() {
-            print!(" ({})", time_until.red());
+            print!(" ({})", time_until.color(theme.overdue));
         } else if task.is_due_today() {
-            print!(" ({})", time_until.yellow().bold());
-        } else if task.is_due_soon() {
-            print!(" ({})", time_until.yellow());
+            print!(" ({})", time_until.color(theme.due_soon).bold());
+        } else if task.is_due_soon(config.due_soon_window()) {
+            print!(" ({})", time_until.color(theme.due_soon));
         } else {
-            print!(" ({})", time_until.blue());
+            print!(" ({})", time_until.color(theme.due));
         }
     }
 
@@ -610,22 +1493,62 @@ fn print_task(task: &models::Task, verbose: bool) {
 
     if verbose {
         if let Some(description) = &task.description {
-            println!("    {}", description.dimmed());
+            println!("{}    {}", indent, description.dimmed());
+        }
+        if let Some(recurrence) = &task.recurrence {
+            println!("{}    {}: {}", indent, "Repeats".magenta(), recurrence.to_string().magenta());
+        }
+        if let Some(parent_id) = task.parent_id {
+            println!("{}    {}: [{}]", indent, "Subtask of".dimmed(), parent_id.to_string().cyan());
+        }
+        if !task.depends_on.is_empty() {
+            let deps: Vec<String> = task.depends_on.iter().map(|dep| format!("[{}]", dep)).collect();
+            println!("{}    {}: {}", indent, "Depends on".dimmed(), deps.join(", ").cyan());
+        }
+        if let Some(scheduled) = task.scheduled {
+            println!("{}    {}: {}", indent, "Scheduled".dimmed(), format_start(scheduled).dimmed());
+        }
+        if let Some(wait) = task.wait {
+            println!("{}    {}: {}", indent, "Wait".dimmed(), format_start(wait).dimmed());
         }
         if let Some(due_date) = task.due_date {
-            let due_str = due_date.format("%Y-%m-%d").to_string();
-            let time_until = format_time_until_due(due_date);
+            let due_str = task.due_date_string().unwrap_or_default();
+            let time_until = format_time_until_due(due_date, task.due_has_time);
             if task.is_overdue() {
-                println!("    {}: {} ({})", "Due".red(), due_str.red(), time_until.red());
+                println!("{}    {}: {} ({})", indent, "Due".color(theme.overdue), due_str.color(theme.overdue), time_until.color(theme.overdue));
             } else if task.is_due_today() {
-                println!("    {}: {} ({})", "Due".yellow().bold(), due_str.yellow().bold(), time_until.yellow().bold());
-            } else if task.is_due_soon() {
-                println!("    {}: {} ({})", "Due".yellow(), due_str.yellow(), time_until.yellow());
+                println!("{}    {}: {} ({})", indent, "Due".color(theme.due_soon).bold(), due_str.color(theme.due_soon).bold(), time_until.color(theme.due_soon).bold());
+            } else if task.is_due_soon(config.due_soon_window()) {
+                println!("{}    {}: {} ({})", indent, "Due".color(theme.due_soon), due_str.color(theme.due_soon), time_until.color(theme.due_soon));
             } else {
-                println!("    {}: {} ({})", "Due".blue(), due_str.blue(), time_until.blue());
+                println!("{}    {}: {} ({})", indent, "Due".color(theme.due), due_str.color(theme.due), time_until.color(theme.due));
             }
         }
-        println!("    {}: {}", "Created".dimmed(), task.created_at.format("%Y-%m-%d %H:%M").to_string().dimmed());
+        println!("{}    {}: {}", indent, "Created".dimmed(), task.created_at.format("%Y-%m-%d %H:%M").to_string().dimmed());
+        if let Some(completed_at) = task.completed_at {
+            println!("{}    {}: {}", indent, "Completed".green(), completed_at.format("%Y-%m-%d %H:%M").to_string().green());
+        }
+    }
+}
+
+/// Print tasks as a tree, nesting subtasks under their parents
+///
+/// Tasks whose parent is not part of `tasks` (e.g. because it was filtered out)
+/// are printed as top-level tasks. Siblings keep the order of `tasks`, so any
+/// sorting applied beforehand is preserved within each level.
+fn print_task_tree(todo_list: &TodoList, tasks: &[&models::Task], verbose: bool, config: &Config) {
+    let ids: HashSet<u32> = tasks.iter().map(|task| task.id).collect();
+
+    for root in tasks.iter().filter(|task| !task.parent_id.is_some_and(|parent| ids.contains(&parent))) {
+        print_subtree(todo_list, tasks, root, verbose, 0, config);
+    }
+}
+
+fn print_subtree(todo_list: &TodoList, tasks: &[&models::Task], task: &models::Task, verbose: bool, depth: usize, config: &Config) {
+    print_task_indented(task, verbose, depth, todo_list, config);
+
+    for child in tasks.iter().filter(|child| child.parent_id == Some(task.id)) {
+        print_subtree(todo_list, tasks, child, verbose, depth + 1, config);
     }
 }
 
@@ -655,9 +1578,9 @@ fn show_task_comparison(before: &models::Task, after: &models::Task) {
     }
 
     // Compare due date
-    if before.due_date != after.due_date {
-        let before_due = before.due_date.map_or("(none)".to_string(), |d| d.format("%Y-%m-%d").to_string());
-        let after_due = after.due_date.map_or("(none)".to_string(), |d| d.format("%Y-%m-%d").to_string());
+    if before.due_date != after.due_date || before.due_has_time != after.due_has_time {
+        let before_due = before.due_date_string().unwrap_or_else(|| "(none)".to_string());
+        let after_due = after.due_date_string().unwrap_or_else(|| "(none)".to_string());
         changes.push(format!("  {}: {} {} {}",
             "Due date".bold(),
             before_due.red(),
@@ -666,6 +1589,21 @@ fn show_task_comparison(before: &models::Task, after: &models::Task) {
         ));
     }
 
+    // Compare scheduled and wait dates
+    for (label, before_date, after_date) in [
+        ("Scheduled", before.scheduled, after.scheduled),
+        ("Wait", before.wait, after.wait),
+    ] {
+        if before_date != after_date {
+            changes.push(format!("  {}: {} {} {}",
+                label.bold(),
+                before_date.map_or("(none)".to_string(), format_start).red(),
+                "→".dimmed(),
+                after_date.map_or("(none)".to_string(), format_start).green()
+            ));
+        }
+    }
+
     // Compare category
     if before.category != after.category {
         let before_cat = before.category.as_deref().unwrap_or("(none)");
@@ -688,6 +1626,64 @@ fn show_task_comparison(before: &models::Task, after: &models::Task) {
         ));
     }
 
+    // Compare recurrence
+    if before.recurrence != after.recurrence {
+        let before_rule = before.recurrence.as_ref().map_or("(none)".to_string(), |r| r.to_string());
+        let after_rule = after.recurrence.as_ref().map_or("(none)".to_string(), |r| r.to_string());
+        changes.push(format!("  {}: {} {} {}",
+            "Repeats".bold(),
+            before_rule.red(),
+            "→".dimmed(),
+            after_rule.green()
+        ));
+    }
+
+    // Compare parent
+    if before.parent_id != after.parent_id {
+        let before_parent = before.parent_id.map_or("(none)".to_string(), |id| format!("[{}]", id));
+        let after_parent = after.parent_id.map_or("(none)".to_string(), |id| format!("[{}]", id));
+        changes.push(format!("  {}: {} {} {}",
+            "Parent".bold(),
+            before_parent.red(),
+            "→".dimmed(),
+            after_parent.green()
+        ));
+    }
+
+    // Compare dependencies
+    if before.depends_on != after.depends_on {
+        let format_deps = |deps: &[u32]| {
+            if deps.is_empty() {
+                "(none)".to_string()
+            } else {
+                deps.iter().map(|dep| format!("[{}]", dep)).collect::<Vec<_>>().join(", ")
+            }
+        };
+        changes.push(format!("  {}: {} {} {}",
+            "Depends on".bold(),
+            format_deps(&before.depends_on).red(),
+            "→".dimmed(),
+            format_deps(&after.depends_on).green()
+        ));
+    }
+
+    // Compare tags
+    if before.tags != after.tags {
+        let format_tags = |tags: &std::collections::BTreeSet<String>| {
+            if tags.is_empty() {
+                "(none)".to_string()
+            } else {
+                tags.iter().map(|tag| format!("+{}", tag)).collect::<Vec<_>>().join(" ")
+            }
+        };
+        changes.push(format!("  {}: {} {} {}",
+            "Tags".bold(),
+            format_tags(&before.tags).red(),
+            "→".dimmed(),
+            format_tags(&after.tags).green()
+        ));
+    }
+
     // Compare completion status
     if before.completed != after.completed {
         let before_status = if before.completed { "completed" } else { "incomplete" };
@@ -710,81 +1706,382 @@ fn show_task_comparison(before: &models::Task, after: &models::Task) {
     }
 }
 
-fn main() -> Result<()> {
+/// Describe the current command for the undo journal, leaving out global options
+fn command_label() -> String {
+    let mut words = Vec::new();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-f" | "--file" | "--config" | "--storage" => {
+                args.next();
+            }
+            "-v" | "--verbose" => {}
+            _ if arg.starts_with("--file=") || arg.starts_with("--config=") || arg.starts_with("--storage=") => {}
+            _ if arg.contains(' ') => words.push(format!("\"{}\"", arg)),
+            _ => words.push(arg),
+        }
+    }
+    words.join(" ")
+}
+
+/// Record the changes a command made in the undo journal
+fn record_operation(journal_path: &std::path::Path, label: &str, before: &TodoList, after: &TodoList) -> Result<()> {
+    save_operation(journal_path, Operation::between(label, before, after))
+}
+
+/// Add an operation, if there is one, to the undo journal
+fn save_operation(journal_path: &std::path::Path, operation: Option<Operation>) -> Result<()> {
+    if let Some(operation) = operation {
+        let mut journal = Journal::load_from_file(journal_path)?;
+        journal.record(operation);
+        journal.save_to_file(journal_path)?;
+    }
+    Ok(())
+}
+
+/// Print the task changes made by undoing (`reverting`) or redoing an operation
+fn print_operation(heading: &str, operation: &Operation, reverting: bool) {
+    println!("{} {} ({})",
+        heading.blue().bold(),
+        operation.command,
+        operation.timestamp.format("%Y-%m-%d %H:%M")
+    );
+
+    for change in &operation.changes {
+        let (from, to) = if reverting {
+            (&change.after, &change.before)
+        } else {
+            (&change.before, &change.after)
+        };
+        match (from, to) {
+            (Some(from), Some(to)) => {
+                println!("[{}] {}", change.id.to_string().cyan(), to.title);
+                show_task_comparison(from, to);
+            }
+            (None, Some(to)) => {
+                let heading = if operation.archive_change(change.id).is_some() { "Unarchived" } else { "Restored" };
+                println!("{} [{}] {}", heading.green(), change.id.to_string().cyan(), to.title);
+            }
+            (Some(from), None) => {
+                let heading = if operation.archive_change(change.id).is_some() { "Archived" } else { "Removed" };
+                println!("{} [{}] {}", heading.red(), change.id.to_string().cyan(), from.title);
+            }
+            (None, None) => {}
+        }
+    }
+
+    // Tasks that only changed in the archive, such as purged ones
+    let in_list = |id: u32| operation.changes.iter().any(|change| change.id == id);
+    for change in operation.archive_changes.iter().filter(|change| !in_list(change.id)) {
+        let (from, to) = if reverting {
+            (&change.after, &change.before)
+        } else {
+            (&change.before, &change.after)
+        };
+        match (from, to) {
+            (None, Some(to)) => {
+                println!("{} [{}] {}", "Restored to the archive".green(), change.id.to_string().cyan(), to.title);
+            }
+            (Some(from), None) => println!("{} [{}] {}", "Purged".red(), change.id.to_string().cyan(), from.title),
+            _ => {}
+        }
+    }
+}
+
+fn main() {
     let cli = Cli::parse();
+    let format: OutputFormat = cli.output.into();
 
-    // Load todo list
-    let mut todo_list = load_todo_list(cli.config_file.clone()).unwrap_or_else(|e| {
-        if cli.verbose {
-            eprintln!("{}: {}", "Warning".yellow(), e);
-            eprintln!("Starting with empty todo list.");
+    if let Err(e) = run(cli) {
+        match format {
+            OutputFormat::Text => eprintln!("{}: {}", "Error".red().bold(), e),
+            _ => eprintln!("{}", serde_json::to_string(&ErrorRecord { error: e.to_string() }).unwrap_or_default()),
         }
-        TodoList::new()
-    });
+        std::process::exit(1);
+    }
+}
+
+/// Run a `config` action on the configuration file at `path`
+fn run_config(action: ConfigAction, path: &Path, mut config: Config) -> Result<()> {
+    match action {
+        ConfigAction::List => {
+            println!("{} {}", "Config file:".cyan().bold(), path.display());
+            for key in config::KEYS {
+                match (config.get(key)?, Config::default_value(key)?) {
+                    (Some(value), _) => println!("  {} = {}", key, value),
+                    (None, Some(default)) => println!("  {} = {} {}", key, default, "(default)".dimmed()),
+                    (None, None) => println!("  {} {}", key, "(not set)".dimmed()),
+                }
+            }
+        }
+        ConfigAction::Get { key } => {
+            if let Some(value) = config.get(&key)?.or(Config::default_value(&key)?) {
+                println!("{}", value);
+            }
+        }
+        ConfigAction::Set { key, value } => {
+            config.set(&key, &value)?;
+            if key == "default_sort" {
+                configured_sort(&config)?;
+            }
+            config.save_to_file(path)?;
+            println!("{} {} = {}", "Set".green().bold(), key.cyan(), config.get(&key)?.unwrap_or_default());
+        }
+        ConfigAction::Unset { key } => {
+            if config.unset(&key)? {
+                config.save_to_file(path)?;
+                println!("{} {}", "Unset".green().bold(), key.cyan());
+            } else {
+                println!("{} is not set", key);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Run a parsed command; errors are reported by `main` with a non-zero exit code
+fn run(cli: Cli) -> Result<()> {
+    let format: OutputFormat = cli.output.into();
+    let weight_overrides = Weights::parse(&cli.category_weight)?;
+
+    let config_path = cli.config.or_else(Config::default_path);
+    if let Some(Commands::Config { action }) = cli.command {
+        let path = config_path.ok_or_else(|| anyhow!("Unable to determine config directory; use --config"))?;
+        // Values aren't checked here, so that `config set` can fix a bad one
+        let config = Config::read_from_file(&path)?;
+        return run_config(action.unwrap_or(ConfigAction::List), &path, config);
+    }
+    let config = match &config_path {
+        Some(path) => Config::load_from_file(path)?,
+        None => Config::default(),
+    };
+    let default_sort = configured_sort(&config)?;
+    let dialect = config.dialect();
+    let weights = config.weights().overridden_by(weight_overrides);
+    let trash_days = cli.trash_days.unwrap_or(config.trash_days());
+    match config.color {
+        Some(ColorChoice::Always) => colored::control::set_override(true),
+        Some(ColorChoice::Never) => colored::control::set_override(false),
+        Some(ColorChoice::Auto) | None => {}
+    }
+    let data_file = cli.data_file.or_else(|| config.data_file());
+
+    // Load todo list, holding the lock until the command has saved its changes
+    // (except while waiting on the user in $EDITOR or the TUI)
+    let storage = open_storage(cli.storage, data_file)?;
+    let lock = storage::lock(storage.path())?;
+    // A missing file loads as an empty list; any other failure (a corrupt file,
+    // or one written by a newer release) stops here so the file is never replaced
+    let mut todo_list = storage.load()?;
+
+    // Expired trash is dropped with the next change that gets saved
+    if trash_days > 0 {
+        todo_list.empty_trash(Some(Local::now() - chrono::Duration::days(trash_days.into())));
+    }
+
+    // Commands save through `commit`, which also records their changes for undo
+    let journal_path = Journal::path_for(storage.path());
+    let label = command_label();
+    let before = todo_list.clone();
+    let commit = |todo_list: &mut TodoList| -> Result<()> {
+        todo_list.stamp_changes(&before, Local::now());
+        storage.save(todo_list)?;
+        if let Err(e) = record_operation(&journal_path, &label, &before, todo_list) {
+            eprintln!("{}: {}. This change cannot be undone.", "Warning".yellow(), e);
+        }
+        Ok(())
+    };
+    // Archive, unarchive and purge also change the archive, which undo has to put back too
+    let commit_with_archive = |todo_list: &mut TodoList, archive_before: &TodoList, archived: &TodoList| -> Result<()> {
+        todo_list.stamp_changes(&before, Local::now());
+        save_with_archive(storage.as_ref(), todo_list, archive_before, archived)?;
+        let operation = Operation::between_with_archive(&label, &before, todo_list, archive_before, archived);
+        if let Err(e) = save_operation(&journal_path, operation) {
+            eprintln!("{}: {}. This change cannot be undone.", "Warning".yellow(), e);
+        }
+        Ok(())
+    };
 
     let result = match cli.command {
-        Some(Commands::Add { title, description, due, category, priority }) => {
-            let due_date = if let Some(due_str) = due {
-                Some(parse_date(&due_str)?)
+        Some(Commands::Add { title, tags, description, due, scheduled, wait, category, priority, repeat, parent, depends_on, editor }) => {
+            let (title, mut tag_set) = extract_tags(&title.unwrap_or_default());
+            tag_set.extend(parse_tag_args(&tags)?);
+            let category = category.or_else(|| config.default_category.clone());
+            let priority = priority.map_or_else(|| config.priority(), Priority::from);
+
+            let (due_date, due_has_time) = if let Some(due_str) = due {
+                let (due_date, has_time) = parse_due(&due_str, dialect)?;
+                (Some(due_date), has_time)
             } else {
-                None
+                (None, false)
             };
+            let scheduled = scheduled.map(|date| parse_start(&date, dialect)).transpose()?;
+            let wait = wait.map(|date| parse_start(&date, dialect)).transpose()?;
+            let recurrence = repeat.map(|rule| rule.parse::<Recurrence>()).transpose()?;
+            if let Some(parent_id) = parent {
+                if todo_list.get_task(parent_id).is_none() {
+                    return Err(anyhow!("Parent task with ID {} not found", parent_id));
+                }
+            }
+            for dep_id in &depends_on {
+                if todo_list.get_task(*dep_id).is_none() {
+                    return Err(anyhow!("Dependency task with ID {} not found", dep_id));
+                }
+            }
 
             let id = todo_list.add_task_with_details(
                 title.clone(),
                 description,
                 due_date,
                 category,
-                priority.into(),
+                priority,
             );
+            if due_has_time {
+                todo_list.update_task(id, TaskUpdate::new().due_date(due_date).due_has_time(true))?;
+            }
+            if scheduled.is_some() || wait.is_some() {
+                todo_list.update_task(id, TaskUpdate::new().scheduled(scheduled).wait(wait))?;
+            }
+            if recurrence.is_some() {
+                todo_list.update_task(id, TaskUpdate::new().recurrence(recurrence))?;
+            }
+            if !tag_set.is_empty() {
+                todo_list.update_task(id, TaskUpdate::new().tags(tag_set))?;
+            }
+            if parent.is_some() {
+                todo_list.set_parent(id, parent)?;
+            }
+            for dep_id in depends_on {
+                todo_list.add_dependency(id, dep_id)?;
+            }
+            if editor {
+                edit_in_editor(&mut todo_list, id, &lock, config.dialect())?;
+            }
 
+            let title = todo_list.get_task(id).map(|task| task.title.clone()).unwrap_or(title);
             println!("{} {} {}", "Added task".green().bold(), id.to_string().cyan(), title);
-            save_todo_list(&todo_list, cli.config_file)
-        }
-
-        Some(Commands::List { completed, pending, category, priority, overdue, due_soon, sort_by, reverse }) => {
-            let tasks: Vec<&models::Task> = if completed {
-                todo_list.get_completed_tasks()
-            } else if pending {
-                todo_list.get_pending_tasks()
-            } else if overdue {
-                todo_list.get_overdue_tasks()
-            } else if due_soon {
-                todo_list.get_due_soon_tasks()
-            } else {
-                todo_list.get_all_tasks().iter().collect()
-            };
+            commit(&mut todo_list)
+        }
 
-            let filtered_tasks: Vec<&models::Task> = tasks.into_iter()
-                .filter(|task| {
-                    if let Some(cat) = &category {
-                        task.category.as_ref().map_or(false, |c| c == cat)
-                    } else {
-                        true
-                    }
-                })
-                .filter(|task| {
-                    if let Some(prio) = &priority {
-                        task.priority == (*prio).clone().into()
-                    } else {
-                        true
-                    }
-                })
-                .collect();
+        Some(Commands::List { args }) => {
+            let heading = if args.filters.archived { "Archive" } else { "Todo List" };
+            args.print(heading, storage.as_ref(), &todo_list, &weights, &config, format, cli.verbose)
+        }
 
-            let sorted_tasks = sort_tasks(filtered_tasks, sort_by, reverse);
+        Some(Commands::View { name: Some(name), .. }) => {
+            let views = Views::load_from_file(Views::path_for(storage.path()))?;
+            let args = views.get(&name).ok_or_else(|| anyhow!("View '{}' not found", name))?;
+            ListArgs::from_saved(args)
+                .map_err(|e| anyhow!("View '{}' is no longer valid: {}", name, e))?
+                .print(&format!("View {}", name), storage.as_ref(), &todo_list, &weights, &config, format, cli.verbose)
+        }
 
-            if sorted_tasks.is_empty() {
-                println!("{}", "No tasks found.".dimmed());
+        Some(Commands::View { name: None, action: Some(ViewAction::Save { name, args }) }) => {
+            if ["save", "list", "delete", "help"].contains(&name.as_str()) {
+                return Err(anyhow!("'{}' is a view command and cannot be used as a view name", name));
+            }
+            // Check the arguments now rather than when the view is shown
+            let list_args = ListArgs::from_saved(&args)?;
+            parse_query(&list_args.query.join(" "), dialect)?;
+
+            let views_path = Views::path_for(storage.path());
+            let mut views = Views::load_from_file(&views_path)?;
+            let replaced = views.insert(name.clone(), args);
+            views.save_to_file(&views_path)?;
+            println!("{} view {}", if replaced { "Updated" } else { "Saved" }.green().bold(), name.cyan());
+            Ok(())
+        }
+
+        Some(Commands::View { name: None, action: Some(ViewAction::Delete { name }) }) => {
+            let views_path = Views::path_for(storage.path());
+            let mut views = Views::load_from_file(&views_path)?;
+            if !views.remove(&name) {
+                return Err(anyhow!("View '{}' not found", name));
+            }
+            views.save_to_file(&views_path)?;
+            println!("{} view {}", "Deleted".red().bold(), name.cyan());
+            Ok(())
+        }
+
+        Some(Commands::View { name: None, action: Some(ViewAction::List) | None }) => {
+            let views = Views::load_from_file(Views::path_for(storage.path()))?;
+            if format != OutputFormat::Text {
+                let records: Vec<ViewRecord> = views.iter().map(|(name, args)| ViewRecord { name, args }).collect();
+                return output::print_records(format, &records);
+            }
+
+            if views.is_empty() {
+                println!("{}", "No saved views.".dimmed());
             } else {
-                println!("{} ({} tasks):", "Todo List".cyan().bold(), sorted_tasks.len());
-                for task in sorted_tasks {
-                    print_task(task, cli.verbose);
+                println!("{}", "Views:".cyan().bold());
+                for (name, args) in views.iter() {
+                    let args: Vec<String> = args
+                        .iter()
+                        .map(|arg| if arg.contains(' ') { format!("\"{}\"", arg) } else { arg.clone() })
+                        .collect();
+                    println!("  {} {}", name.bold(), args.join(" ").dimmed());
                 }
             }
             Ok(())
         }
 
+        Some(Commands::Trash { action: Some(TrashAction::List) | None }) => {
+            let trash: Vec<&TrashedTask> = todo_list.trash().iter().rev().collect();
+            if format != OutputFormat::Text {
+                let records: Vec<TrashRecord> = trash.iter()
+                    .map(|entry| TrashRecord {
+                        task: TaskRecord::new(&entry.task, &todo_list),
+                        deleted_at: entry.deleted_at,
+                    })
+                    .collect();
+                return output::print_records(format, &records);
+            }
+
+            if trash.is_empty() {
+                println!("{}", "The trash is empty.".dimmed());
+                return Ok(());
+            }
+            println!("{} ({} tasks):", "Trash".cyan().bold(), trash.len());
+            for entry in trash {
+                println!("[{}] {} {}",
+                    entry.task.id.to_string().cyan(),
+                    entry.task.title,
+                    format!("(removed {})", entry.deleted_at.format("%Y-%m-%d %H:%M")).dimmed()
+                );
+            }
+            if trash_days > 0 {
+                println!("{}", format!("Removed tasks are deleted for good after {} days.", trash_days).dimmed());
+            }
+            Ok(())
+        }
+
+        Some(Commands::Trash { action: Some(TrashAction::Restore { id }) }) => {
+            let new_id = todo_list.restore_from_trash(id)?;
+            if let Some(task) = todo_list.get_task(new_id) {
+                println!("{} {}", "Restored:".green().bold(), task.title);
+            }
+            if new_id != id {
+                println!("ID {} has been taken since, so the task is now [{}]", id, new_id.to_string().cyan());
+            }
+            commit(&mut todo_list)
+        }
+
+        Some(Commands::Trash { action: Some(TrashAction::Empty { confirm }) }) => {
+            let count = todo_list.trash().len();
+            if count == 0 {
+                println!("{}", "The trash is empty.".dimmed());
+                return Ok(());
+            }
+            if confirm || confirm_action(&format!("Permanently delete {} task(s) in the trash?", count)) {
+                todo_list.empty_trash(None);
+                println!("{} {} task(s)", "Deleted:".red().bold(), count);
+                commit(&mut todo_list)
+            } else {
+                println!("Operation cancelled.");
+                Ok(())
+            }
+        }
+
         Some(Commands::Search {
             query,
             case_insensitive,
@@ -793,237 +2090,374 @@ fn main() -> Result<()> {
             pending,
             category,
             priority,
+            tag,
+            exclude_tag,
             overdue,
             due_soon,
+            filter,
             sort_by,
-            reverse
+            reverse,
+            archived
         }) => {
+            if archived {
+                todo_list = load_archive(storage.as_ref())?.1;
+            }
+
             // First, perform the search
             let search_results = todo_list.search_tasks(&query, case_insensitive, regex)?;
 
-            // Then apply filters
+            // Then apply the filter flags and expression, all of which must match.
+            // Category and tag flags match exactly, as they do for `list`.
+            let strip = |tags: Vec<String>| -> Vec<String> {
+                tags.iter().map(|tag| tag.trim_start_matches('+').to_string()).collect()
+            };
+            let flags = TaskFilter {
+                category,
+                priority: priority.map(Into::into),
+                tags: strip(tag),
+                exclude_tags: strip(exclude_tag),
+                ..Default::default()
+            };
+            let mut conditions = Vec::new();
+            if completed {
+                conditions.push(Condition::Status(query::Status::Completed));
+            }
+            if pending {
+                conditions.push(Condition::Status(query::Status::Pending));
+            }
+            if overdue {
+                conditions.push(Condition::Status(query::Status::Overdue));
+            }
+            if due_soon {
+                conditions.push(Condition::Status(query::Status::DueSoon));
+            }
+            let mut queries: Vec<Query> = conditions.into_iter().map(Query::Condition).collect();
+            queries.extend(parse_query(filter.as_deref().unwrap_or_default(), dialect)?);
+            let filter = Query::all(queries);
+
             let filtered_tasks: Vec<&models::Task> = search_results.into_iter()
-                .filter(|task| {
-                    // Filter by completion status
-                    if completed {
-                        task.completed
-                    } else if pending {
-                        !task.completed
-                    } else {
-                        true
-                    }
-                })
-                .filter(|task| {
-                    // Filter by category
-                    if let Some(cat) = &category {
-                        task.category.as_ref().map_or(false, |c| c == cat)
-                    } else {
-                        true
-                    }
-                })
-                .filter(|task| {
-                    // Filter by priority
-                    if let Some(prio) = &priority {
-                        task.priority == (*prio).clone().into()
-                    } else {
-                        true
-                    }
-                })
-                .filter(|task| {
-                    // Filter by overdue
-                    if overdue {
-                        task.is_overdue()
-                    } else {
-                        true
-                    }
-                })
-                .filter(|task| {
-                    // Filter by due soon
-                    if due_soon {
-                        task.is_due_soon()
-                    } else {
-                        true
-                    }
-                })
+                .filter(|task| flags.matches(task))
+                .filter(|task| filter.as_ref().is_none_or(|filter| filter.matches(task, &todo_list, config.due_soon_window())))
                 .collect();
 
             // Sort the results
-            let sorted_tasks = sort_tasks(filtered_tasks, sort_by, reverse);
+            let sorted_tasks = sort_tasks(filtered_tasks, sort_by.or(default_sort), reverse, &weights);
 
             // Display results
+            if format != OutputFormat::Text {
+                return output::print_tasks(format, &sorted_tasks, &todo_list);
+            }
             if sorted_tasks.is_empty() {
                 println!("{}", "No tasks found matching the search criteria.".dimmed());
             } else {
                 println!("{} ({} matching tasks):", "Search Results".cyan().bold(), sorted_tasks.len());
                 for task in sorted_tasks {
-                    print_task_with_highlight(task, cli.verbose, &query, case_insensitive, regex);
+                    print_task_with_highlight(task, cli.verbose, &query, case_insensitive, regex, &config);
                 }
             }
             Ok(())
         }
 
-        Some(Commands::Complete { id, all }) => {
-            if all {
-                let pending_tasks = todo_list.get_pending_tasks();
-                if pending_tasks.is_empty() {
-                    println!("{}", "No pending tasks to complete.".dimmed());
-                    return Ok(());
+        Some(Commands::Complete { selection, all }) => {
+            if let Some(task_id) = selection.single_id() {
+                warn_open_dependencies(&todo_list, task_id);
+                let pending_subtasks = todo_list.get_descendants(task_id)
+                    .iter()
+                    .filter(|task| !task.completed)
+                    .count();
+                let next_id = todo_list.mark_complete(task_id)?;
+                if let Some(task) = todo_list.get_task(task_id) {
+                    println!("{} {}", "Completed:".green().bold(), task.title);
                 }
-
-                let count = pending_tasks.len();
-                println!("Found {} pending task(s):", count);
-                for task in &pending_tasks {
-                    println!("  - [{}] {}", task.id, task.title);
+                if pending_subtasks > 0 {
+                    println!("{} {} subtask(s)", "Also completed:".green(), pending_subtasks);
                 }
-
-                if confirm_action(&format!("Complete all {} task(s)?", count)) {
-                    let task_ids: Vec<u32> = pending_tasks.iter().map(|task| task.id).collect();
-                    let mut completed_count = 0;
-                    for task_id in task_ids {
-                        if todo_list.mark_complete(task_id).is_ok() {
-                            completed_count += 1;
-                        }
-                    }
-                    println!("{} {} task(s)", "Completed:".green().bold(), completed_count);
-                    save_todo_list(&todo_list, cli.config_file)
-                } else {
-                    println!("Operation cancelled.");
-                    Ok(())
+                if let Some(next) = next_id.and_then(|next_id| todo_list.get_task(next_id)) {
+                    let due_str = next.due_date_string().unwrap_or_default();
+                    println!("{} [{}] due {}", "Next occurrence:".magenta().bold(), next.id.to_string().cyan(), due_str);
                 }
-            } else if let Some(task_id) = id {
-                match todo_list.mark_complete(task_id) {
-                    Ok(_) => {
-                        if let Some(task) = todo_list.get_task(task_id) {
-                            println!("{} {}", "Completed:".green().bold(), task.title);
-                        }
-                        save_todo_list(&todo_list, cli.config_file)
-                    }
-                    Err(e) => {
-                        eprintln!("{}: {}", "Error".red().bold(), e);
-                        Ok(())
+                return commit(&mut todo_list);
+            }
+
+            let mut task_ids: Vec<u32> = if all {
+                todo_list.get_pending_tasks().iter().map(|task| task.id).collect()
+            } else if selection.is_empty() {
+                return Err(anyhow!("Must specify task IDs, --where or the --all flag"));
+            } else {
+                selection.resolve(&todo_list, &config)?
+            };
+            task_ids.retain(|id| todo_list.get_task(*id).is_some_and(|task| !task.completed));
+            if task_ids.is_empty() {
+                println!("{}", "No tasks to complete.".dimmed());
+                return Ok(());
+            }
+
+            if confirm_selection(&todo_list, &task_ids, "Complete", selection.confirm) {
+                // Completing a parent also completes its subtasks, which may be selected too
+                let mut completed_count = 0;
+                for task_id in task_ids {
+                    if todo_list.get_task(task_id).is_some_and(|task| !task.completed) {
+                        warn_open_dependencies(&todo_list, task_id);
+                        todo_list.mark_complete(task_id)?;
+                        completed_count += 1;
                     }
                 }
+                println!("{} {} task(s)", "Completed:".green().bold(), completed_count);
+                commit(&mut todo_list)
             } else {
-                eprintln!("{}: Must specify either a task ID or use --all flag", "Error".red().bold());
+                println!("Operation cancelled.");
                 Ok(())
             }
         }
 
-        Some(Commands::Incomplete { id }) => {
-            match todo_list.mark_incomplete(id) {
-                Ok(_) => {
-                    if let Some(task) = todo_list.get_task(id) {
-                        println!("{} {}", "Marked as incomplete:".yellow().bold(), task.title);
-                    }
-                    save_todo_list(&todo_list, cli.config_file)
+        Some(Commands::Incomplete { selection }) => {
+            if let Some(id) = selection.single_id() {
+                todo_list.mark_incomplete(id)?;
+                if let Some(task) = todo_list.get_task(id) {
+                    println!("{} {}", "Marked as incomplete:".yellow().bold(), task.title);
                 }
-                Err(e) => {
-                    eprintln!("{}: {}", "Error".red().bold(), e);
-                    Ok(())
+                return commit(&mut todo_list);
+            }
+
+            let mut task_ids = selection.resolve(&todo_list, &config)?;
+            task_ids.retain(|id| todo_list.get_task(*id).is_some_and(|task| task.completed));
+            if task_ids.is_empty() {
+                println!("{}", "No tasks to mark as incomplete.".dimmed());
+                return Ok(());
+            }
+            if confirm_selection(&todo_list, &task_ids, "Reopen", selection.confirm) {
+                for task_id in &task_ids {
+                    todo_list.mark_incomplete(*task_id)?;
                 }
+                println!("{} {} task(s)", "Marked as incomplete:".yellow().bold(), task_ids.len());
+                commit(&mut todo_list)
+            } else {
+                println!("Operation cancelled.");
+                Ok(())
             }
         }
 
-        Some(Commands::Remove { id, confirm }) => {
-            if let Some(task) = todo_list.get_task(id) {
-                let should_remove = if confirm {
+        Some(Commands::Remove { selection }) => {
+            if let Some(id) = selection.single_id() {
+                let task = todo_list.get_task(id)
+                    .ok_or_else(|| anyhow!("Task with ID {} not found", id))?;
+                let subtask_count = todo_list.get_subtasks(id).len();
+                let should_remove = if selection.confirm {
                     true
+                } else if subtask_count > 0 {
+                    confirm_action(&format!(
+                        "Are you sure you want to remove task [{}] '{}'? Its {} subtask(s) will be moved up one level.",
+                        id, task.title, subtask_count
+                    ))
                 } else {
                     confirm_action(&format!("Are you sure you want to remove task [{}] '{}'?", id, task.title))
                 };
 
-                if should_remove {
-                    match todo_list.remove_task(id) {
-                        Some(task) => {
-                            println!("{} {}", "Removed:".red().bold(), task.title);
-                            save_todo_list(&todo_list, cli.config_file)
-                        }
-                        None => {
-                            eprintln!("{}: Task with ID {} not found", "Error".red().bold(), id);
-                            Ok(())
-                        }
+                return if should_remove {
+                    let task = todo_list.trash_task(id)
+                        .ok_or_else(|| anyhow!("Task with ID {} not found", id))?;
+                    println!("{} {}", "Removed:".red().bold(), task.title);
+                    if subtask_count > 0 {
+                        println!("Moved {} subtask(s) up one level", subtask_count);
                     }
+                    println!("{}", format!("Restore it with: rtodo trash restore {}", id).dimmed());
+                    commit(&mut todo_list)
                 } else {
                     println!("Remove operation cancelled.");
                     Ok(())
+                };
+            }
+
+            let task_ids = selection.resolve(&todo_list, &config)?;
+            if task_ids.is_empty() {
+                println!("{}", "No tasks to remove.".dimmed());
+                return Ok(());
+            }
+            if confirm_selection(&todo_list, &task_ids, "Remove", selection.confirm) {
+                for task_id in &task_ids {
+                    todo_list.trash_task(*task_id);
                 }
+                println!("{} {} task(s)", "Removed:".red().bold(), task_ids.len());
+                println!("{}", "Restore them with: rtodo trash restore <ID>".dimmed());
+                commit(&mut todo_list)
             } else {
-                eprintln!("{}: Task with ID {} not found", "Error".red().bold(), id);
+                println!("Remove operation cancelled.");
                 Ok(())
             }
         }
 
-        Some(Commands::Edit { id, title, description, due, category, priority, incomplete }) => {
-            // Get the task before making changes for comparison
-            let task_before = match todo_list.get_task(id) {
-                Some(task) => task.clone(),
-                None => {
-                    eprintln!("{}: Task with ID {} not found", "Error".red().bold(), id);
-                    return Ok(());
+        Some(Commands::Edit {
+            mut selection,
+            mut tags,
+            untag,
+            title,
+            description,
+            due,
+            scheduled,
+            wait,
+            category,
+            priority,
+            repeat,
+            parent,
+            depends_on,
+            remove_depends_on,
+            incomplete,
+            editor
+        }) => {
+            // With --where, a +tag written before the options lands in the IDs position
+            if selection.filter.is_some() {
+                if let Some(tag) = selection.ids.take_if(|ids| ids.starts_with('+')) {
+                    tags.insert(0, tag);
                 }
-            };
-
-            let mut update = TaskUpdate::new();
-
-            if let Some(new_title) = title {
-                update = update.title(new_title);
-            }
-
-            if let Some(desc) = description {
-                update = update.description(if desc == "none" || desc.is_empty() {
-                    None
-                } else {
-                    Some(desc)
-                });
             }
 
-            if let Some(due_str) = due {
-                if due_str == "none" {
-                    update = update.due_date(None);
-                } else {
-                    let due_date = parse_date(&due_str)?;
-                    update = update.due_date(Some(due_date));
+            let single_id = selection.single_id();
+            let task_ids = match single_id {
+                Some(id) => vec![id],
+                None => selection.resolve(&todo_list, &config)?,
+            };
+            if single_id.is_none() {
+                if title.is_some() || editor {
+                    return Err(anyhow!("--title and --editor can only be used when editing a single task"));
+                }
+                if task_ids.is_empty() {
+                    println!("{}", "No tasks to edit.".dimmed());
+                    return Ok(());
+                }
+                if !confirm_selection(&todo_list, &task_ids, "Edit", selection.confirm) {
+                    println!("Operation cancelled.");
+                    return Ok(());
                 }
             }
 
-            if let Some(cat) = category {
-                update = update.category(if cat == "none" {
-                    None
+            // Check every new value before changing any task
+            let description = description.map(|desc| if desc == "none" || desc.is_empty() {
+                None
+            } else {
+                Some(desc)
+            });
+            let due_date = due
+                .map(|due_str| if due_str == "none" { Ok(None) } else { parse_due(&due_str, dialect).map(Some) })
+                .transpose()?;
+            let parse_optional_start = |date: String| if date == "none" { Ok(None) } else { parse_start(&date, dialect).map(Some) };
+            let scheduled = scheduled.map(parse_optional_start).transpose()?;
+            let wait = wait.map(parse_optional_start).transpose()?;
+            let category = category.map(|cat| if cat == "none" {
+                None
+            } else {
+                Some(cat)
+            });
+            let recurrence = repeat
+                .map(|rule| if rule == "none" { Ok(None) } else { rule.parse::<Recurrence>().map(Some) })
+                .transpose()?;
+            let parent_id = parent
+                .map(|parent_str| if parent_str == "none" {
+                    Ok(None)
                 } else {
-                    Some(cat)
-                });
-            }
+                    parent_str.parse::<u32>()
+                        .map(Some)
+                        .map_err(|_| anyhow!("Invalid parent task ID '{}'", parent_str))
+                })
+                .transpose()?;
+            let added_tags = parse_tag_args(&tags)?;
+            let removed_tags = untag.iter().map(|tag| normalize_tag(tag)).collect::<Result<Vec<_>>>()?;
+
+            let mut updated = 0;
+            for id in task_ids {
+                // Get the task before making changes for comparison
+                let task_before = todo_list.get_task(id)
+                    .cloned()
+                    .ok_or_else(|| anyhow!("Task with ID {} not found", id))?;
+
+                let mut update = TaskUpdate::new();
+                if let Some(new_title) = &title {
+                    update = update.title(new_title.clone());
+                }
+                if let Some(desc) = &description {
+                    update = update.description(desc.clone());
+                }
+                if let Some(due) = due_date {
+                    update = update.due_date(due.map(|(date, _)| date)).due_has_time(due.is_some_and(|(_, has_time)| has_time));
+                }
+                if let Some(scheduled) = scheduled {
+                    update = update.scheduled(scheduled);
+                }
+                if let Some(wait) = wait {
+                    update = update.wait(wait);
+                }
+                if let Some(cat) = &category {
+                    update = update.category(cat.clone());
+                }
+                if let Some(prio) = &priority {
+                    update = update.priority(prio.clone().into());
+                }
+                if !added_tags.is_empty() || !removed_tags.is_empty() {
+                    let mut tag_set = task_before.tags.clone();
+                    tag_set.extend(added_tags.iter().cloned());
+                    for tag in &removed_tags {
+                        tag_set.remove(tag);
+                    }
+                    update = update.tags(tag_set);
+                }
+                if let Some(rule) = &recurrence {
+                    update = update.recurrence(rule.clone());
+                }
 
-            if let Some(prio) = priority {
-                update = update.priority(prio.into());
-            }
+                if let Some(parent_id) = parent_id {
+                    todo_list.set_parent(id, parent_id)?;
+                }
+                for dep_id in &depends_on {
+                    todo_list.add_dependency(id, *dep_id)?;
+                }
+                for dep_id in &remove_depends_on {
+                    // Tasks picked in bulk don't all have to depend on it
+                    if single_id.is_some() || task_before.depends_on.contains(dep_id) {
+                        todo_list.remove_dependency(id, *dep_id)?;
+                    }
+                }
 
-            match todo_list.update_task(id, update) {
-                Ok(_) => {
-                    if incomplete {
-                        todo_list.mark_incomplete(id)?;
+                todo_list.update_task(id, update)?;
+                if incomplete {
+                    todo_list.mark_incomplete(id)?;
+                }
+                if editor {
+                    edit_in_editor(&mut todo_list, id, &lock, config.dialect())?;
+                    if todo_list.get_task(id) == Some(&task_before) {
+                        println!("{}", "No changes made.".dimmed());
+                        return Ok(());
                     }
-                    if let Some(task_after) = todo_list.get_task(id) {
+                }
+                if let Some(task_after) = todo_list.get_task(id) {
+                    if single_id.is_some() || task_after != &task_before {
                         println!("{} [{}]", "Updated task".blue().bold(), id.to_string().cyan());
                         show_task_comparison(&task_before, task_after);
+                        updated += 1;
                     }
-                    save_todo_list(&todo_list, cli.config_file)
-                }
-                Err(e) => {
-                    eprintln!("{}: {}", "Error".red().bold(), e);
-                    Ok(())
                 }
             }
+            if single_id.is_none() {
+                println!("{} {} task(s)", "Updated:".blue().bold(), updated);
+            }
+            commit(&mut todo_list)
         }
 
         Some(Commands::Categories) => {
             let categories = todo_list.get_all_categories();
 
+            if format != OutputFormat::Text {
+                let mut records: Vec<CategoryRecord> = categories.iter()
+                    .map(|(name, count)| CategoryRecord { name, count: *count })
+                    .collect();
+                records.sort_by_key(|record| record.name);
+                return output::print_records(format, &records);
+            }
+
             if categories.is_empty() {
                 println!("{}", "No categories found.".dimmed());
             } else {
                 println!("{}", "Categories:".cyan().bold());
 
+                let theme = config.theme();
                 // Sort categories alphabetically
                 let mut sorted_categories: Vec<(&String, &usize)> = categories.iter().collect();
                 sorted_categories.sort_by_key(|(name, _)| name.as_str());
@@ -1031,7 +2465,7 @@ fn main() -> Result<()> {
                 for (category, count) in sorted_categories {
                     let task_word = if *count == 1 { "task" } else { "tasks" };
                     println!("  {} {} ({} {})",
-                        format!("#{}", category).green(),
+                        format!("#{}", category).color(theme.category),
                         category.bold(),
                         count.to_string().cyan(),
                         task_word.dimmed()
@@ -1053,39 +2487,465 @@ fn main() -> Result<()> {
 
         Some(Commands::RenameCategory { old_name, new_name }) => {
             if old_name == new_name {
-                eprintln!("{}: Old and new category names are the same", "Error".red().bold());
-                return Ok(());
+                return Err(anyhow!("Old and new category names are the same"));
+            }
+
+            let count = todo_list.rename_category(&old_name, &new_name)?;
+            let task_word = if count == 1 { "task" } else { "tasks" };
+            println!("{} Renamed category '{}' to '{}' for {} {}",
+                "Success:".green().bold(),
+                old_name.yellow(),
+                new_name.green(),
+                count.to_string().cyan(),
+                task_word
+            );
+            commit(&mut todo_list)
+        }
+
+        Some(Commands::Tags) => {
+            let tags = todo_list.get_all_tags();
+
+            if format != OutputFormat::Text {
+                let mut records: Vec<TagRecord> = tags.iter()
+                    .map(|(name, count)| TagRecord { name, count: *count })
+                    .collect();
+                records.sort_by_key(|record| record.name);
+                return output::print_records(format, &records);
             }
 
-            match todo_list.rename_category(&old_name, &new_name) {
-                Ok(count) => {
-                    let task_word = if count == 1 { "task" } else { "tasks" };
-                    println!("{} Renamed category '{}' to '{}' for {} {}",
-                        "Success:".green().bold(),
-                        old_name.yellow(),
-                        new_name.green(),
+            if tags.is_empty() {
+                println!("{}", "No tags found.".dimmed());
+            } else {
+                println!("{}", "Tags:".cyan().bold());
+
+                // Sort tags alphabetically
+                let mut sorted_tags: Vec<(&String, &usize)> = tags.iter().collect();
+                sorted_tags.sort_by_key(|(name, _)| name.as_str());
+
+                for (tag, count) in sorted_tags {
+                    let task_word = if *count == 1 { "task" } else { "tasks" };
+                    println!("  {} ({} {})",
+                        format!("+{}", tag).bright_blue().bold(),
                         count.to_string().cyan(),
-                        task_word
+                        task_word.dimmed()
                     );
-                    save_todo_list(&todo_list, cli.config_file)
                 }
-                Err(e) => {
-                    eprintln!("{}: {}", "Error".red().bold(), e);
-                    Ok(())
+
+                println!();
+                println!("{} {} tags",
+                    "Summary:".bold(),
+                    tags.len().to_string().cyan()
+                );
+            }
+            Ok(())
+        }
+
+        Some(Commands::RenameTag { old_name, new_name }) => {
+            let old_name = normalize_tag(&old_name)?;
+            let new_name = normalize_tag(&new_name)?;
+            if old_name == new_name {
+                return Err(anyhow!("Old and new tag names are the same"));
+            }
+
+            let count = todo_list.rename_tag(&old_name, &new_name)?;
+            let task_word = if count == 1 { "task" } else { "tasks" };
+            println!("{} Renamed tag '{}' to '{}' for {} {}",
+                "Success:".green().bold(),
+                old_name.yellow(),
+                new_name.green(),
+                count.to_string().cyan(),
+                task_word
+            );
+            commit(&mut todo_list)
+        }
+
+        Some(Commands::Done { since, until }) => {
+            let since_date = match since {
+                Some(since_str) => start_of_day(parse_date(&since_str, dialect)?),
+                None => start_of_day(Local::now() - chrono::Duration::days(6)),
+            };
+            let until_date = until.map(|until_str| parse_date(&until_str, dialect)).transpose()?;
+
+            let tasks = todo_list.get_completed_between(Some(since_date), until_date);
+            let sorted_tasks = sort_tasks(tasks, Some(SortField::Completed), false, &weights);
+
+            if format != OutputFormat::Text {
+                return output::print_tasks(format, &sorted_tasks, &todo_list);
+            }
+            let until_str = until_date.map_or("now".to_string(), |d| d.format("%Y-%m-%d").to_string());
+            if sorted_tasks.is_empty() {
+                println!("{}", format!("No tasks completed between {} and {}.", since_date.format("%Y-%m-%d"), until_str).dimmed());
+            } else {
+                println!("{} {} → {} ({} tasks):",
+                    "Completed".green().bold(),
+                    since_date.format("%Y-%m-%d"),
+                    until_str,
+                    sorted_tasks.len()
+                );
+
+                let mut current_day = None;
+                for task in sorted_tasks {
+                    let day = task.completed_at.map(|d| d.date_naive());
+                    if day != current_day {
+                        if let Some(day) = day {
+                            println!("{}", day.format("%a %Y-%m-%d").to_string().bold());
+                        }
+                        current_day = day;
+                    }
+                    print_task_indented(task, cli.verbose, 1, &todo_list, &config);
+                }
+            }
+            Ok(())
+        }
+
+        Some(Commands::Recurring) => {
+            let recurring = todo_list.get_recurring_tasks();
+            let completed_count = |task: &models::Task| {
+                todo_list.get_series_tasks(task.series_root())
+                    .iter()
+                    .filter(|t| t.completed)
+                    .count()
+            };
+
+            if format != OutputFormat::Text {
+                let records: Vec<RecurringRecord> = recurring.iter()
+                    .map(|task| RecurringRecord {
+                        task: TaskRecord::new(task, &todo_list),
+                        completed_count: completed_count(task),
+                    })
+                    .collect();
+                return output::print_records(format, &records);
+            }
+
+            if recurring.is_empty() {
+                println!("{}", "No recurring tasks found.".dimmed());
+            } else {
+                println!("{} ({} series):", "Recurring Tasks".cyan().bold(), recurring.len());
+                for task in recurring {
+                    let done = completed_count(task);
+                    print_task(task, cli.verbose, &todo_list, &config);
+                    // The verbose listing already shows the rule
+                    if cli.verbose {
+                        println!("    {}: {}", "Completed".dimmed(), done.to_string().cyan());
+                    } else {
+                        println!("    {} {}, {} completed",
+                            "Repeats".magenta(),
+                            task.recurrence.as_ref().map_or(String::new(), |r| r.to_string()).magenta(),
+                            done.to_string().cyan()
+                        );
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        Some(Commands::StopRecurring { id }) => {
+            todo_list.stop_recurrence(id)?;
+            if let Some(task) = todo_list.get_task(id) {
+                println!("{} {}", "Stopped recurrence:".yellow().bold(), task.title);
+            }
+            commit(&mut todo_list)
+        }
+
+        Some(Commands::Archive { older_than, dry_run }) => {
+            let cutoff = older_than.map(|days| Local::now() - chrono::Duration::days(days.into()));
+            let task_ids = archive::select(&todo_list, cutoff);
+            if task_ids.is_empty() {
+                println!("{}", "No completed tasks to archive.".dimmed());
+                return Ok(());
+            }
+
+            let heading = if dry_run { "Would archive" } else { "Archived" };
+            println!("{} {} task(s):", heading.blue().bold(), task_ids.len());
+            for task in task_ids.iter().filter_map(|id| todo_list.get_task(*id)) {
+                println!("  - [{}] {}", task.id, task.title);
+            }
+            if dry_run {
+                return Ok(());
+            }
+
+            let (_, mut archived) = load_archive(storage.as_ref())?;
+            let archive_before = archived.clone();
+            archive::move_tasks(&mut todo_list, &mut archived, &task_ids)?;
+            commit_with_archive(&mut todo_list, &archive_before, &archived)
+        }
+
+        Some(Commands::Unarchive { id }) => {
+            let (_, mut archived) = load_archive(storage.as_ref())?;
+            let title = archived.get_task(id)
+                .map(|task| task.title.clone())
+                .ok_or_else(|| anyhow!("Task with ID {} not found in the archive", id))?;
+            let mut task_ids: BTreeSet<u32> = archived.get_descendants(id).iter().map(|task| task.id).collect();
+            task_ids.insert(id);
+
+            let archive_before = archived.clone();
+            archive::move_tasks(&mut archived, &mut todo_list, &task_ids)?;
+            commit_with_archive(&mut todo_list, &archive_before, &archived)?;
+            println!("{} {}", "Unarchived:".green().bold(), title);
+            if task_ids.len() > 1 {
+                println!("Also moved back {} subtask(s)", task_ids.len() - 1);
+            }
+            Ok(())
+        }
+
+        Some(Commands::Purge { before, confirm }) => {
+            let cutoff = start_of_day(parse_date(&before, dialect)?);
+            let (_, mut archived) = load_archive(storage.as_ref())?;
+            let task_ids: Vec<u32> = archived.get_all_tasks().iter()
+                .filter(|task| archive::completed_at(task) < cutoff)
+                .map(|task| task.id)
+                .collect();
+            if task_ids.is_empty() {
+                println!("{}", format!("No archived tasks completed before {}.", cutoff.format("%Y-%m-%d")).dimmed());
+                return Ok(());
+            }
+
+            if confirm_selection(&archived, &task_ids, "Permanently delete", confirm) {
+                let archive_before = archived.clone();
+                for task_id in &task_ids {
+                    archived.remove_task(*task_id);
+                }
+                commit_with_archive(&mut todo_list, &archive_before, &archived)?;
+                println!("{} {} archived task(s)", "Purged:".red().bold(), task_ids.len());
+            } else {
+                println!("Purge operation cancelled.");
+            }
+            Ok(())
+        }
+
+        Some(Commands::MigrateStorage { to, dest, force }) => {
+            let backend: Backend = to.into();
+            let dest = dest.unwrap_or_else(|| storage.path().with_extension(backend.extension()));
+            if dest == storage.path() {
+                return Err(anyhow!("Destination {} is the current storage file", dest.display()));
+            }
+            if todo_list.is_empty() {
+                return Err(anyhow!("Nothing to migrate: {} has no tasks", storage.path().display()));
+            }
+
+            let target = storage::open(backend, dest);
+            let _target_lock = storage::lock(target.path())?;
+            let existing = target.load()?;
+            if !existing.is_empty() && !force {
+                return Err(anyhow!(
+                    "Destination {} already contains {} tasks. Use --force to overwrite it.",
+                    target.path().display(),
+                    existing.len()
+                ));
+            }
+
+            target.save(&todo_list)?;
+            let migrated = target.load()?;
+            if migrated.len() != todo_list.len() {
+                return Err(anyhow!(
+                    "Migration incomplete: wrote {} of {} tasks to {}",
+                    migrated.len(),
+                    todo_list.len(),
+                    target.path().display()
+                ));
+            }
+
+            println!("{} {} tasks from {} ({}) to {} ({})",
+                "Migrated".green().bold(),
+                migrated.len(),
+                storage.backend(),
+                storage.path().display(),
+                target.backend(),
+                target.path().display()
+            );
+            println!("Use it with: rtodo --storage {} --file {}", target.backend(), target.path().display());
+            Ok(())
+        }
+
+        Some(Commands::Import { path, format, columns }) => {
+            let contents = if path.as_os_str() == "-" {
+                io::read_to_string(io::stdin())
+                    .map_err(|e| anyhow!("Failed to read standard input: {}", e))?
+            } else {
+                std::fs::read_to_string(&path)
+                    .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?
+            };
+
+            let imported = formats::import(format.into(), &contents, &todo_list, &columns)?;
+            for warning in &imported.warnings {
+                eprintln!("{}: {}", "Warning".yellow(), warning);
+            }
+            let count = imported.tasks.len();
+            for task in imported.tasks {
+                let id = todo_list.insert_task(task);
+                if cli.verbose {
+                    println!("  {} {}", format!("#{}", id).dimmed(), todo_list.get_task(id).unwrap().title);
+                }
+            }
+            println!("{} {} tasks", "Imported".green().bold(), count);
+            if !imported.updated.is_empty() {
+                println!("{} {} existing tasks", "Updated".green().bold(), imported.updated.len());
+                for task in imported.updated {
+                    todo_list.restore_task(task);
                 }
             }
+            commit(&mut todo_list)
+        }
+
+        Some(Commands::Export { format, path, filter, filters }) => {
+            let query = parse_query(filter.as_deref().unwrap_or_default(), dialect)?;
+            let archive;
+            let (storage, todo_list): (&dyn Storage, &TodoList) = if filters.archived {
+                archive = load_archive(storage.as_ref())?;
+                (&archive.0, &archive.1)
+            } else {
+                (storage.as_ref(), &todo_list)
+            };
+            let tasks = filters.select(storage, todo_list, query.as_ref(), &weights, &config)?;
+            let contents = formats::export(format.into(), &tasks)?;
+            match path {
+                Some(path) => {
+                    std::fs::write(&path, contents)
+                        .map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))?;
+                    println!("{} {} tasks to {}", "Exported".green().bold(), tasks.len(), path.display());
+                }
+                None => print!("{}", contents),
+            }
+            Ok(())
+        }
+
+        Some(Commands::Tui) => {
+            if !io::stdout().is_terminal() || !io::stdin().is_terminal() {
+                return Err(anyhow!("The TUI needs an interactive terminal"));
+            }
+            // Each change is saved and journaled on its own, so it can be undone separately.
+            // Other commands may run during the session, so the lock is only taken to save.
+            lock.release();
+            tui::run(todo_list, &config, |label, before, after| {
+                lock.reacquire()?;
+                let saved = match storage.save(after) {
+                    // The file is written, so a journal failure must not look like a failed save
+                    Ok(()) => Ok(match record_operation(&journal_path, label, before, after) {
+                        Ok(()) => tui::Saved::Done,
+                        Err(e) => tui::Saved::DoneWithWarning(format!("{}. This change cannot be undone", e)),
+                    }),
+                    Err(e) if e.is::<ExternalChange>() => storage.load().map(tui::Saved::Reloaded),
+                    Err(e) => Err(e),
+                };
+                lock.release();
+                saved
+            })
+        }
+
+        Some(Commands::Undo { steps, dry_run }) => {
+            let mut journal = Journal::load_from_file(&journal_path)?;
+            let (_, mut archived) = load_archive(storage.as_ref())?;
+            let archive_before = archived.clone();
+            let mut undone = Vec::new();
+            for _ in 0..steps {
+                match journal.undo(&mut todo_list, &mut archived)? {
+                    Some(operation) => undone.push(operation.clone()),
+                    None => break,
+                }
+            }
+
+            if undone.is_empty() {
+                println!("{}", "Nothing to undo.".dimmed());
+                return Ok(());
+            }
+            for operation in &undone {
+                print_operation(if dry_run { "Would undo:" } else { "Undid:" }, operation, true);
+            }
+            if undone.len() < steps {
+                println!("{}", format!("Only {} operation(s) could be undone.", undone.len()).dimmed());
+            }
+
+            if dry_run {
+                Ok(())
+            } else {
+                todo_list.stamp_changes(&before, Local::now());
+                save_with_archive(storage.as_ref(), &todo_list, &archive_before, &archived)?;
+                journal.save_to_file(&journal_path)
+            }
+        }
+
+        Some(Commands::Redo { steps, dry_run }) => {
+            let mut journal = Journal::load_from_file(&journal_path)?;
+            let (_, mut archived) = load_archive(storage.as_ref())?;
+            let archive_before = archived.clone();
+            let mut redone = Vec::new();
+            for _ in 0..steps {
+                match journal.redo(&mut todo_list, &mut archived)? {
+                    Some(operation) => redone.push(operation.clone()),
+                    None => break,
+                }
+            }
+
+            if redone.is_empty() {
+                println!("{}", "Nothing to redo.".dimmed());
+                return Ok(());
+            }
+            for operation in &redone {
+                print_operation(if dry_run { "Would redo:" } else { "Redid:" }, operation, false);
+            }
+            if redone.len() < steps {
+                println!("{}", format!("Only {} operation(s) could be redone.", redone.len()).dimmed());
+            }
+
+            if dry_run {
+                Ok(())
+            } else {
+                todo_list.stamp_changes(&before, Local::now());
+                save_with_archive(storage.as_ref(), &todo_list, &archive_before, &archived)?;
+                journal.save_to_file(&journal_path)
+            }
+        }
+
+        Some(Commands::History { limit }) => {
+            let journal = Journal::load_from_file(&journal_path)?;
+            let operations = journal.undo_stack();
+
+            if format != OutputFormat::Text {
+                let records: Vec<HistoryRecord> = operations.iter().rev().take(limit).enumerate()
+                    .map(|(step, operation)| HistoryRecord {
+                        step: step + 1,
+                        command: &operation.command,
+                        timestamp: operation.timestamp,
+                        tasks: operation.task_count(),
+                    })
+                    .collect();
+                return output::print_records(format, &records);
+            }
+
+            if operations.is_empty() {
+                println!("{}", "No changes to undo.".dimmed());
+            } else {
+                println!("{} (most recent first):", "Undo History".cyan().bold());
+                for (step, operation) in operations.iter().rev().take(limit).enumerate() {
+                    println!("{:>3}. {} {}",
+                        step + 1,
+                        operation.command,
+                        format!("({}, {} task(s))",
+                            operation.timestamp.format("%Y-%m-%d %H:%M"),
+                            operation.task_count()
+                        ).dimmed()
+                    );
+                }
+            }
+            if !journal.redo_stack().is_empty() {
+                println!("{} operation(s) can be redone.", journal.redo_stack().len());
+            }
+            Ok(())
         }
 
         Some(Commands::DueToday { sort_by, reverse }) => {
             let tasks = todo_list.get_due_today_tasks();
-            let sorted_tasks = sort_tasks(tasks, sort_by, reverse);
+            let sorted_tasks = sort_tasks(tasks, sort_by.or(default_sort), reverse, &weights);
 
+            if format != OutputFormat::Text {
+                return output::print_tasks(format, &sorted_tasks, &todo_list);
+            }
             if sorted_tasks.is_empty() {
                 println!("{}", "No tasks due today.".dimmed());
             } else {
                 println!("{} ({} tasks):", "Tasks Due Today".cyan().bold(), sorted_tasks.len());
                 for task in sorted_tasks {
-                    print_task(task, cli.verbose);
+                    print_task(task, cli.verbose, &todo_list, &config);
                 }
             }
             Ok(())
@@ -1093,27 +2953,79 @@ fn main() -> Result<()> {
 
         Some(Commands::Overdue { sort_by, reverse }) => {
             let tasks = todo_list.get_overdue_tasks();
-            let sorted_tasks = sort_tasks(tasks, sort_by, reverse);
+            let sorted_tasks = sort_tasks(tasks, sort_by.or(default_sort), reverse, &weights);
 
+            if format != OutputFormat::Text {
+                return output::print_tasks(format, &sorted_tasks, &todo_list);
+            }
             if sorted_tasks.is_empty() {
                 println!("{}", "No overdue tasks.".dimmed());
             } else {
                 println!("{} ({} tasks):", "Overdue Tasks".red().bold(), sorted_tasks.len());
                 for task in sorted_tasks {
-                    print_task(task, cli.verbose);
+                    print_task(task, cli.verbose, &todo_list, &config);
                 }
             }
             Ok(())
         }
 
-        None => {
-            println!("{}", "Welcome to rtodo!".cyan().bold());
-            println!("Use 'rtodo --help' to see available commands.");
+        Some(Commands::Available { sort_by, reverse }) => {
+            let tasks = todo_list.get_available_tasks();
+            let sorted_tasks = sort_tasks(tasks, sort_by.or(default_sort), reverse, &weights);
+
+            if format != OutputFormat::Text {
+                return output::print_tasks(format, &sorted_tasks, &todo_list);
+            }
+            if sorted_tasks.is_empty() {
+                println!("{}", "No tasks available today.".dimmed());
+            } else {
+                println!("{} ({} tasks):", "Available Today".cyan().bold(), sorted_tasks.len());
+                for task in sorted_tasks {
+                    print_task(task, cli.verbose, &todo_list, &config);
+                }
+            }
+            Ok(())
+        }
+
+        Some(Commands::Next { count }) => {
+            let tasks = todo_list.get_available_tasks();
+            let mut sorted_tasks = sort_tasks(tasks, Some(SortField::Urgency), false, &weights);
+            sorted_tasks.truncate(count);
+
+            if format != OutputFormat::Text {
+                return output::print_tasks(format, &sorted_tasks, &todo_list);
+            }
+            if sorted_tasks.is_empty() {
+                println!("{}", "Nothing to work on right now.".dimmed());
+            } else {
+                println!("{} ({} tasks):", "Next".cyan().bold(), sorted_tasks.len());
+                let now = Local::now();
+                for task in sorted_tasks {
+                    print_task(task, cli.verbose, &todo_list, &config);
+                    if cli.verbose {
+                        let urgency = Urgency::of(task, &weights, now);
+                        println!("    {}: {}", "Urgency".magenta(), format_urgency(&urgency).magenta());
+                    }
+                }
+            }
+            Ok(())
+        }
 
+        Some(Commands::Config { .. }) => unreachable!("handled before the data file is opened"),
+
+        None => {
             let total = todo_list.len();
             let completed = todo_list.get_completed_tasks().len();
             let pending = todo_list.get_pending_tasks().len();
             let overdue = todo_list.get_overdue_tasks().len();
+            let blocked = todo_list.get_blocked_tasks().len();
+
+            if format != OutputFormat::Text {
+                return output::print_record(format, &SummaryRecord { total, completed, pending, overdue, blocked });
+            }
+
+            println!("{}", "Welcome to rtodo!".cyan().bold());
+            println!("Use 'rtodo --help' to see available commands.");
 
             if total > 0 {
                 println!();
@@ -1125,16 +3037,14 @@ fn main() -> Result<()> {
                 if overdue > 0 {
                     println!("  {} overdue tasks!", overdue.to_string().red().bold());
                 }
+                if blocked > 0 {
+                    println!("  {} blocked tasks", blocked.to_string().yellow());
+                }
             }
 
             Ok(())
         }
     };
 
-    if let Err(e) = result {
-        eprintln!("{}: {}", "Error".red().bold(), e);
-        std::process::exit(1);
-    }
-
-    Ok(())
+    result
 }
\ No newline at end of file
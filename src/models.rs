@@ -1,20 +1,201 @@
 use anyhow::{anyhow, Result};
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDate, TimeZone, Weekday};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::schema;
 
 /// Priority levels for tasks
-#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Priority {
     Low,
+    #[default]
     Medium,
     High,
 }
 
-impl Default for Priority {
-    fn default() -> Self {
-        Priority::Medium
+/// Recurrence rule for repeating tasks
+///
+/// Calendar-based rules (`Daily`, `Weekly`, `Monthly`) advance from the previous
+/// due date, while `AfterCompletion` advances from the moment the task is completed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Recurrence {
+    /// Repeat every day
+    Daily,
+    /// Repeat every week on the given weekdays (same weekday as the due date if empty)
+    Weekly { weekdays: Vec<Weekday> },
+    /// Repeat every month on the given day (clamped to the last day of short months)
+    Monthly { day: u32 },
+    /// Repeat a number of days after the previous instance was completed
+    AfterCompletion { days: u32 },
+}
+
+impl Recurrence {
+    /// Compute the due date of the next instance
+    ///
+    /// `due_date` is the due date of the instance being completed and `completed_at`
+    /// the time it was completed. Calendar-based rules skip occurrences that are
+    /// already in the past so that a late completion doesn't spawn an overdue task.
+    pub fn next_due_date(&self, due_date: Option<DateTime<Local>>, completed_at: DateTime<Local>) -> DateTime<Local> {
+        let base = due_date.unwrap_or(completed_at);
+
+        if let Recurrence::AfterCompletion { days } = self {
+            let next = completed_at.date_naive() + Duration::days(i64::from(*days));
+            return Self::at_time_of(next, base);
+        }
+
+        let mut next = self.advance(base.date_naive());
+        while Self::at_time_of(next, base) <= completed_at {
+            next = self.advance(next);
+        }
+        Self::at_time_of(next, base)
+    }
+
+    /// Advance a date to the next occurrence strictly after it
+    fn advance(&self, date: NaiveDate) -> NaiveDate {
+        match self {
+            Recurrence::Daily => date + Duration::days(1),
+            Recurrence::Weekly { weekdays } => {
+                if weekdays.is_empty() {
+                    return date + Duration::weeks(1);
+                }
+                (1..=7)
+                    .map(|offset| date + Duration::days(offset))
+                    .find(|candidate| weekdays.contains(&candidate.weekday()))
+                    .unwrap_or(date + Duration::weeks(1))
+            }
+            Recurrence::Monthly { day } => {
+                let this_month = Self::day_in_month(date.year(), date.month(), *day);
+                if this_month > date {
+                    this_month
+                } else if date.month() == 12 {
+                    Self::day_in_month(date.year() + 1, 1, *day)
+                } else {
+                    Self::day_in_month(date.year(), date.month() + 1, *day)
+                }
+            }
+            Recurrence::AfterCompletion { days } => date + Duration::days(i64::from(*days)),
+        }
+    }
+
+    /// Get the given day of a month, clamped to the month's last day
+    fn day_in_month(year: i32, month: u32, day: u32) -> NaiveDate {
+        (1..=day.clamp(1, 31))
+            .rev()
+            .find_map(|d| NaiveDate::from_ymd_opt(year, month, d))
+            .expect("every month has a first day")
+    }
+
+    /// Combine a date with the time of day of another datetime
+    fn at_time_of(date: NaiveDate, time_source: DateTime<Local>) -> DateTime<Local> {
+        let naive = date.and_time(time_source.time());
+        Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .unwrap_or(time_source)
+    }
+}
+
+impl fmt::Display for Recurrence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Recurrence::Daily => write!(f, "daily"),
+            Recurrence::Weekly { weekdays } if weekdays.is_empty() => write!(f, "weekly"),
+            Recurrence::Weekly { weekdays } => {
+                let days: Vec<String> = weekdays
+                    .iter()
+                    .map(|day| day.to_string().to_lowercase())
+                    .collect();
+                write!(f, "weekly:{}", days.join(","))
+            }
+            Recurrence::Monthly { day } => write!(f, "monthly:{}", day),
+            Recurrence::AfterCompletion { days } => write!(f, "after:{}", days),
+        }
+    }
+}
+
+impl FromStr for Recurrence {
+    type Err = anyhow::Error;
+
+    /// Parse a recurrence rule
+    ///
+    /// Accepted forms: `daily`, `weekly`, `weekly:mon,thu`, `monthly:15` and
+    /// `after:3` (three days after completion).
+    fn from_str(s: &str) -> Result<Self> {
+        let rule = s.trim().to_lowercase();
+        let (kind, arg) = match rule.split_once(':') {
+            Some((kind, arg)) => (kind.trim(), Some(arg.trim())),
+            None => (rule.as_str(), None),
+        };
+
+        match (kind, arg) {
+            ("daily", None) => Ok(Recurrence::Daily),
+            ("weekly", None) => Ok(Recurrence::Weekly { weekdays: Vec::new() }),
+            ("weekly", Some(days)) => {
+                let mut weekdays = Vec::new();
+                for day in days.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+                    let weekday: Weekday = day
+                        .parse()
+                        .map_err(|_| anyhow!("Invalid weekday '{}' in recurrence rule", day))?;
+                    if !weekdays.contains(&weekday) {
+                        weekdays.push(weekday);
+                    }
+                }
+                if weekdays.is_empty() {
+                    return Err(anyhow!("Weekly recurrence needs at least one weekday"));
+                }
+                weekdays.sort_by_key(|day| day.num_days_from_monday());
+                Ok(Recurrence::Weekly { weekdays })
+            }
+            ("monthly", Some(day)) => match day.parse::<u32>() {
+                Ok(day) if (1..=31).contains(&day) => Ok(Recurrence::Monthly { day }),
+                _ => Err(anyhow!("Invalid day of month '{}' in recurrence rule (expected 1-31)", day)),
+            },
+            ("after", Some(days)) => match days.trim_end_matches('d').parse::<u32>() {
+                Ok(days) if days > 0 => Ok(Recurrence::AfterCompletion { days }),
+                _ => Err(anyhow!("Invalid number of days '{}' in recurrence rule", days)),
+            },
+            _ => Err(anyhow!(
+                "Invalid recurrence rule '{}'. Use daily, weekly[:mon,thu], monthly:<day> or after:<days>",
+                s
+            )),
+        }
+    }
+}
+
+/// Validate a tag name, stripping an optional leading `+`
+pub fn normalize_tag(tag: &str) -> Result<String> {
+    let name = tag.trim().trim_start_matches('+');
+    if name.is_empty() || name.chars().any(char::is_whitespace) {
+        return Err(anyhow!("Invalid tag '{}': tags must be non-empty and contain no spaces", tag));
+    }
+    Ok(name.to_string())
+}
+
+/// Split `+tag` words out of a task title
+///
+/// Returns the title with the tag words removed and the set of tags found.
+pub fn extract_tags(title: &str) -> (String, BTreeSet<String>) {
+    let mut tags = BTreeSet::new();
+    let mut words = Vec::new();
+
+    for word in title.split_whitespace() {
+        match word.strip_prefix('+') {
+            Some(tag) if !tag.is_empty() && !tag.starts_with('+') => {
+                tags.insert(tag.to_string());
+            }
+            _ => words.push(word),
+        }
+    }
+
+    if tags.is_empty() || words.is_empty() {
+        (title.to_string(), tags)
+    } else {
+        (words.join(" "), tags)
     }
 }
 
@@ -24,8 +205,14 @@ pub struct TaskUpdate {
     pub title: Option<String>,
     pub description: Option<Option<String>>,
     pub due_date: Option<Option<DateTime<Local>>>,
+    /// Whether a new due date has a time of day, rather than being due by the end of the day
+    pub due_has_time: bool,
+    pub scheduled: Option<Option<DateTime<Local>>>,
+    pub wait: Option<Option<DateTime<Local>>>,
     pub category: Option<Option<String>>,
     pub priority: Option<Priority>,
+    pub recurrence: Option<Option<Recurrence>>,
+    pub tags: Option<BTreeSet<String>>,
 }
 
 impl TaskUpdate {
@@ -52,6 +239,24 @@ impl TaskUpdate {
         self
     }
 
+    /// Mark the new due date as having a time of day
+    pub fn due_has_time(mut self, due_has_time: bool) -> Self {
+        self.due_has_time = due_has_time;
+        self
+    }
+
+    /// Set the date work on the task is planned to start
+    pub fn scheduled(mut self, scheduled: Option<DateTime<Local>>) -> Self {
+        self.scheduled = Some(scheduled);
+        self
+    }
+
+    /// Set the date until which the task is hidden
+    pub fn wait(mut self, wait: Option<DateTime<Local>>) -> Self {
+        self.wait = Some(wait);
+        self
+    }
+
     /// Set the category
     pub fn category<S: Into<String>>(mut self, category: Option<S>) -> Self {
         self.category = Some(category.map(|s| s.into()));
@@ -63,23 +268,80 @@ impl TaskUpdate {
         self.priority = Some(priority);
         self
     }
+
+    /// Set the recurrence rule
+    pub fn recurrence(mut self, recurrence: Option<Recurrence>) -> Self {
+        self.recurrence = Some(recurrence);
+        self
+    }
+
+    /// Replace the set of tags
+    pub fn tags<I, S>(mut self, tags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tags = Some(tags.into_iter().map(|tag| tag.into()).collect());
+        self
+    }
+}
+
+fn is_zero(value: &u32) -> bool {
+    *value == 0
 }
 
 /// A single todo task
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Task {
     pub id: u32,
     pub title: String,
     pub description: Option<String>,
     pub completed: bool,
     pub created_at: DateTime<Local>,
+    /// When the task was completed; cleared when it is marked incomplete again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<DateTime<Local>>,
+    /// When the task was last changed, if it has been since it was created
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified_at: Option<DateTime<Local>>,
+    /// How many times the task has been changed (see `TodoList::stamp_changes`)
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub revision: u32,
     pub due_date: Option<DateTime<Local>>,
+    /// Whether the due date has a time of day; date-only due dates are kept at 23:59:59
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub due_has_time: bool,
+    /// When work on the task is planned to start; it isn't available before that day
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scheduled: Option<DateTime<Local>>,
+    /// The task is hidden from `list` until this time
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wait: Option<DateTime<Local>>,
     pub category: Option<String>,
     pub priority: Priority,
+    /// Recurrence rule; only the live (pending) instance of a series carries it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<Recurrence>,
+    /// ID of the first task in the recurrence series this task belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub series_id: Option<u32>,
+    /// ID of the parent task if this is a subtask
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub parent_id: Option<u32>,
+    /// IDs of tasks that must be completed before this one can start
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<u32>,
+    /// Free-form tags, in addition to the single category
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub tags: BTreeSet<String>,
+    /// UUID of the task in the tool it was imported from, used to recognise it on later imports
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
 }
 
 impl Task {
     /// Create a new task with the given title
+    #[allow(dead_code)]
     pub fn new(id: u32, title: String) -> Self {
         Self {
             id,
@@ -87,9 +349,21 @@ impl Task {
             description: None,
             completed: false,
             created_at: Local::now(),
+            completed_at: None,
+            modified_at: None,
+            revision: 0,
             due_date: None,
+            due_has_time: false,
+            scheduled: None,
+            wait: None,
             category: None,
             priority: Priority::default(),
+            recurrence: None,
+            series_id: None,
+            parent_id: None,
+            depends_on: Vec::new(),
+            tags: BTreeSet::new(),
+            uuid: None,
         }
     }
 
@@ -108,20 +382,58 @@ impl Task {
             description,
             completed: false,
             created_at: Local::now(),
+            completed_at: None,
+            modified_at: None,
+            revision: 0,
             due_date,
+            due_has_time: false,
+            scheduled: None,
+            wait: None,
             category,
             priority,
+            recurrence: None,
+            series_id: None,
+            parent_id: None,
+            depends_on: Vec::new(),
+            tags: BTreeSet::new(),
+            uuid: None,
         }
     }
 
-    /// Mark the task as completed
+    /// Check whether two versions of a task are the same apart from their change stamps
+    pub fn same_content(&self, other: &Task) -> bool {
+        let unstamped = |task: &Task| Task { modified_at: None, revision: 0, ..task.clone() };
+        unstamped(self) == unstamped(other)
+    }
+
+    /// The due date as `YYYY-MM-DD`, followed by `HH:MM` if it has a time of day
+    pub fn due_date_string(&self) -> Option<String> {
+        let format = if self.due_has_time { "%Y-%m-%d %H:%M" } else { "%Y-%m-%d" };
+        self.due_date.map(|due_date| due_date.format(format).to_string())
+    }
+
+    /// Mark the task as completed, recording the completion time
     pub fn complete(&mut self) {
+        if !self.completed {
+            self.completed_at = Some(Local::now());
+        }
         self.completed = true;
     }
 
     /// Mark the task as incomplete
     pub fn uncomplete(&mut self) {
         self.completed = false;
+        self.completed_at = None;
+    }
+
+    /// Check if the task repeats
+    pub fn is_recurring(&self) -> bool {
+        self.recurrence.is_some()
+    }
+
+    /// Get the ID identifying this task's recurrence series
+    pub fn series_root(&self) -> u32 {
+        self.series_id.unwrap_or(self.id)
     }
 
     /// Check if the task is overdue
@@ -133,17 +445,51 @@ impl Task {
         }
     }
 
-    /// Check if the task is due soon (within a week)
-    pub fn is_due_soon(&self) -> bool {
+    /// Check if the task is due within `window` from now
+    pub fn is_due_soon(&self, window: Duration) -> bool {
         if let Some(due_date) = self.due_date {
             let now = Local::now();
-            let one_week_from_now = now + chrono::Duration::weeks(1);
-            !self.completed && now <= due_date && due_date <= one_week_from_now
+            let window_end = now + window;
+            !self.completed && now <= due_date && due_date <= window_end
         } else {
             false
         }
     }
 
+    /// Check if the task is pending and hidden until its wait date
+    pub fn is_waiting(&self) -> bool {
+        !self.completed && self.wait.is_some_and(|wait| Local::now() < wait)
+    }
+
+    /// Check if the task can be worked on today
+    ///
+    /// That is, it is pending, not waiting, not blocked, and not scheduled to
+    /// start after today.
+    pub fn is_available(&self, todo_list: &TodoList) -> bool {
+        let today = Local::now().date_naive();
+        !self.completed
+            && !self.is_waiting()
+            && !self.is_blocked(todo_list)
+            && self.scheduled.is_none_or(|scheduled| scheduled.date_naive() <= today)
+    }
+
+    /// Check if the task has the given tag
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.contains(tag)
+    }
+
+    /// Check if the task is blocked by dependencies that are still open
+    ///
+    /// Completed tasks are never blocked, and dependencies that no longer exist
+    /// in `todo_list` are ignored.
+    pub fn is_blocked(&self, todo_list: &TodoList) -> bool {
+        !self.completed
+            && self
+                .depends_on
+                .iter()
+                .any(|dep| todo_list.get_task(*dep).is_some_and(|task| !task.completed))
+    }
+
     /// Check if the task is due today
     pub fn is_due_today(&self) -> bool {
         if let Some(due_date) = self.due_date {
@@ -157,11 +503,21 @@ impl Task {
     }
 }
 
+/// A removed task, kept in the trash until it is restored or expires
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrashedTask {
+    pub deleted_at: DateTime<Local>,
+    pub task: Task,
+}
+
 /// Collection of tasks with management operations
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TodoList {
     tasks: Vec<Task>,
     next_id: u32,
+    /// Removed tasks, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    trash: Vec<TrashedTask>,
 }
 
 impl TodoList {
@@ -170,10 +526,12 @@ impl TodoList {
         Self {
             tasks: Vec::new(),
             next_id: 1,
+            trash: Vec::new(),
         }
     }
 
     /// Add a new task to the list
+    #[allow(dead_code)]
     pub fn add_task(&mut self, title: String) -> u32 {
         let id = self.next_id;
         let task = Task::new(id, title);
@@ -209,32 +567,193 @@ impl TodoList {
     }
 
     /// Remove a task by ID
+    ///
+    /// Subtasks of the removed task are moved up to the removed task's parent.
     pub fn remove_task(&mut self, id: u32) -> Option<Task> {
-        if let Some(pos) = self.tasks.iter().position(|task| task.id == id) {
-            Some(self.tasks.remove(pos))
-        } else {
-            None
+        let pos = self.tasks.iter().position(|task| task.id == id)?;
+        let removed = self.tasks.remove(pos);
+
+        for task in &mut self.tasks {
+            if task.parent_id == Some(id) {
+                task.parent_id = removed.parent_id;
+            }
+            task.depends_on.retain(|dep| *dep != id);
+        }
+
+        Some(removed)
+    }
+
+    /// Add a fully built task, e.g. one read by an importer, under the next free ID
+    ///
+    /// The task's own `id` is ignored and the new ID is returned.
+    pub fn insert_task(&mut self, mut task: Task) -> u32 {
+        let id = self.next_id;
+        task.id = id;
+        self.tasks.push(task);
+        self.next_id += 1;
+        id
+    }
+
+    /// Put a task back exactly as given, replacing any task with the same ID
+    ///
+    /// Unlike adding a task this keeps the task's ID and leaves other tasks
+    /// alone, so earlier states can be restored when undoing operations.
+    pub fn restore_task(&mut self, task: Task) {
+        match self.tasks.iter().position(|existing| existing.id == task.id) {
+            Some(pos) => self.tasks[pos] = task,
+            None => {
+                let pos = self.tasks.partition_point(|existing| existing.id < task.id);
+                self.tasks.insert(pos, task);
+            }
         }
     }
 
+    /// Drop a task without adjusting the subtasks or dependencies referring to it
+    pub fn discard_task(&mut self, id: u32) -> Option<Task> {
+        let pos = self.tasks.iter().position(|task| task.id == id)?;
+        Some(self.tasks.remove(pos))
+    }
+
+    /// Record which tasks changed since `before`
+    ///
+    /// Each task that differs from its version in `before` gets `modified_at`
+    /// set to `now` and a revision above both versions', so the revision keeps
+    /// growing even when an undo puts back an older version of the task.
+    pub fn stamp_changes(&mut self, before: &TodoList, now: DateTime<Local>) {
+        for task in &mut self.tasks {
+            if let Some(old) = before.get_task(task.id).filter(|old| !old.same_content(task)) {
+                task.modified_at = Some(now);
+                task.revision = task.revision.max(old.revision) + 1;
+            }
+        }
+    }
+
+    /// Remove a task like `remove_task`, keeping it in the trash
+    pub fn trash_task(&mut self, id: u32) -> Option<&Task> {
+        let task = self.remove_task(id)?;
+        self.trash.push(TrashedTask {
+            deleted_at: Local::now(),
+            task,
+        });
+        self.trash.last().map(|trashed| &trashed.task)
+    }
+
+    /// Removed tasks, oldest first
+    pub fn trash(&self) -> &[TrashedTask] {
+        &self.trash
+    }
+
+    /// Move a task from the trash back into the list
+    ///
+    /// The task gets a new ID if its old one has been taken since. Its parent
+    /// and dependencies are kept if those tasks still exist. Subtasks and
+    /// dependents of the task aren't linked to it again. Returns the task's ID.
+    pub fn restore_from_trash(&mut self, id: u32) -> Result<u32> {
+        let pos = self
+            .trash
+            .iter()
+            .rposition(|trashed| trashed.task.id == id)
+            .ok_or_else(|| anyhow!("Task with ID {} not found in the trash", id))?;
+        let mut task = self.trash.remove(pos).task;
+
+        if self.get_task(task.id).is_some() {
+            task.id = self.next_id;
+        }
+        if task.parent_id.is_some_and(|parent| self.get_task(parent).is_none()) {
+            task.parent_id = None;
+        }
+        task.depends_on.retain(|dep| self.get_task(*dep).is_some());
+
+        let id = task.id;
+        self.next_id = self.next_id.max(id + 1);
+        self.restore_task(task);
+        Ok(id)
+    }
+
+    /// Delete tasks in the trash for good, only those removed before `cutoff` if given
+    ///
+    /// Returns the number of tasks deleted.
+    pub fn empty_trash(&mut self, cutoff: Option<DateTime<Local>>) -> usize {
+        let before = self.trash.len();
+        self.trash.retain(|trashed| cutoff.is_some_and(|cutoff| trashed.deleted_at >= cutoff));
+        before - self.trash.len()
+    }
+
+    /// Put an entry back into the trash, unless it is there already
+    ///
+    /// Used to restore earlier states when undoing operations.
+    pub fn restore_trash_entry(&mut self, entry: TrashedTask) {
+        if !self.trash.contains(&entry) {
+            let pos = self.trash.partition_point(|trashed| trashed.deleted_at <= entry.deleted_at);
+            self.trash.insert(pos, entry);
+        }
+    }
+
+    /// Drop an entry from the trash, if it is still there
+    pub fn discard_trash_entry(&mut self, entry: &TrashedTask) {
+        self.trash.retain(|trashed| trashed != entry);
+    }
+
     /// Complete a task by ID
+    #[allow(dead_code)]
     pub fn complete_task(&mut self, id: u32) -> bool {
-        if let Some(task) = self.get_task_mut(id) {
-            task.complete();
-            true
-        } else {
-            false
-        }
+        self.mark_complete(id).is_ok()
     }
 
     /// Mark a task as complete by ID with error handling
-    pub fn mark_complete(&mut self, id: u32) -> Result<()> {
-        if let Some(task) = self.get_task_mut(id) {
-            task.complete();
-            Ok(())
+    ///
+    /// Completing a pending recurring task spawns the next instance of its series,
+    /// whose ID is returned. The recurrence rule moves to the new instance.
+    /// Pending subtasks are completed along with their parent.
+    pub fn mark_complete(&mut self, id: u32) -> Result<Option<u32>> {
+        let next_id = self.next_id;
+        let task = self
+            .get_task_mut(id)
+            .ok_or_else(|| anyhow!("Task with ID {} not found", id))?;
+
+        if task.completed {
+            return Ok(None);
+        }
+        task.complete();
+
+        let spawned = if let Some(recurrence) = task.recurrence.take() {
+            let now = Local::now();
+            let mut next = task.clone();
+            next.id = next_id;
+            next.completed = false;
+            next.completed_at = None;
+            next.created_at = now;
+            // A new instance is a new task to other tools, not another change to this one
+            next.uuid = None;
+            next.modified_at = None;
+            next.revision = 0;
+            let next_due = recurrence.next_due_date(task.due_date, now);
+            // Scheduled and wait dates keep their distance to the due date
+            let shift = next_due - task.due_date.unwrap_or(now);
+            next.due_date = Some(next_due);
+            next.scheduled = task.scheduled.map(|scheduled| scheduled + shift);
+            next.wait = task.wait.map(|wait| wait + shift);
+            next.series_id = Some(task.series_root());
+            next.recurrence = Some(recurrence);
+
+            self.tasks.push(next);
+            self.next_id += 1;
+            Some(next_id)
         } else {
-            Err(anyhow!("Task with ID {} not found", id))
+            None
+        };
+
+        let pending_children: Vec<u32> = self
+            .get_subtasks(id)
+            .iter()
+            .filter(|task| !task.completed)
+            .map(|task| task.id)
+            .collect();
+        for child_id in pending_children {
+            self.mark_complete(child_id)?;
         }
+
+        Ok(spawned)
     }
 
     /// Mark a task as incomplete by ID with error handling
@@ -257,14 +776,27 @@ impl TodoList {
                 task.description = description;
             }
             if let Some(due_date) = updates.due_date {
+                task.due_has_time = due_date.is_some() && updates.due_has_time;
                 task.due_date = due_date;
             }
+            if let Some(scheduled) = updates.scheduled {
+                task.scheduled = scheduled;
+            }
+            if let Some(wait) = updates.wait {
+                task.wait = wait;
+            }
             if let Some(category) = updates.category {
                 task.category = category;
             }
             if let Some(priority) = updates.priority {
                 task.priority = priority;
             }
+            if let Some(recurrence) = updates.recurrence {
+                task.recurrence = recurrence;
+            }
+            if let Some(tags) = updates.tags {
+                task.tags = tags;
+            }
             Ok(())
         } else {
             Err(anyhow!("Task with ID {} not found", id))
@@ -281,24 +813,47 @@ impl TodoList {
         self.tasks.iter().filter(|task| task.completed).collect()
     }
 
+    /// Get tasks completed within a time window (both bounds inclusive)
+    ///
+    /// Tasks completed before completion times were recorded are never included.
+    pub fn get_completed_between(
+        &self,
+        since: Option<DateTime<Local>>,
+        until: Option<DateTime<Local>>,
+    ) -> Vec<&Task> {
+        self.tasks
+            .iter()
+            .filter(|task| task.completed)
+            .filter(|task| match task.completed_at {
+                Some(completed_at) => {
+                    since.iter().all(|since| completed_at >= *since)
+                        && until.iter().all(|until| completed_at <= *until)
+                }
+                None => false,
+            })
+            .collect()
+    }
+
     /// Get pending (incomplete) tasks
     pub fn get_pending_tasks(&self) -> Vec<&Task> {
         self.tasks.iter().filter(|task| !task.completed).collect()
     }
 
     /// Get tasks by category
+    #[allow(dead_code)]
     pub fn get_tasks_by_category(&self, category: &str) -> Vec<&Task> {
         self.tasks
             .iter()
             .filter(|task| {
                 task.category
                     .as_ref()
-                    .map_or(false, |cat| cat == category)
+                    .is_some_and(|cat| cat == category)
             })
             .collect()
     }
 
     /// Get tasks by priority
+    #[allow(dead_code)]
     pub fn get_tasks_by_priority(&self, priority: Priority) -> Vec<&Task> {
         self.tasks
             .iter()
@@ -311,9 +866,10 @@ impl TodoList {
         self.tasks.iter().filter(|task| task.is_overdue()).collect()
     }
 
-    /// Get tasks due soon (within a week)
-    pub fn get_due_soon_tasks(&self) -> Vec<&Task> {
-        self.tasks.iter().filter(|task| task.is_due_soon()).collect()
+    /// Get tasks due soon (see `Task::is_due_soon`)
+    #[allow(dead_code)]
+    pub fn get_due_soon_tasks(&self, window: Duration) -> Vec<&Task> {
+        self.tasks.iter().filter(|task| task.is_due_soon(window)).collect()
     }
 
     /// Get tasks due today
@@ -321,9 +877,187 @@ impl TodoList {
         self.tasks.iter().filter(|task| task.is_due_today()).collect()
     }
 
+    /// Get tasks that can be worked on today
+    pub fn get_available_tasks(&self) -> Vec<&Task> {
+        self.tasks.iter().filter(|task| task.is_available(self)).collect()
+    }
+
+    /// Get the direct subtasks of a task
+    pub fn get_subtasks(&self, id: u32) -> Vec<&Task> {
+        self.tasks
+            .iter()
+            .filter(|task| task.parent_id == Some(id))
+            .collect()
+    }
+
+    /// Get all subtasks of a task, recursively
+    pub fn get_descendants(&self, id: u32) -> Vec<&Task> {
+        let mut descendants = Vec::new();
+        let mut queue = vec![id];
+
+        while let Some(current) = queue.pop() {
+            for task in self.get_subtasks(current) {
+                queue.push(task.id);
+                descendants.push(task);
+            }
+        }
+
+        descendants
+    }
+
+    /// Get the (completed, total) subtask counts of a task, rolled up over all levels
+    ///
+    /// Returns `None` if the task has no subtasks.
+    pub fn subtask_progress(&self, id: u32) -> Option<(usize, usize)> {
+        let descendants = self.get_descendants(id);
+        if descendants.is_empty() {
+            return None;
+        }
+        let done = descendants.iter().filter(|task| task.completed).count();
+        Some((done, descendants.len()))
+    }
+
+    /// Set or clear the parent of a task
+    ///
+    /// Fails if either task doesn't exist or if the change would make a task
+    /// its own ancestor.
+    pub fn set_parent(&mut self, id: u32, parent_id: Option<u32>) -> Result<()> {
+        if self.get_task(id).is_none() {
+            return Err(anyhow!("Task with ID {} not found", id));
+        }
+
+        if let Some(parent_id) = parent_id {
+            if self.get_task(parent_id).is_none() {
+                return Err(anyhow!("Parent task with ID {} not found", parent_id));
+            }
+            if parent_id == id || self.get_descendants(id).iter().any(|task| task.id == parent_id) {
+                return Err(anyhow!("Task {} cannot be a subtask of itself or of its own subtasks", id));
+            }
+        }
+
+        if let Some(task) = self.get_task_mut(id) {
+            task.parent_id = parent_id;
+        }
+        Ok(())
+    }
+
+    /// Make a task depend on another task
+    ///
+    /// Fails if either task doesn't exist or if the new dependency would create a cycle.
+    pub fn add_dependency(&mut self, id: u32, depends_on: u32) -> Result<()> {
+        if self.get_task(id).is_none() {
+            return Err(anyhow!("Task with ID {} not found", id));
+        }
+        if self.get_task(depends_on).is_none() {
+            return Err(anyhow!("Dependency task with ID {} not found", depends_on));
+        }
+        if id == depends_on || self.depends_transitively(depends_on, id) {
+            return Err(anyhow!(
+                "Task {} cannot depend on task {}: this would create a dependency cycle",
+                id, depends_on
+            ));
+        }
+
+        if let Some(task) = self.get_task_mut(id) {
+            if !task.depends_on.contains(&depends_on) {
+                task.depends_on.push(depends_on);
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove a dependency from a task
+    pub fn remove_dependency(&mut self, id: u32, depends_on: u32) -> Result<()> {
+        let task = self
+            .get_task_mut(id)
+            .ok_or_else(|| anyhow!("Task with ID {} not found", id))?;
+
+        let before = task.depends_on.len();
+        task.depends_on.retain(|dep| *dep != depends_on);
+        if task.depends_on.len() == before {
+            return Err(anyhow!("Task {} does not depend on task {}", id, depends_on));
+        }
+        Ok(())
+    }
+
+    /// Check whether `id` depends on `target`, directly or through other tasks
+    fn depends_transitively(&self, id: u32, target: u32) -> bool {
+        let mut visited = std::collections::HashSet::new();
+        let mut stack = vec![id];
+
+        while let Some(current) = stack.pop() {
+            if !visited.insert(current) {
+                continue;
+            }
+            if let Some(task) = self.get_task(current) {
+                for dep in &task.depends_on {
+                    if *dep == target {
+                        return true;
+                    }
+                    stack.push(*dep);
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Get the dependencies of a task that are not completed yet
+    pub fn get_open_dependencies(&self, id: u32) -> Vec<&Task> {
+        self.get_task(id)
+            .map(|task| {
+                task.depends_on
+                    .iter()
+                    .filter_map(|dep| self.get_task(*dep))
+                    .filter(|dep| !dep.completed)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Get pending tasks blocked by open dependencies
+    pub fn get_blocked_tasks(&self) -> Vec<&Task> {
+        self.tasks.iter().filter(|task| task.is_blocked(self)).collect()
+    }
+
+    /// Get tasks carrying a recurrence rule (the live instance of each series)
+    pub fn get_recurring_tasks(&self) -> Vec<&Task> {
+        self.tasks.iter().filter(|task| task.is_recurring()).collect()
+    }
+
+    /// Get all tasks belonging to a recurrence series, including completed instances
+    pub fn get_series_tasks(&self, series_root: u32) -> Vec<&Task> {
+        self.tasks
+            .iter()
+            .filter(|task| task.series_root() == series_root)
+            .collect()
+    }
+
+    /// Stop the recurrence series a task belongs to
+    ///
+    /// Completed instances are kept; the series simply stops spawning new ones.
+    pub fn stop_recurrence(&mut self, id: u32) -> Result<usize> {
+        let series_root = self
+            .get_task(id)
+            .map(|task| task.series_root())
+            .ok_or_else(|| anyhow!("Task with ID {} not found", id))?;
+
+        let mut count = 0;
+        for task in &mut self.tasks {
+            if task.series_root() == series_root && task.recurrence.take().is_some() {
+                count += 1;
+            }
+        }
+
+        if count == 0 {
+            return Err(anyhow!("Task with ID {} is not part of an active recurrence series", id));
+        }
+
+        Ok(count)
+    }
+
     /// Get all categories with their task counts
-    pub fn get_all_categories(&self) -> std::collections::HashMap<String, usize> {
-        use std::collections::HashMap;
+    pub fn get_all_categories(&self) -> HashMap<String, usize> {
         let mut category_counts = HashMap::new();
 
         for task in &self.tasks {
@@ -355,6 +1089,39 @@ impl TodoList {
         Ok(count)
     }
 
+    /// Get all tags with their task counts
+    pub fn get_all_tags(&self) -> HashMap<String, usize> {
+        let mut tag_counts = HashMap::new();
+
+        for task in &self.tasks {
+            for tag in &task.tags {
+                *tag_counts.entry(tag.clone()).or_insert(0) += 1;
+            }
+        }
+
+        tag_counts
+    }
+
+    /// Rename a tag across all tasks
+    ///
+    /// Tasks that already carry the new tag simply lose the old one.
+    pub fn rename_tag(&mut self, old_name: &str, new_name: &str) -> Result<usize> {
+        let mut count = 0;
+
+        for task in &mut self.tasks {
+            if task.tags.remove(old_name) {
+                task.tags.insert(new_name.to_string());
+                count += 1;
+            }
+        }
+
+        if count == 0 {
+            return Err(anyhow!("No tasks found with tag '{}'", old_name));
+        }
+
+        Ok(count)
+    }
+
     /// Search tasks by query in title and description
     pub fn search_tasks(&self, query: &str, case_insensitive: bool, use_regex: bool) -> Result<Vec<&Task>> {
         use regex::Regex;
@@ -430,7 +1197,18 @@ impl TodoList {
         self.tasks.len()
     }
 
+    /// Get the ID that will be assigned to the next new task
+    pub fn next_id(&self) -> u32 {
+        self.next_id
+    }
+
+    /// Set the ID that will be assigned to the next new task
+    pub fn set_next_id(&mut self, next_id: u32) {
+        self.next_id = next_id;
+    }
+
     /// Check if the todo list is empty
+    #[allow(dead_code)]
     pub fn is_empty(&self) -> bool {
         self.tasks.is_empty()
     }
@@ -468,9 +1246,8 @@ impl TodoList {
                 .map_err(|e| anyhow!("Failed to create backup at {}: {}", backup_path.display(), e))?;
         }
 
-        // Serialize to JSON
-        let json_data = serde_json::to_string_pretty(self)
-            .map_err(|e| anyhow!("Failed to serialize todo list: {}", e))?;
+        // Serialize to JSON in the current versioned format
+        let json_data = schema::to_json(self)?;
 
         // Write atomically using a temporary file
         let temp_path = path.with_extension("json.tmp");
@@ -485,6 +1262,7 @@ impl TodoList {
     }
 
     /// Save the todo list to the default file location
+    #[allow(dead_code)]
     pub fn save(&self) -> Result<()> {
         let path = Self::default_file_path()?;
         self.save_to_file(path)
@@ -505,13 +1283,30 @@ impl TodoList {
             return Ok(Self::new());
         }
 
-        let todo_list: Self = serde_json::from_str(&contents)
-            .map_err(|e| anyhow!("Failed to parse JSON from {}: {}. File may be corrupted.", path.display(), e))?;
+        let (todo_list, version) = schema::from_json(&contents).map_err(|e| {
+            if e.is::<serde_json::Error>() {
+                anyhow!("Failed to parse JSON from {}: {}. File may be corrupted.", path.display(), e)
+            } else if e.is::<schema::UnsupportedVersion>() {
+                e
+            } else {
+                anyhow!("Failed to load {}: {}", path.display(), e)
+            }
+        })?;
+
+        // Keep a copy of files written in an older format before they get overwritten
+        if version < schema::CURRENT_VERSION {
+            let backup_path = path.with_extension(format!("json.v{}.backup", version));
+            if !backup_path.exists() {
+                fs::copy(path, &backup_path)
+                    .map_err(|e| anyhow!("Failed to back up {} before migration: {}", path.display(), e))?;
+            }
+        }
 
         Ok(todo_list)
     }
 
     /// Load a todo list from the default file location
+    #[allow(dead_code)]
     pub fn load() -> Result<Self> {
         let path = Self::default_file_path()?;
         Self::load_from_file(path)
@@ -527,7 +1322,7 @@ impl Default for TodoList {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use chrono::{Duration, Local};
+    use chrono::{Duration, Local, TimeZone, Weekday};
 
     #[test]
     fn test_priority_default() {
@@ -573,9 +1368,21 @@ mod tests {
 
         task.complete();
         assert!(task.completed);
+        assert!(task.completed_at.is_some());
 
         task.uncomplete();
         assert!(!task.completed);
+        assert!(task.completed_at.is_none());
+    }
+
+    #[test]
+    fn test_completed_at_kept_when_completing_again() {
+        let mut task = Task::new(1, "Test task".to_string());
+        task.complete();
+        let first = task.completed_at;
+
+        task.complete();
+        assert_eq!(task.completed_at, first);
     }
 
     #[test]
@@ -878,14 +1685,20 @@ mod tests {
 
         let task = todo_list.get_task(id).unwrap();
         assert_eq!(task.due_date, Some(due_date));
+        assert!(!task.due_has_time);
+
+        let timed_update = TaskUpdate::new().due_date(Some(due_date)).due_has_time(true);
+        todo_list.update_task(id, timed_update).unwrap();
+        assert!(todo_list.get_task(id).unwrap().due_has_time);
 
         // Clear the due date
-        let clear_update = TaskUpdate::new().due_date(None);
+        let clear_update = TaskUpdate::new().due_date(None).due_has_time(true);
         let result = todo_list.update_task(id, clear_update);
         assert!(result.is_ok());
 
         let task = todo_list.get_task(id).unwrap();
         assert_eq!(task.due_date, None);
+        assert!(!task.due_has_time);
     }
 
     #[test]
@@ -1200,8 +2013,8 @@ mod tests {
 
         // Verify the tasks were updated
         for task in todo_list.get_all_tasks() {
-            if task.category.is_some() {
-                assert_eq!(task.category.as_ref().unwrap(), "business");
+            if let Some(category) = &task.category {
+                assert_eq!(category, "business");
             }
         }
     }
@@ -1450,6 +2263,32 @@ mod tests {
         assert_eq!(results.len(), 2); // Both tasks have "description" in title
     }
 
+    #[test]
+    fn test_waiting_and_available() {
+        let mut todo_list = TodoList::new();
+        let free = todo_list.add_task("Water plants".to_string());
+        let waiting = todo_list.add_task("Renew certificate".to_string());
+        let scheduled = todo_list.add_task("Plan offsite".to_string());
+        let blocked = todo_list.add_task("Book venue".to_string());
+        todo_list.update_task(waiting, TaskUpdate::new().wait(Some(Local::now() + Duration::days(14)))).unwrap();
+        todo_list.update_task(scheduled, TaskUpdate::new().scheduled(Some(Local::now() + Duration::days(2)))).unwrap();
+        todo_list.add_dependency(blocked, scheduled).unwrap();
+
+        assert!(todo_list.get_task(waiting).unwrap().is_waiting());
+        assert!(!todo_list.get_task(scheduled).unwrap().is_waiting());
+        let available: Vec<u32> = todo_list.get_available_tasks().iter().map(|task| task.id).collect();
+        assert_eq!(available, vec![free]);
+
+        // A wait date in the past no longer hides the task, and completed tasks never wait
+        todo_list.update_task(waiting, TaskUpdate::new().wait(Some(Local::now() - Duration::days(1)))).unwrap();
+        todo_list.update_task(scheduled, TaskUpdate::new().scheduled(Some(Local::now()))).unwrap();
+        assert!(!todo_list.get_task(waiting).unwrap().is_waiting());
+        assert_eq!(todo_list.get_available_tasks().len(), 3);
+        todo_list.update_task(free, TaskUpdate::new().wait(Some(Local::now() + Duration::days(1)))).unwrap();
+        todo_list.mark_complete(free).unwrap();
+        assert!(!todo_list.get_task(free).unwrap().is_waiting());
+    }
+
     #[test]
     fn test_is_due_today() {
         let today = Local::now();
@@ -1550,4 +2389,416 @@ mod tests {
         assert_eq!(due_today_tasks.len(), 1);
         assert_eq!(due_today_tasks[0].title, "Due today");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_recurrence_parse() {
+        assert_eq!("daily".parse::<Recurrence>().unwrap(), Recurrence::Daily);
+        assert_eq!(
+            "weekly".parse::<Recurrence>().unwrap(),
+            Recurrence::Weekly { weekdays: vec![] }
+        );
+        assert_eq!(
+            "Weekly:thu,mon".parse::<Recurrence>().unwrap(),
+            Recurrence::Weekly { weekdays: vec![Weekday::Mon, Weekday::Thu] }
+        );
+        assert_eq!("monthly:15".parse::<Recurrence>().unwrap(), Recurrence::Monthly { day: 15 });
+        assert_eq!("after:3".parse::<Recurrence>().unwrap(), Recurrence::AfterCompletion { days: 3 });
+
+        assert!("monthly:32".parse::<Recurrence>().is_err());
+        assert!("weekly:funday".parse::<Recurrence>().is_err());
+        assert!("after:0".parse::<Recurrence>().is_err());
+        assert!("hourly".parse::<Recurrence>().is_err());
+    }
+
+    #[test]
+    fn test_recurrence_display_round_trip() {
+        for rule in ["daily", "weekly", "weekly:mon,fri", "monthly:31", "after:10"] {
+            let recurrence: Recurrence = rule.parse().unwrap();
+            assert_eq!(recurrence.to_string(), rule);
+        }
+    }
+
+    #[test]
+    fn test_recurrence_next_due_date() {
+        let at = |y, m, d| Local.with_ymd_and_hms(y, m, d, 9, 30, 0).unwrap();
+        let completed_at = at(2024, 1, 10);
+
+        // 2024-01-10 is a Wednesday
+        assert_eq!(Recurrence::Daily.next_due_date(Some(at(2024, 1, 10)), at(2024, 1, 9)), at(2024, 1, 11));
+        assert_eq!(
+            Recurrence::Weekly { weekdays: vec![] }.next_due_date(Some(at(2024, 1, 10)), at(2024, 1, 9)),
+            at(2024, 1, 17)
+        );
+        assert_eq!(
+            Recurrence::Weekly { weekdays: vec![Weekday::Mon, Weekday::Thu] }
+                .next_due_date(Some(at(2024, 1, 10)), at(2024, 1, 9)),
+            at(2024, 1, 11)
+        );
+        assert_eq!(
+            Recurrence::Monthly { day: 31 }.next_due_date(Some(at(2024, 1, 31)), at(2024, 1, 30)),
+            at(2024, 2, 29)
+        );
+        assert_eq!(
+            Recurrence::AfterCompletion { days: 3 }.next_due_date(Some(at(2024, 1, 1)), completed_at),
+            at(2024, 1, 13)
+        );
+
+        // Calendar rules skip occurrences already in the past
+        assert_eq!(Recurrence::Daily.next_due_date(Some(at(2024, 1, 1)), completed_at), at(2024, 1, 11));
+    }
+
+    #[test]
+    fn test_mark_complete_spawns_next_instance() {
+        let mut todo_list = TodoList::new();
+        let due_date = Local::now() + Duration::days(1);
+        let id = todo_list.add_task_with_details(
+            "Standup prep".to_string(),
+            Some("Collect updates".to_string()),
+            Some(due_date),
+            Some("work".to_string()),
+            Priority::High,
+        );
+        let wait = due_date - Duration::hours(2);
+        todo_list
+            .update_task(id, TaskUpdate::new().recurrence(Some(Recurrence::Daily)).wait(Some(wait)))
+            .unwrap();
+
+        let next_id = todo_list.mark_complete(id).unwrap().expect("next instance");
+        assert_eq!(todo_list.len(), 2);
+
+        let completed = todo_list.get_task(id).unwrap();
+        assert!(completed.completed);
+        assert!(completed.recurrence.is_none());
+
+        let next = todo_list.get_task(next_id).unwrap();
+        assert!(!next.completed);
+        assert!(next.completed_at.is_none());
+        assert_eq!(next.title, "Standup prep");
+        assert_eq!(next.category, Some("work".to_string()));
+        assert_eq!(next.priority, Priority::High);
+        assert_eq!(next.recurrence, Some(Recurrence::Daily));
+        assert_eq!(next.series_id, Some(id));
+        assert_eq!(next.due_date, Some(due_date + Duration::days(1)));
+        assert_eq!(next.wait, Some(wait + Duration::days(1)));
+
+        // Completing an already completed instance doesn't spawn again
+        assert_eq!(todo_list.mark_complete(id).unwrap(), None);
+        assert_eq!(todo_list.get_series_tasks(id).len(), 2);
+    }
+
+    #[test]
+    fn test_stop_recurrence() {
+        let mut todo_list = TodoList::new();
+        let id = todo_list.add_task("Expense report".to_string());
+        todo_list
+            .update_task(id, TaskUpdate::new().recurrence(Some(Recurrence::Monthly { day: 1 })))
+            .unwrap();
+        let next_id = todo_list.mark_complete(id).unwrap().unwrap();
+        assert_eq!(todo_list.get_recurring_tasks().len(), 1);
+
+        // Stopping via any member of the series clears the live instance
+        assert_eq!(todo_list.stop_recurrence(id).unwrap(), 1);
+        assert!(todo_list.get_recurring_tasks().is_empty());
+        assert_eq!(todo_list.mark_complete(next_id).unwrap(), None);
+
+        let result = todo_list.stop_recurrence(next_id);
+        assert!(result.is_err());
+        assert!(todo_list.stop_recurrence(999).is_err());
+    }
+
+    #[test]
+    fn test_subtasks_and_progress() {
+        let mut todo_list = TodoList::new();
+        let release = todo_list.add_task("Release".to_string());
+        let notes = todo_list.add_task("Write notes".to_string());
+        let tag = todo_list.add_task("Tag build".to_string());
+        let sign = todo_list.add_task("Sign tag".to_string());
+
+        todo_list.set_parent(notes, Some(release)).unwrap();
+        todo_list.set_parent(tag, Some(release)).unwrap();
+        todo_list.set_parent(sign, Some(tag)).unwrap();
+
+        assert_eq!(todo_list.get_subtasks(release).len(), 2);
+        assert_eq!(todo_list.get_descendants(release).len(), 3);
+        assert_eq!(todo_list.subtask_progress(release), Some((0, 3)));
+        assert_eq!(todo_list.subtask_progress(sign), None);
+
+        todo_list.mark_complete(notes).unwrap();
+        assert_eq!(todo_list.subtask_progress(release), Some((1, 3)));
+    }
+
+    #[test]
+    fn test_set_parent_rejects_cycles() {
+        let mut todo_list = TodoList::new();
+        let parent = todo_list.add_task("Parent".to_string());
+        let child = todo_list.add_task("Child".to_string());
+        let grandchild = todo_list.add_task("Grandchild".to_string());
+        todo_list.set_parent(child, Some(parent)).unwrap();
+        todo_list.set_parent(grandchild, Some(child)).unwrap();
+
+        assert!(todo_list.set_parent(parent, Some(parent)).is_err());
+        assert!(todo_list.set_parent(parent, Some(grandchild)).is_err());
+        assert!(todo_list.set_parent(child, Some(999)).is_err());
+        assert!(todo_list.set_parent(999, Some(parent)).is_err());
+
+        todo_list.set_parent(grandchild, None).unwrap();
+        assert_eq!(todo_list.get_task(grandchild).unwrap().parent_id, None);
+    }
+
+    #[test]
+    fn test_complete_parent_completes_subtasks() {
+        let mut todo_list = TodoList::new();
+        let parent = todo_list.add_task("Parent".to_string());
+        let child = todo_list.add_task("Child".to_string());
+        let grandchild = todo_list.add_task("Grandchild".to_string());
+        let unrelated = todo_list.add_task("Unrelated".to_string());
+        todo_list.set_parent(child, Some(parent)).unwrap();
+        todo_list.set_parent(grandchild, Some(child)).unwrap();
+
+        todo_list.mark_complete(parent).unwrap();
+
+        assert!(todo_list.get_task(child).unwrap().completed);
+        assert!(todo_list.get_task(grandchild).unwrap().completed);
+        assert!(!todo_list.get_task(unrelated).unwrap().completed);
+    }
+
+    #[test]
+    fn test_remove_parent_promotes_subtasks() {
+        let mut todo_list = TodoList::new();
+        let root = todo_list.add_task("Root".to_string());
+        let middle = todo_list.add_task("Middle".to_string());
+        let leaf = todo_list.add_task("Leaf".to_string());
+        todo_list.set_parent(middle, Some(root)).unwrap();
+        todo_list.set_parent(leaf, Some(middle)).unwrap();
+
+        todo_list.remove_task(middle).unwrap();
+        assert_eq!(todo_list.get_task(leaf).unwrap().parent_id, Some(root));
+
+        todo_list.remove_task(root).unwrap();
+        assert_eq!(todo_list.get_task(leaf).unwrap().parent_id, None);
+    }
+
+    #[test]
+    fn test_dependencies_and_blocked_state() {
+        let mut todo_list = TodoList::new();
+        let review = todo_list.add_task("Review".to_string());
+        let deploy = todo_list.add_task("Deploy".to_string());
+        todo_list.add_dependency(deploy, review).unwrap();
+
+        assert!(todo_list.get_task(deploy).unwrap().is_blocked(&todo_list));
+        assert!(!todo_list.get_task(review).unwrap().is_blocked(&todo_list));
+        assert_eq!(todo_list.get_open_dependencies(deploy).len(), 1);
+        assert_eq!(todo_list.get_blocked_tasks().len(), 1);
+
+        todo_list.mark_complete(review).unwrap();
+        assert!(!todo_list.get_task(deploy).unwrap().is_blocked(&todo_list));
+        assert!(todo_list.get_open_dependencies(deploy).is_empty());
+
+        todo_list.remove_dependency(deploy, review).unwrap();
+        assert!(todo_list.get_task(deploy).unwrap().depends_on.is_empty());
+        assert!(todo_list.remove_dependency(deploy, review).is_err());
+    }
+
+    #[test]
+    fn test_dependency_cycle_detection() {
+        let mut todo_list = TodoList::new();
+        let a = todo_list.add_task("A".to_string());
+        let b = todo_list.add_task("B".to_string());
+        let c = todo_list.add_task("C".to_string());
+        todo_list.add_dependency(b, a).unwrap();
+        todo_list.add_dependency(c, b).unwrap();
+
+        assert!(todo_list.add_dependency(a, a).is_err());
+        assert!(todo_list.add_dependency(a, c).is_err());
+        assert!(todo_list.add_dependency(a, 999).is_err());
+
+        // Adding the same dependency twice is a no-op
+        todo_list.add_dependency(c, b).unwrap();
+        assert_eq!(todo_list.get_task(c).unwrap().depends_on, vec![b]);
+    }
+
+    #[test]
+    fn test_trash_restore_and_empty() {
+        let mut todo_list = TodoList::new();
+        let parent = todo_list.add_task("Parent".to_string());
+        let child = todo_list.add_task("Child".to_string());
+        let other = todo_list.add_task("Other".to_string());
+        todo_list.set_parent(child, Some(parent)).unwrap();
+        todo_list.add_dependency(other, child).unwrap();
+
+        assert_eq!(todo_list.trash_task(child).unwrap().title, "Child");
+        assert!(todo_list.trash_task(99).is_none());
+        todo_list.trash_task(parent);
+        assert_eq!(todo_list.len(), 1);
+        assert!(todo_list.get_task(other).unwrap().depends_on.is_empty());
+        assert_eq!(todo_list.trash().len(), 2);
+
+        // The parent is gone, so the child comes back as a top-level task
+        assert_eq!(todo_list.restore_from_trash(child).unwrap(), child);
+        assert_eq!(todo_list.get_task(child).unwrap().parent_id, None);
+        assert!(todo_list.restore_from_trash(child).is_err());
+
+        // A task whose ID has been taken in the meantime gets a new one
+        todo_list.trash_task(child);
+        todo_list.restore_task(Task::new(child, "Replacement".to_string()));
+        assert_eq!(todo_list.restore_from_trash(child).unwrap(), 4);
+        assert_eq!(todo_list.next_id(), 5);
+
+        assert_eq!(todo_list.empty_trash(Some(Local::now() - Duration::days(1))), 0);
+        assert_eq!(todo_list.empty_trash(None), 1);
+        assert!(todo_list.trash().is_empty());
+    }
+
+    #[test]
+    fn test_stamp_changes() {
+        let mut todo_list = TodoList::new();
+        let edited = todo_list.add_task("Edited".to_string());
+        let untouched = todo_list.add_task("Untouched".to_string());
+        let now = Local::now();
+
+        let before = todo_list.clone();
+        todo_list.stamp_changes(&before, now);
+        assert_eq!(todo_list.get_all_tasks(), before.get_all_tasks());
+
+        todo_list.update_task(edited, TaskUpdate::new().priority(Priority::High)).unwrap();
+        let added = todo_list.add_task("Added".to_string());
+        todo_list.stamp_changes(&before, now);
+        assert_eq!(todo_list.get_task(edited).unwrap().modified_at, Some(now));
+        assert_eq!(todo_list.get_task(edited).unwrap().revision, 1);
+        assert_eq!(todo_list.get_task(untouched).unwrap().revision, 0);
+        assert_eq!(todo_list.get_task(added).unwrap().modified_at, None);
+
+        // Going back to an earlier state (as undo does) still counts as a change
+        let stamped = todo_list.clone();
+        let mut undone = before.clone();
+        assert!(undone.get_task(edited).unwrap().same_content(before.get_task(edited).unwrap()));
+        undone.stamp_changes(&stamped, now);
+        assert_eq!(undone.get_task(edited).unwrap().revision, 2);
+    }
+
+    #[test]
+    fn test_remove_task_drops_dependencies_on_it() {
+        let mut todo_list = TodoList::new();
+        let review = todo_list.add_task("Review".to_string());
+        let deploy = todo_list.add_task("Deploy".to_string());
+        todo_list.add_dependency(deploy, review).unwrap();
+
+        todo_list.remove_task(review);
+        let deploy_task = todo_list.get_task(deploy).unwrap();
+        assert!(deploy_task.depends_on.is_empty());
+        assert!(!deploy_task.is_blocked(&todo_list));
+    }
+
+    #[test]
+    fn test_extract_and_normalize_tags() {
+        let (title, tags) = extract_tags("Call customer +urgent +work today");
+        assert_eq!(title, "Call customer today");
+        assert_eq!(tags.into_iter().collect::<Vec<_>>(), vec!["urgent", "work"]);
+
+        // Words that merely contain a plus are left alone
+        let (title, tags) = extract_tags("Learn C++ and a+b");
+        assert_eq!(title, "Learn C++ and a+b");
+        assert!(tags.is_empty());
+
+        assert_eq!(normalize_tag("+work").unwrap(), "work");
+        assert_eq!(normalize_tag("work").unwrap(), "work");
+        assert!(normalize_tag("+").is_err());
+        assert!(normalize_tag("two words").is_err());
+    }
+
+    #[test]
+    fn test_tags_counts_and_rename() {
+        let mut todo_list = TodoList::new();
+        let id1 = todo_list.add_task("Task 1".to_string());
+        let id2 = todo_list.add_task("Task 2".to_string());
+        todo_list.add_task("Task 3".to_string());
+        todo_list.update_task(id1, TaskUpdate::new().tags(["work", "urgent"])).unwrap();
+        todo_list.update_task(id2, TaskUpdate::new().tags(["work", "customer"])).unwrap();
+
+        let tags = todo_list.get_all_tags();
+        assert_eq!(tags.len(), 3);
+        assert_eq!(tags.get("work"), Some(&2));
+        assert_eq!(tags.get("urgent"), Some(&1));
+
+        // Renaming onto an existing tag merges instead of duplicating
+        assert_eq!(todo_list.rename_tag("urgent", "customer").unwrap(), 1);
+        let tags = todo_list.get_all_tags();
+        assert_eq!(tags.get("customer"), Some(&2));
+        assert_eq!(tags.get("urgent"), None);
+        assert!(todo_list.get_task(id1).unwrap().has_tag("customer"));
+
+        let result = todo_list.rename_tag("missing", "other");
+        assert!(result.is_err());
+        assert_eq!(result.unwrap_err().to_string(), "No tasks found with tag 'missing'");
+    }
+
+    #[test]
+    fn test_get_completed_between() {
+        let mut todo_list = TodoList::new();
+        let old = todo_list.add_task("Old".to_string());
+        let recent = todo_list.add_task("Recent".to_string());
+        let legacy = todo_list.add_task("Legacy".to_string());
+        todo_list.add_task("Pending".to_string());
+
+        todo_list.mark_complete(old).unwrap();
+        todo_list.mark_complete(recent).unwrap();
+        todo_list.get_task_mut(old).unwrap().completed_at = Some(Local::now() - Duration::days(10));
+        // Completed before completion times were recorded
+        todo_list.get_task_mut(legacy).unwrap().completed = true;
+
+        let week_ago = Some(Local::now() - Duration::days(7));
+        let done = todo_list.get_completed_between(week_ago, None);
+        assert_eq!(done.len(), 1);
+        assert_eq!(done[0].id, recent);
+
+        let done = todo_list.get_completed_between(None, week_ago);
+        assert_eq!(done.len(), 1);
+        assert_eq!(done[0].id, old);
+
+        assert_eq!(todo_list.get_completed_between(None, None).len(), 2);
+    }
+
+    #[test]
+    fn test_load_legacy_file_creates_migration_backup() {
+        let temp_dir = std::env::temp_dir();
+        let test_file = temp_dir.join("test_legacy_todo.json");
+        let migration_backup = temp_dir.join("test_legacy_todo.json.v1.backup");
+        let _ = fs::remove_file(&migration_backup);
+
+        let legacy = r#"{"tasks": [{"id": 1, "title": "Legacy task", "description": null,
+            "completed": false, "created_at": "2024-09-21T07:16:57-04:00", "due_date": null,
+            "category": null, "priority": "Medium"}], "next_id": 2}"#;
+        fs::write(&test_file, legacy).unwrap();
+
+        let loaded_list = TodoList::load_from_file(&test_file).unwrap();
+        assert_eq!(loaded_list.get_task(1).unwrap().title, "Legacy task");
+        assert_eq!(fs::read_to_string(&migration_backup).unwrap(), legacy);
+
+        // Saving writes the current version
+        loaded_list.save_to_file(&test_file).unwrap();
+        let saved: serde_json::Value = serde_json::from_str(&fs::read_to_string(&test_file).unwrap()).unwrap();
+        assert_eq!(saved["version"], schema::CURRENT_VERSION);
+
+        // Clean up
+        let _ = fs::remove_file(&test_file);
+        let _ = fs::remove_file(&migration_backup);
+        let _ = fs::remove_file(temp_dir.join("test_legacy_todo.json.backup"));
+    }
+
+    #[test]
+    fn test_load_newer_version_fails() {
+        let temp_dir = std::env::temp_dir();
+        let test_file = temp_dir.join("test_future_todo.json");
+        fs::write(&test_file, r#"{"version": 99, "tasks": [], "next_id": 1}"#).unwrap();
+
+        let result = TodoList::load_from_file(&test_file);
+        let error = result.unwrap_err();
+        assert!(error.is::<schema::UnsupportedVersion>());
+        let message = error.to_string();
+        assert!(message.contains("schema version 99"));
+        assert!(!message.contains("corrupted"));
+
+        // Clean up
+        let _ = fs::remove_file(&test_file);
+    }
+}
@@ -0,0 +1,216 @@
+//! Machine-readable output for listing commands
+//!
+//! With `--output json` a command prints one pretty-printed JSON document;
+//! with `--output jsonl` it prints one compact JSON object per line. The
+//! records below are the documented output schema: fields are never omitted
+//! (missing values are `null`), and new fields are only ever added.
+
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use serde::Serialize;
+
+use crate::models::{Priority, Task, TodoList};
+
+/// Output formats for listing commands
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Coloured human-readable text
+    #[default]
+    Text,
+    /// A single JSON document
+    Json,
+    /// One JSON object per line
+    Jsonl,
+}
+
+/// A task as printed by `list`, `search`, `due-today`, `overdue` and `done`
+#[derive(Debug, Serialize)]
+pub struct TaskRecord<'a> {
+    pub id: u32,
+    pub title: &'a str,
+    pub description: Option<&'a str>,
+    pub completed: bool,
+    /// "low", "medium" or "high"
+    pub priority: &'static str,
+    pub category: Option<&'a str>,
+    pub tags: Vec<&'a str>,
+    pub due_date: Option<DateTime<Local>>,
+    /// Whether `due_date` has a time of day, rather than being due by the end of the day
+    pub due_has_time: bool,
+    pub scheduled: Option<DateTime<Local>>,
+    pub wait: Option<DateTime<Local>>,
+    pub created_at: DateTime<Local>,
+    pub completed_at: Option<DateTime<Local>>,
+    /// Recurrence rule in the syntax accepted by `--repeat`
+    pub recurrence: Option<String>,
+    pub parent_id: Option<u32>,
+    pub depends_on: &'a [u32],
+    pub overdue: bool,
+    pub blocked: bool,
+}
+
+impl<'a> TaskRecord<'a> {
+    pub fn new(task: &'a Task, todo_list: &TodoList) -> Self {
+        Self {
+            id: task.id,
+            title: &task.title,
+            description: task.description.as_deref(),
+            completed: task.completed,
+            priority: match task.priority {
+                Priority::Low => "low",
+                Priority::Medium => "medium",
+                Priority::High => "high",
+            },
+            category: task.category.as_deref(),
+            tags: task.tags.iter().map(String::as_str).collect(),
+            due_date: task.due_date,
+            due_has_time: task.due_has_time,
+            scheduled: task.scheduled,
+            wait: task.wait,
+            created_at: task.created_at,
+            completed_at: task.completed_at,
+            recurrence: task.recurrence.as_ref().map(|rule| rule.to_string()),
+            parent_id: task.parent_id,
+            depends_on: &task.depends_on,
+            overdue: task.is_overdue(),
+            blocked: task.is_blocked(todo_list),
+        }
+    }
+}
+
+/// A category and the number of tasks in it, as printed by `categories`
+#[derive(Debug, Serialize)]
+pub struct CategoryRecord<'a> {
+    pub name: &'a str,
+    pub count: usize,
+}
+
+/// A tag and the number of tasks that have it, as printed by `tags`
+#[derive(Debug, Serialize)]
+pub struct TagRecord<'a> {
+    pub name: &'a str,
+    pub count: usize,
+}
+
+/// A recurring task and how many instances of its series are completed, as
+/// printed by `recurring`
+#[derive(Debug, Serialize)]
+pub struct RecurringRecord<'a> {
+    #[serde(flatten)]
+    pub task: TaskRecord<'a>,
+    pub completed_count: usize,
+}
+
+/// An operation that can be undone, as printed by `history`
+#[derive(Debug, Serialize)]
+pub struct HistoryRecord<'a> {
+    /// 1 for the operation `undo` would revert next
+    pub step: usize,
+    pub command: &'a str,
+    pub timestamp: DateTime<Local>,
+    /// Number of tasks the operation changed
+    pub tasks: usize,
+}
+
+/// A saved view and the `list` arguments it runs, as printed by `view list`
+#[derive(Debug, Serialize)]
+pub struct ViewRecord<'a> {
+    pub name: &'a str,
+    pub args: &'a [String],
+}
+
+/// A removed task and when it was removed, as printed by `trash list`
+#[derive(Debug, Serialize)]
+pub struct TrashRecord<'a> {
+    #[serde(flatten)]
+    pub task: TaskRecord<'a>,
+    pub deleted_at: DateTime<Local>,
+}
+
+/// Task counts printed when rtodo runs without a subcommand
+#[derive(Debug, Serialize)]
+pub struct SummaryRecord {
+    pub total: usize,
+    pub completed: usize,
+    pub pending: usize,
+    pub overdue: usize,
+    pub blocked: usize,
+}
+
+/// Error reported on stderr in machine-readable modes
+#[derive(Debug, Serialize)]
+pub struct ErrorRecord {
+    pub error: String,
+}
+
+/// Print a list of records: a JSON array, or one object per line
+pub fn print_records<T: Serialize>(format: OutputFormat, records: &[T]) -> Result<()> {
+    match format {
+        OutputFormat::Jsonl => {
+            for record in records {
+                println!("{}", serde_json::to_string(record)?);
+            }
+        }
+        _ => println!("{}", serde_json::to_string_pretty(records)?),
+    }
+    Ok(())
+}
+
+/// Print a single record as a JSON document, or as one line
+pub fn print_record<T: Serialize>(format: OutputFormat, record: &T) -> Result<()> {
+    match format {
+        OutputFormat::Jsonl => println!("{}", serde_json::to_string(record)?),
+        _ => println!("{}", serde_json::to_string_pretty(record)?),
+    }
+    Ok(())
+}
+
+/// Print tasks as `TaskRecord`s
+pub fn print_tasks(format: OutputFormat, tasks: &[&Task], todo_list: &TodoList) -> Result<()> {
+    let records: Vec<TaskRecord> = tasks
+        .iter()
+        .map(|task| TaskRecord::new(task, todo_list))
+        .collect();
+    print_records(format, &records)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TaskUpdate;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_task_record_schema() {
+        let mut todo_list = TodoList::new();
+        let first = todo_list.add_task_with_details(
+            "Write report".to_string(),
+            None,
+            None,
+            Some("work".to_string()),
+            Priority::High,
+        );
+        let second = todo_list.add_task_with_details("Send report".to_string(), None, None, None, Priority::Low);
+        todo_list
+            .update_task(second, TaskUpdate::new().tags(["email"]).recurrence(Some("weekly".parse().unwrap())))
+            .unwrap();
+        todo_list.add_dependency(second, first).unwrap();
+
+        let value = serde_json::to_value(TaskRecord::new(todo_list.get_task(second).unwrap(), &todo_list)).unwrap();
+        let keys: BTreeSet<&str> = value.as_object().unwrap().keys().map(String::as_str).collect();
+        let expected = [
+            "id", "title", "description", "completed", "priority", "category", "tags", "due_date",
+            "due_has_time", "scheduled", "wait", "created_at", "completed_at", "recurrence", "parent_id",
+            "depends_on", "overdue", "blocked",
+        ];
+        assert_eq!(keys, expected.into_iter().collect());
+        assert_eq!(value["priority"], "low");
+        assert_eq!(value["tags"], serde_json::json!(["email"]));
+        assert_eq!(value["recurrence"], "weekly");
+        assert_eq!(value["depends_on"], serde_json::json!([first]));
+        assert_eq!(value["blocked"], true);
+        assert!(value["category"].is_null());
+        assert!(value["due_date"].is_null());
+        assert_eq!(value["due_has_time"], false);
+    }
+}
@@ -0,0 +1,730 @@
+//! Filter expressions for `list`, `search` and `export`
+//!
+//! A query is a boolean expression over conditions on task fields:
+//!
+//! ```text
+//! category:work and (priority:high or due<friday) and not tag:someday
+//! ```
+//!
+//! # Grammar
+//!
+//! ```text
+//! query     := or
+//! or        := and ("or" and)*
+//! and       := not ("and"? not)*        conditions next to each other must all match
+//! not       := "not" not | primary
+//! primary   := "(" query ")" | condition
+//! condition := FIELD OP VALUE | +TAG | WORD
+//! OP        := ":" | "=" | "!=" | "<" | "<=" | ">" | ">="
+//! ```
+//!
+//! # Conditions
+//!
+//! - `category:NAME` (or `cat:`), `category:none`
+//! - `tag:NAME` or `+NAME`
+//! - `priority:high` (or `pri:`), with `<`/`>` comparing low < medium < high
+//! - `status:pending|completed|overdue|due-soon|blocked|ready|recurring|waiting|available` (or `is:`)
+//! - `due`, `scheduled`, `wait`, `created` and `completed` (or `done`) compared
+//!   by day with a date in any format `--due` accepts; `due:none` matches tasks
+//!   without a due date
+//! - `title:TEXT` matches the title, a bare `WORD` the title or description
+//! - `id:N` (with comparisons) and `parent:N` or `parent:none`
+//!
+//! Text, category and tag matching ignores case. Values containing spaces are
+//! quoted: `title:"buy milk"`, `due<"next friday"`.
+
+use chrono::{Duration, Local, NaiveDate};
+use chrono_english::Dialect;
+use std::fmt;
+
+use crate::models::{Priority, Task, TodoList};
+
+/// A parsed filter expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    And(Box<Query>, Box<Query>),
+    Or(Box<Query>, Box<Query>),
+    Not(Box<Query>),
+    Condition(Condition),
+}
+
+/// A single test against a task
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    /// The title or description contains the text
+    Text(String),
+    /// The title contains the text
+    Title(String),
+    /// The task is in the category, or has none
+    Category(Option<String>),
+    Tag(String),
+    Priority(Comparison, Priority),
+    Status(Status),
+    /// Compares the day of a date field; `None` matches tasks without that date
+    Date(DateField, Comparison, Option<NaiveDate>),
+    Id(Comparison, u32),
+    /// The task is a subtask of the given task, or a top-level task
+    Parent(Option<u32>),
+}
+
+/// How a task's value is compared with the one in the query
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl Comparison {
+    fn holds<T: Ord>(self, left: T, right: T) -> bool {
+        match self {
+            Comparison::Eq => left == right,
+            Comparison::Lt => left < right,
+            Comparison::Le => left <= right,
+            Comparison::Gt => left > right,
+            Comparison::Ge => left >= right,
+        }
+    }
+}
+
+/// Task states usable with `status:`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Pending,
+    Completed,
+    Overdue,
+    DueSoon,
+    Blocked,
+    Ready,
+    Recurring,
+    Waiting,
+    Available,
+}
+
+/// Date fields usable in comparisons
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateField {
+    Due,
+    Scheduled,
+    Wait,
+    Created,
+    Completed,
+}
+
+const FIELDS: &str = "category, tag, priority, status, due, scheduled, wait, created, completed, title, id, parent";
+
+impl Query {
+    /// Parse a query expression, reading dates such as 02/11 in `dialect`
+    pub fn parse(input: &str, dialect: Dialect) -> Result<Self, QueryError> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser { input, dialect, tokens, position: 0 };
+        if parser.tokens.is_empty() {
+            return Err(parser.error_at_end("empty query"));
+        }
+
+        let query = parser.parse_or()?;
+        match parser.tokens.get(parser.position) {
+            Some(token) if token.kind == TokenKind::Close => Err(parser.error(token, "unexpected ')' without a matching '('")),
+            Some(token) => Err(parser.error(token, "expected 'and' or 'or'")),
+            None => Ok(query),
+        }
+    }
+
+    /// Combine queries so that all of them must match; `None` if there are none
+    pub fn all<I: IntoIterator<Item = Query>>(queries: I) -> Option<Self> {
+        queries
+            .into_iter()
+            .reduce(|left, right| Query::And(Box::new(left), Box::new(right)))
+    }
+
+    /// Check whether a task matches the query
+    ///
+    /// `due_soon_window` is how far ahead `status:due-soon` looks.
+    pub fn matches(&self, task: &Task, todo_list: &TodoList, due_soon_window: Duration) -> bool {
+        let matches = |query: &Query| query.matches(task, todo_list, due_soon_window);
+        match self {
+            Query::And(left, right) => matches(left) && matches(right),
+            Query::Or(left, right) => matches(left) || matches(right),
+            Query::Not(query) => !matches(query),
+            Query::Condition(condition) => condition.matches(task, todo_list, due_soon_window),
+        }
+    }
+
+    /// Check whether the query tests for a status anywhere, even negated
+    pub fn mentions(&self, status: Status) -> bool {
+        match self {
+            Query::And(left, right) | Query::Or(left, right) => left.mentions(status) || right.mentions(status),
+            Query::Not(query) => query.mentions(status),
+            Query::Condition(condition) => *condition == Condition::Status(status),
+        }
+    }
+}
+
+impl Condition {
+    fn matches(&self, task: &Task, todo_list: &TodoList, due_soon_window: Duration) -> bool {
+        let contains = |text: &str, needle: &str| text.to_lowercase().contains(&needle.to_lowercase());
+        match self {
+            Condition::Text(text) => {
+                contains(&task.title, text) || task.description.as_deref().is_some_and(|d| contains(d, text))
+            }
+            Condition::Title(text) => contains(&task.title, text),
+            Condition::Category(None) => task.category.is_none(),
+            Condition::Category(Some(name)) => task.category.as_deref().is_some_and(|c| c.eq_ignore_ascii_case(name)),
+            Condition::Tag(name) => task.tags.iter().any(|tag| tag.eq_ignore_ascii_case(name)),
+            Condition::Priority(comparison, priority) => comparison.holds(rank(&task.priority), rank(priority)),
+            Condition::Status(status) => match status {
+                Status::Pending => !task.completed,
+                Status::Completed => task.completed,
+                Status::Overdue => task.is_overdue(),
+                Status::DueSoon => task.is_due_soon(due_soon_window),
+                Status::Blocked => task.is_blocked(todo_list),
+                Status::Ready => !task.completed && !task.is_blocked(todo_list),
+                Status::Recurring => task.is_recurring(),
+                Status::Waiting => task.is_waiting(),
+                Status::Available => task.is_available(todo_list),
+            },
+            Condition::Date(field, comparison, date) => {
+                let value = match field {
+                    DateField::Due => task.due_date,
+                    DateField::Scheduled => task.scheduled,
+                    DateField::Wait => task.wait,
+                    DateField::Created => Some(task.created_at),
+                    DateField::Completed => task.completed_at,
+                };
+                match (value, date) {
+                    (value, None) => value.is_none(),
+                    (Some(value), Some(date)) => comparison.holds(value.date_naive(), *date),
+                    (None, Some(_)) => false,
+                }
+            }
+            Condition::Id(comparison, id) => comparison.holds(task.id, *id),
+            Condition::Parent(parent_id) => task.parent_id == *parent_id,
+        }
+    }
+}
+
+fn rank(priority: &Priority) -> u8 {
+    match priority {
+        Priority::Low => 0,
+        Priority::Medium => 1,
+        Priority::High => 2,
+    }
+}
+
+/// A query that could not be parsed, with the part of the input at fault
+#[derive(Debug)]
+pub struct QueryError {
+    message: String,
+    input: String,
+    start: usize,
+    end: usize,
+}
+
+impl fmt::Display for QueryError {
+    /// Shows the message and the query with the offending token underlined
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let column = self.input[..self.start].chars().count();
+        let width = self.input[self.start..self.end].chars().count().max(1);
+        write!(
+            f,
+            "Invalid query: {}\n  {}\n  {}{}",
+            self.message,
+            self.input,
+            " ".repeat(column),
+            "^".repeat(width)
+        )
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Word,
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+}
+
+/// A token and its byte range in the input
+#[derive(Debug)]
+struct Token {
+    kind: TokenKind,
+    start: usize,
+    end: usize,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = input.char_indices().peekable();
+
+    while let Some(&(start, ch)) = chars.peek() {
+        if ch.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        if ch == '(' || ch == ')' {
+            chars.next();
+            let kind = if ch == '(' { TokenKind::Open } else { TokenKind::Close };
+            tokens.push(Token { kind, start, end: start + 1 });
+            continue;
+        }
+
+        // A word runs to the next space or parenthesis outside quotes
+        let mut end = input.len();
+        let mut quote_start = None;
+        while let Some(&(index, ch)) = chars.peek() {
+            if quote_start.is_none() && (ch.is_whitespace() || ch == '(' || ch == ')') {
+                end = index;
+                break;
+            }
+            if ch == '"' {
+                quote_start = match quote_start {
+                    Some(_) => None,
+                    None => Some(index),
+                };
+            }
+            chars.next();
+        }
+        if let Some(quote) = quote_start {
+            return Err(QueryError {
+                message: "unterminated quote".to_string(),
+                input: input.to_string(),
+                start: quote,
+                end: quote + 1,
+            });
+        }
+
+        let kind = match input[start..end].to_ascii_lowercase().as_str() {
+            "and" => TokenKind::And,
+            "or" => TokenKind::Or,
+            "not" => TokenKind::Not,
+            _ => TokenKind::Word,
+        };
+        tokens.push(Token { kind, start, end });
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    dialect: Dialect,
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn error(&self, token: &Token, message: &str) -> QueryError {
+        self.error_span(token.start, token.end, message)
+    }
+
+    fn error_span(&self, start: usize, end: usize, message: &str) -> QueryError {
+        QueryError {
+            message: message.to_string(),
+            input: self.input.to_string(),
+            start,
+            end,
+        }
+    }
+
+    fn error_at_end(&self, message: &str) -> QueryError {
+        self.error_span(self.input.len(), self.input.len(), message)
+    }
+
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.position).map(|token| &token.kind)
+    }
+
+    fn parse_or(&mut self) -> Result<Query, QueryError> {
+        let mut query = self.parse_and()?;
+        while self.peek() == Some(&TokenKind::Or) {
+            self.position += 1;
+            let right = self.parse_and()?;
+            query = Query::Or(Box::new(query), Box::new(right));
+        }
+        Ok(query)
+    }
+
+    fn parse_and(&mut self) -> Result<Query, QueryError> {
+        let mut query = self.parse_not()?;
+        loop {
+            match self.peek() {
+                Some(TokenKind::And) => self.position += 1,
+                Some(TokenKind::Word | TokenKind::Open | TokenKind::Not) => {}
+                _ => break,
+            }
+            let right = self.parse_not()?;
+            query = Query::And(Box::new(query), Box::new(right));
+        }
+        Ok(query)
+    }
+
+    fn parse_not(&mut self) -> Result<Query, QueryError> {
+        if self.peek() == Some(&TokenKind::Not) {
+            self.position += 1;
+            return Ok(Query::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Query, QueryError> {
+        let Some(token) = self.tokens.get(self.position) else {
+            return Err(self.error_at_end("expected a condition at the end of the query"));
+        };
+        let (start, end) = (token.start, token.end);
+        match token.kind {
+            TokenKind::Open => {
+                let open = self.position;
+                self.position += 1;
+                let query = self.parse_or()?;
+                if self.peek() != Some(&TokenKind::Close) {
+                    return Err(self.error(&self.tokens[open], "missing ')' for this '('"));
+                }
+                self.position += 1;
+                Ok(query)
+            }
+            TokenKind::Close => Err(self.error(token, "expected a condition before ')'")),
+            TokenKind::And | TokenKind::Or => Err(self.error(
+                token,
+                &format!("expected a condition before '{}'", &self.input[token.start..token.end]),
+            )),
+            TokenKind::Not => unreachable!("handled by parse_not"),
+            TokenKind::Word => {
+                self.position += 1;
+                self.parse_condition(start, end)
+            }
+        }
+    }
+
+    fn parse_condition(&self, start: usize, end: usize) -> Result<Query, QueryError> {
+        let word = &self.input[start..end];
+
+        if let Some(tag) = word.strip_prefix('+').filter(|tag| !tag.is_empty()) {
+            return Ok(Query::Condition(Condition::Tag(unquote(tag))));
+        }
+
+        // Operators only count before the first quote
+        let unquoted = &word[..word.find('"').unwrap_or(word.len())];
+        let Some(op_start) = unquoted.find([':', '=', '!', '<', '>']) else {
+            return Ok(Query::Condition(Condition::Text(unquote(word))));
+        };
+        let op_len = match &word[op_start..] {
+            rest if rest.starts_with("<=") || rest.starts_with(">=") || rest.starts_with("!=") => 2,
+            rest if rest.starts_with('!') => {
+                return Err(self.error_span(start + op_start, start + op_start + 1, "expected '!=' for not equal"));
+            }
+            _ => 1,
+        };
+        let field = &word[..op_start];
+        let operator = &word[op_start..op_start + op_len];
+        let value_start = start + op_start + op_len;
+        let value = unquote(&word[op_start + op_len..]);
+
+        if field.is_empty() {
+            return Err(self.error_span(start, value_start, "missing field name before the operator"));
+        }
+        if value.is_empty() {
+            return Err(self.error_span(start, end, &format!("missing value after '{}{}'", field, operator)));
+        }
+
+        let field_error = |message: String| self.error_span(start, start + op_start, &message);
+        let value_error = |message: String| self.error_span(value_start, end, &message);
+        let comparison = match operator {
+            "<" => Comparison::Lt,
+            "<=" => Comparison::Le,
+            ">" => Comparison::Gt,
+            ">=" => Comparison::Ge,
+            _ => Comparison::Eq,
+        };
+        let equality_only = |name: &str| {
+            if comparison == Comparison::Eq {
+                Ok(())
+            } else {
+                Err(self.error_span(start + op_start, value_start, &format!("'{}' can only be compared with ':' or '!='", name)))
+            }
+        };
+
+        let condition = match field.to_ascii_lowercase().as_str() {
+            "category" | "cat" => {
+                equality_only("category")?;
+                Condition::Category(Some(value).filter(|value| !value.eq_ignore_ascii_case("none")))
+            }
+            "tag" => {
+                equality_only("tag")?;
+                Condition::Tag(value.trim_start_matches('+').to_string())
+            }
+            "title" => {
+                equality_only("title")?;
+                Condition::Title(value)
+            }
+            "priority" | "pri" => {
+                let priority = match value.to_ascii_lowercase().as_str() {
+                    "high" | "h" => Priority::High,
+                    "medium" | "med" | "m" => Priority::Medium,
+                    "low" | "l" => Priority::Low,
+                    _ => return Err(value_error(format!("unknown priority '{}' (expected high, medium or low)", value))),
+                };
+                Condition::Priority(comparison, priority)
+            }
+            "status" | "is" => {
+                equality_only("status")?;
+                let status = match value.to_ascii_lowercase().as_str() {
+                    "pending" | "open" => Status::Pending,
+                    "completed" | "done" => Status::Completed,
+                    "overdue" => Status::Overdue,
+                    "due-soon" | "soon" => Status::DueSoon,
+                    "blocked" => Status::Blocked,
+                    "ready" => Status::Ready,
+                    "recurring" => Status::Recurring,
+                    "waiting" => Status::Waiting,
+                    "available" => Status::Available,
+                    _ => {
+                        return Err(value_error(format!(
+                            "unknown status '{}' (expected pending, completed, overdue, due-soon, blocked, ready, recurring, waiting or available)",
+                            value
+                        )))
+                    }
+                };
+                Condition::Status(status)
+            }
+            "due" | "scheduled" | "wait" | "created" | "completed" | "done" => {
+                let field = match field.to_ascii_lowercase().as_str() {
+                    "due" => DateField::Due,
+                    "scheduled" => DateField::Scheduled,
+                    "wait" => DateField::Wait,
+                    "created" => DateField::Created,
+                    _ => DateField::Completed,
+                };
+                if value.eq_ignore_ascii_case("none") {
+                    equality_only("none")?;
+                    Condition::Date(field, comparison, None)
+                } else {
+                    let date = parse_day(&value, self.dialect).ok_or_else(|| {
+                        value_error(format!("invalid date '{}' (try YYYY-MM-DD, 'today' or 'friday')", value))
+                    })?;
+                    Condition::Date(field, comparison, Some(date))
+                }
+            }
+            "id" => {
+                let id = value
+                    .parse()
+                    .map_err(|_| value_error(format!("invalid task ID '{}'", value)))?;
+                Condition::Id(comparison, id)
+            }
+            "parent" => {
+                equality_only("parent")?;
+                if value.eq_ignore_ascii_case("none") {
+                    Condition::Parent(None)
+                } else {
+                    let id = value
+                        .parse()
+                        .map_err(|_| value_error(format!("invalid task ID '{}'", value)))?;
+                    Condition::Parent(Some(id))
+                }
+            }
+            _ => return Err(field_error(format!("unknown field '{}' (expected one of: {})", field, FIELDS))),
+        };
+
+        let query = Query::Condition(condition);
+        Ok(if operator == "!=" { Query::Not(Box::new(query)) } else { query })
+    }
+}
+
+fn unquote(text: &str) -> String {
+    text.replace('"', "")
+}
+
+/// Parse a day in any format accepted by `--due`
+fn parse_day(value: &str, dialect: Dialect) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").ok().or_else(|| {
+        chrono_english::parse_date_string(value, Local::now(), dialect)
+            .ok()
+            .map(|date| date.date_naive())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TaskUpdate;
+    use chrono::TimeZone;
+
+    fn parse(input: &str) -> Result<Query, QueryError> {
+        Query::parse(input, Dialect::Us)
+    }
+
+    fn condition(condition: Condition) -> Query {
+        Query::Condition(condition)
+    }
+
+    fn sample_list() -> TodoList {
+        let mut todo_list = TodoList::new();
+        let in_days = |days: i64| Some(Local::now() + Duration::days(days));
+        todo_list.add_task_with_details("Write report".to_string(), None, in_days(2), Some("work".to_string()), Priority::High);
+        todo_list.add_task_with_details("Plan offsite".to_string(), None, in_days(30), Some("Work".to_string()), Priority::Low);
+        todo_list.add_task_with_details(
+            "Learn piano".to_string(),
+            Some("Someday, maybe".to_string()),
+            None,
+            None,
+            Priority::Medium,
+        );
+        todo_list.update_task(3, TaskUpdate::new().tags(["someday"])).unwrap();
+        todo_list.add_task_with_details("Fix bike".to_string(), None, in_days(-1), Some("home".to_string()), Priority::High);
+        todo_list.add_dependency(2, 1).unwrap();
+        todo_list.mark_complete(4).unwrap();
+        todo_list
+    }
+
+    fn matching(query: &str, todo_list: &TodoList) -> Vec<u32> {
+        let query = parse(query).unwrap();
+        todo_list
+            .get_all_tasks()
+            .iter()
+            .filter(|task| query.matches(task, todo_list, Duration::weeks(1)))
+            .map(|task| task.id)
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        let query = parse("category:work and (priority:high or due<2024-05-03) and not tag:someday").unwrap();
+        let expected = Query::And(
+            Box::new(Query::And(
+                Box::new(condition(Condition::Category(Some("work".to_string())))),
+                Box::new(Query::Or(
+                    Box::new(condition(Condition::Priority(Comparison::Eq, Priority::High))),
+                    Box::new(condition(Condition::Date(
+                        DateField::Due,
+                        Comparison::Lt,
+                        NaiveDate::from_ymd_opt(2024, 5, 3),
+                    ))),
+                )),
+            )),
+            Box::new(Query::Not(Box::new(condition(Condition::Tag("someday".to_string()))))),
+        );
+        assert_eq!(query, expected);
+
+        // `and` binds tighter than `or`, and is implied between conditions
+        assert_eq!(parse("a b or c").unwrap(), parse("(a and b) or c").unwrap());
+        assert_eq!(
+            parse("title:\"buy milk\" +errand").unwrap(),
+            Query::And(
+                Box::new(condition(Condition::Title("buy milk".to_string()))),
+                Box::new(condition(Condition::Tag("errand".to_string()))),
+            )
+        );
+        assert_eq!(
+            parse("priority!=low").unwrap(),
+            Query::Not(Box::new(condition(Condition::Priority(Comparison::Eq, Priority::Low))))
+        );
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let todo_list = sample_list();
+        assert_eq!(matching("category:work", &todo_list), vec![1, 2]);
+        assert_eq!(matching("cat:work and not priority:low", &todo_list), vec![1]);
+        assert_eq!(matching("priority>=medium", &todo_list), vec![1, 3, 4]);
+        assert_eq!(matching("category:none or status:completed", &todo_list), vec![3, 4]);
+        assert_eq!(matching("is:blocked", &todo_list), vec![2]);
+        assert_eq!(matching("status:ready", &todo_list), vec![1, 3]);
+        assert_eq!(matching("due<\"7 days\" and status:pending", &todo_list), vec![1]);
+        assert_eq!(matching("due:none", &todo_list), vec![3]);
+        assert_eq!(matching("due>today", &todo_list), vec![1, 2]);
+        assert_eq!(matching("done:today", &todo_list), vec![4]);
+        assert_eq!(matching("someday", &todo_list), vec![3]);
+        assert_eq!(matching("+SOMEDAY or id>3", &todo_list), vec![3, 4]);
+        assert_eq!(matching("parent:none and id<=2", &todo_list), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_waiting_and_scheduled() {
+        let mut todo_list = sample_list();
+        let in_days = |days: i64| Some(Local::now() + Duration::days(days));
+        todo_list.update_task(1, TaskUpdate::new().wait(in_days(3))).unwrap();
+        todo_list.update_task(3, TaskUpdate::new().scheduled(in_days(5))).unwrap();
+        assert_eq!(matching("status:waiting", &todo_list), vec![1]);
+        assert_eq!(matching("is:available", &todo_list), Vec::<u32>::new());
+        assert_eq!(matching("scheduled>today or wait:none", &todo_list), vec![2, 3, 4]);
+
+        let query = parse("+someday or not status:waiting").unwrap();
+        assert!(query.mentions(Status::Waiting));
+        assert!(!query.mentions(Status::Ready));
+    }
+
+    #[test]
+    fn test_dialect_and_due_soon_window() {
+        let due_on = |year, month, day| condition(Condition::Date(DateField::Due, Comparison::Eq, NaiveDate::from_ymd_opt(year, month, day)));
+        assert_eq!(parse("due:02/11/2030").unwrap(), due_on(2030, 2, 11));
+        assert_eq!(Query::parse("due:02/11/2030", Dialect::Uk).unwrap(), due_on(2030, 11, 2));
+
+        let todo_list = sample_list();
+        let query = parse("status:due-soon").unwrap();
+        let due_within = |days: i64| -> Vec<u32> {
+            todo_list
+                .get_all_tasks()
+                .iter()
+                .filter(|task| query.matches(task, &todo_list, Duration::days(days)))
+                .map(|task| task.id)
+                .collect()
+        };
+        assert_eq!(due_within(7), vec![1]);
+        assert_eq!(due_within(60), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_parse_errors_point_at_token() {
+        let error = parse("category:work and prio:high").unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Invalid query: unknown field 'prio' (expected one of: {})\n  category:work and prio:high\n                    ^^^^",
+                FIELDS
+            )
+        );
+
+        let error = parse("due<someday-ish").unwrap_err();
+        assert_eq!((error.start, error.end), (4, 15));
+        assert!(error.message.contains("invalid date"));
+
+        let error = parse("(priority:high or tag:x").unwrap_err();
+        assert_eq!((error.start, error.end), (0, 1));
+        assert!(error.message.contains("missing ')'"));
+
+        let error = parse("tag:x or").unwrap_err();
+        assert_eq!((error.start, error.end), (8, 8));
+
+        let error = parse("and tag:x").unwrap_err();
+        assert!(error.message.contains("before 'and'"));
+
+        let error = parse("tag:x )").unwrap_err();
+        assert_eq!((error.start, error.end), (6, 7));
+
+        assert!(parse("category<work").unwrap_err().message.contains("can only be compared"));
+        assert!(parse("title:\"open").unwrap_err().message.contains("unterminated"));
+        assert!(parse("   ").unwrap_err().message.contains("empty"));
+        assert!(parse("priority:urgent").unwrap_err().message.contains("unknown priority"));
+    }
+
+    #[test]
+    fn test_all_combines_with_and() {
+        assert_eq!(Query::all(Vec::new()), None);
+        let query = Query::all([
+            condition(Condition::Status(Status::Pending)),
+            condition(Condition::Tag("x".to_string())),
+        ])
+        .unwrap();
+        assert_eq!(query, parse("status:pending tag:x").unwrap());
+
+        let mut todo_list = TodoList::new();
+        todo_list.add_task("Task".to_string());
+        todo_list.get_task_mut(1).unwrap().created_at = Local.with_ymd_and_hms(2024, 1, 15, 9, 0, 0).unwrap();
+        assert_eq!(matching("created:2024-01-15", &todo_list), vec![1]);
+        assert!(matching("created<2024-01-15", &todo_list).is_empty());
+    }
+}
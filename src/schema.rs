@@ -0,0 +1,302 @@
+//! On-disk schema versioning for task files
+//!
+//! Task files are written as a versioned envelope: the serialized `TodoList`
+//! with an additional top-level `version` field. Files written by older
+//! releases are upgraded on load by running them through the migration
+//! pipeline one version at a time, working on raw JSON values so that each
+//! migration only needs to know about the layout it upgrades from.
+//!
+//! # Versions
+//!
+//! - **1** - the original unversioned layout: `{"tasks": [...], "next_id": N}`
+//! - **2** - adds the `version` marker and optional task fields (recurrence,
+//!   subtasks, dependencies, tags, completion time)
+//! - **3** - adds the optional task `uuid` recorded by imports; older releases
+//!   would drop it when saving, so they must not open these files
+//! - **4** - adds the optional `trash` of removed tasks, each with the time it
+//!   was removed
+//! - **5** - adds the optional task `due_has_time` flag; older releases would
+//!   show timed due dates as due by the end of the day
+//! - **6** - adds the optional task `scheduled` and `wait` dates
+//! - **7** - adds the optional task `modified_at` time and `revision` count;
+//!   older releases would drop them, so exported calendars would stop
+//!   telling clients about later edits
+
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+
+use crate::models::TodoList;
+
+/// Schema version written by this build
+pub const CURRENT_VERSION: u32 = 7;
+
+/// A migration upgrading a JSON document from one version to the next
+type Migration = fn(Value) -> Result<Value>;
+
+/// Migrations indexed by the version they upgrade from (index 0 upgrades v1 to v2)
+const MIGRATIONS: &[Migration] = &[migrate_v1_to_v2, bump_version, bump_version, bump_version, bump_version, bump_version];
+
+/// Error returned for files written by a newer release of rtodo
+///
+/// Such files must never be replaced by an empty list, so callers can check
+/// for this error to refuse to continue instead of falling back.
+#[derive(Debug)]
+pub struct UnsupportedVersion {
+    pub found: u32,
+}
+
+impl fmt::Display for UnsupportedVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "File uses schema version {}, but this version of rtodo only supports up to version {}. Please upgrade rtodo.",
+            self.found, CURRENT_VERSION
+        )
+    }
+}
+
+impl std::error::Error for UnsupportedVersion {}
+
+/// Versioned envelope used when writing task files
+#[derive(Serialize)]
+struct Envelope<'a> {
+    version: u32,
+    #[serde(flatten)]
+    todo_list: &'a TodoList,
+}
+
+/// Serialize a todo list in the current on-disk format
+pub fn to_json(todo_list: &TodoList) -> Result<String> {
+    let envelope = Envelope {
+        version: CURRENT_VERSION,
+        todo_list,
+    };
+    serde_json::to_string_pretty(&envelope)
+        .map_err(|e| anyhow!("Failed to serialize todo list: {}", e))
+}
+
+/// Detect the schema version of a parsed task file
+///
+/// Files without a `version` field predate versioning and are version 1.
+pub fn detect_version(value: &Value) -> Result<u32> {
+    match value.get("version") {
+        None => Ok(1),
+        Some(version) => version
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .filter(|v| *v >= 1)
+            .ok_or_else(|| anyhow!("Invalid schema version {}", version)),
+    }
+}
+
+/// Upgrade a parsed task file to the current schema version
+///
+/// Returns the migrated document together with the version it was read as.
+pub fn migrate(mut value: Value) -> Result<(Value, u32)> {
+    let original = detect_version(&value)?;
+    if original > CURRENT_VERSION {
+        return Err(UnsupportedVersion { found: original }.into());
+    }
+
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(original as usize - 1) {
+        let from = index as u32 + 1;
+        value = migration(value)
+            .map_err(|e| anyhow!("Failed to migrate from schema version {} to {}: {}", from, from + 1, e))?;
+    }
+
+    Ok((value, original))
+}
+
+/// Parse a task file in any supported version
+///
+/// Returns the todo list and the schema version the file was written with.
+pub fn from_json(contents: &str) -> Result<(TodoList, u32)> {
+    let value: Value = serde_json::from_str(contents)?;
+    from_value(value)
+}
+
+/// Build a todo list from an already parsed document in any supported version
+///
+/// Used by storage backends that keep tasks as JSON but not as a single file.
+pub fn from_value(value: Value) -> Result<(TodoList, u32)> {
+    let (value, original) = migrate(value)?;
+    let todo_list = serde_json::from_value(value)?;
+    Ok((todo_list, original))
+}
+
+/// Version 1 to 2: add the version marker
+///
+/// Version 1 files were sometimes written by hand (e.g. `{"tasks": []}`), so a
+/// missing `next_id` is reconstructed from the highest task ID.
+fn migrate_v1_to_v2(mut value: Value) -> Result<Value> {
+    let object = value
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("expected a JSON object at the top level"))?;
+
+    let tasks = object
+        .entry("tasks")
+        .or_insert_with(|| Value::Array(Vec::new()));
+    let max_id = tasks
+        .as_array()
+        .ok_or_else(|| anyhow!("expected 'tasks' to be an array"))?
+        .iter()
+        .filter_map(|task| task.get("id").and_then(Value::as_u64))
+        .max()
+        .unwrap_or(0);
+
+    object
+        .entry("next_id")
+        .or_insert_with(|| Value::from(max_id + 1));
+    object.insert("version".to_string(), Value::from(2));
+
+    Ok(value)
+}
+
+/// Upgrade to a version that only adds optional fields, so only the marker changes
+///
+/// Versions 3 to 7 all work this way: older files simply lack the new fields.
+fn bump_version(mut value: Value) -> Result<Value> {
+    let version = detect_version(&value)?;
+    let object = value
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("expected a JSON object at the top level"))?;
+    object.insert("version".to_string(), Value::from(version + 1));
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::path::Path;
+
+    fn fixture(name: &str) -> String {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests")
+            .join("fixtures")
+            .join(name);
+        std::fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn test_detect_version() {
+        assert_eq!(detect_version(&json!({"tasks": [], "next_id": 1})).unwrap(), 1);
+        assert_eq!(detect_version(&json!({"version": 2, "tasks": []})).unwrap(), 2);
+        assert!(detect_version(&json!({"version": "two"})).is_err());
+        assert!(detect_version(&json!({"version": 0})).is_err());
+    }
+
+    #[test]
+    fn test_load_v1_fixture() {
+        let (todo_list, version) = from_json(&fixture("v1_tasks.json")).unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(todo_list.len(), 3);
+
+        let task = todo_list.get_task(2).unwrap();
+        assert_eq!(task.title, "Finish quarterly report");
+        assert_eq!(task.category, Some("work".to_string()));
+        assert!(task.completed);
+        assert!(task.completed_at.is_none());
+        assert!(task.tags.is_empty());
+    }
+
+    #[test]
+    fn test_load_v2_fixture() {
+        let (todo_list, version) = from_json(&fixture("v2_tasks.json")).unwrap();
+        assert_eq!(version, 2);
+        assert_eq!(todo_list.len(), 3);
+
+        let task = todo_list.get_task(1).unwrap();
+        assert!(task.recurrence.is_some());
+        assert!(task.tags.contains("standup"));
+        assert_eq!(todo_list.get_task(3).unwrap().parent_id, Some(2));
+        assert_eq!(todo_list.get_task(3).unwrap().depends_on, vec![1]);
+    }
+
+    #[test]
+    fn test_load_v3_fixture() {
+        let (todo_list, version) = from_json(&fixture("v3_tasks.json")).unwrap();
+        assert_eq!(version, 3);
+        assert_eq!(todo_list.len(), 2);
+        assert_eq!(todo_list.get_task(1).unwrap().uuid.as_deref(), Some("6d3c1f2e-8b4a-4c1e-9f0a-2b7d5e8c9a10"));
+        assert_eq!(todo_list.get_task(2).unwrap().uuid, None);
+        assert!(todo_list.trash().is_empty());
+    }
+
+    #[test]
+    fn test_load_v4_fixture() {
+        let (todo_list, version) = from_json(&fixture("v4_tasks.json")).unwrap();
+        assert_eq!(version, 4);
+        assert_eq!(todo_list.len(), 1);
+        assert_eq!(todo_list.trash().len(), 1);
+        assert_eq!(todo_list.trash()[0].task.title, "Old draft");
+        assert!(!todo_list.get_task(1).unwrap().due_has_time);
+    }
+
+    #[test]
+    fn test_load_v5_fixture() {
+        let (todo_list, version) = from_json(&fixture("v5_tasks.json")).unwrap();
+        assert_eq!(version, 5);
+        assert_eq!(todo_list.len(), 2);
+        assert!(todo_list.get_task(1).unwrap().due_has_time);
+        assert!(!todo_list.get_task(2).unwrap().due_has_time);
+        assert!(todo_list.get_task(1).unwrap().scheduled.is_none());
+    }
+
+    #[test]
+    fn test_load_v6_fixture() {
+        let (todo_list, version) = from_json(&fixture("v6_tasks.json")).unwrap();
+        assert_eq!(version, 6);
+        let task = todo_list.get_task(1).unwrap();
+        assert!(task.scheduled.is_some() && task.wait.is_some());
+        assert!(task.modified_at.is_none());
+        assert_eq!(task.revision, 0);
+    }
+
+    #[test]
+    fn test_migrate_only_bumps_marker_after_v2() {
+        for version in 2..CURRENT_VERSION {
+            let document = json!({"version": version, "tasks": [], "next_id": 1});
+            let (value, original) = migrate(document).unwrap();
+            assert_eq!(original, version);
+            assert_eq!(value, json!({"version": CURRENT_VERSION, "tasks": [], "next_id": 1}));
+        }
+    }
+
+    #[test]
+    fn test_migrate_v1_reconstructs_next_id() {
+        let (value, version) = migrate(json!({"tasks": [{"id": 4}, {"id": 9}]})).unwrap();
+        assert_eq!(version, 1);
+        assert_eq!(value["version"], CURRENT_VERSION);
+        assert_eq!(value["next_id"], 10);
+
+        let (value, _) = migrate(json!({})).unwrap();
+        assert_eq!(value["tasks"], json!([]));
+        assert_eq!(value["next_id"], 1);
+    }
+
+    #[test]
+    fn test_newer_version_is_rejected() {
+        let result = from_json(r#"{"version": 99, "tasks": [], "next_id": 1}"#);
+        let error = result.unwrap_err();
+        assert!(error.is::<UnsupportedVersion>());
+        assert!(error.to_string().contains("Please upgrade rtodo"));
+    }
+
+    #[test]
+    fn test_round_trip_writes_current_version() {
+        let mut todo_list = TodoList::new();
+        todo_list.add_task("Round trip".to_string());
+
+        let json = to_json(&todo_list).unwrap();
+        let value: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["version"], CURRENT_VERSION);
+        assert_eq!(value["tasks"][0]["title"], "Round trip");
+
+        let (loaded, version) = from_json(&json).unwrap();
+        assert_eq!(version, CURRENT_VERSION);
+        assert_eq!(loaded.len(), 1);
+    }
+}
@@ -0,0 +1,838 @@
+//! Storage backends for persisting todo lists
+//!
+//! Commands work on an in-memory `TodoList`; a `Storage` implementation is
+//! responsible for loading it before a command runs and saving it afterwards.
+//!
+//! # Backends
+//!
+//! - **json** - the default: the whole list in one versioned JSON file
+//!   (see the `schema` module), rewritten atomically on every save
+//! - **sqlite** - a local SQLite database with one row per task. Saves only
+//!   write the tasks that changed, and the filters supported by `list` are
+//!   answered with indexed queries.
+//!
+//! # Concurrency
+//!
+//! `lock` takes an exclusive advisory lock on a `.lock` file next to the
+//! storage file, so concurrent rtodo processes run their load-modify-save
+//! cycles one after another. Commands that wait for the user, such as editing
+//! in `$EDITOR` or the TUI, release the lock while waiting and take it back to
+//! save. Both backends also remember a fingerprint of the file when loading
+//! and refuse to save with an `ExternalChange` error if another program (or
+//! rtodo process) changed it since.
+
+use anyhow::{anyhow, Result};
+use chrono::{Duration, Local};
+use fs2::FileExt;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Instant, SystemTime};
+
+/// How long to wait for another process to release the storage lock
+const LOCK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Delay between attempts to take the storage lock
+const LOCK_RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_millis(25);
+
+use crate::models::{Priority, Task, TodoList};
+use crate::schema;
+
+/// Available storage backends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    Json,
+    Sqlite,
+}
+
+impl Backend {
+    /// Guess the backend from a file extension (`.db`, `.sqlite`, `.sqlite3` use SQLite)
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("db") | Some("sqlite") | Some("sqlite3") => Backend::Sqlite,
+            _ => Backend::Json,
+        }
+    }
+
+    /// File extension used for this backend's default location
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Backend::Json => "json",
+            Backend::Sqlite => "db",
+        }
+    }
+
+    /// Default location for this backend, next to the default JSON file
+    pub fn default_path(&self) -> Result<PathBuf> {
+        Ok(TodoList::default_file_path()?.with_extension(self.extension()))
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Backend::Json => write!(f, "json"),
+            Backend::Sqlite => write!(f, "sqlite"),
+        }
+    }
+}
+
+/// Completion and due-date status a query can be restricted to
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StatusFilter {
+    #[default]
+    All,
+    Completed,
+    Pending,
+    Overdue,
+    /// Pending and due within the given window
+    DueSoon(Duration),
+}
+
+/// Filters shared by `list` that a backend can answer without loading every task
+#[derive(Debug, Clone, Default)]
+pub struct TaskFilter {
+    pub status: StatusFilter,
+    pub category: Option<String>,
+    pub priority: Option<Priority>,
+    /// Tags a task must all have (without the leading `+`)
+    pub tags: Vec<String>,
+    /// Tags a task must not have (without the leading `+`)
+    pub exclude_tags: Vec<String>,
+}
+
+impl TaskFilter {
+    /// Check whether a task matches the filter
+    pub fn matches(&self, task: &Task) -> bool {
+        let status = match self.status {
+            StatusFilter::All => true,
+            StatusFilter::Completed => task.completed,
+            StatusFilter::Pending => !task.completed,
+            StatusFilter::Overdue => task.is_overdue(),
+            StatusFilter::DueSoon(window) => task.is_due_soon(window),
+        };
+
+        status
+            && (self.category.is_none() || task.category == self.category)
+            && (self.priority.is_none() || self.priority.as_ref() == Some(&task.priority))
+            && self.tags.iter().all(|tag| task.has_tag(tag))
+            && !self.exclude_tags.iter().any(|tag| task.has_tag(tag))
+    }
+}
+
+/// A place todo lists can be loaded from and saved to
+pub trait Storage {
+    /// The backend implementing this storage
+    fn backend(&self) -> Backend;
+
+    /// Location of the underlying file
+    fn path(&self) -> &Path;
+
+    /// Load the todo list, returning an empty list if nothing has been saved yet
+    fn load(&self) -> Result<TodoList>;
+
+    /// Save the todo list, replacing what was stored before
+    fn save(&self, todo_list: &TodoList) -> Result<()>;
+
+    /// Find the IDs of tasks matching a filter, in ID order
+    ///
+    /// The default implementation filters the already loaded `todo_list`;
+    /// backends that can answer the query themselves override it.
+    fn query(&self, todo_list: &TodoList, filter: &TaskFilter) -> Result<Vec<u32>> {
+        let mut ids: Vec<u32> = todo_list
+            .get_all_tasks()
+            .iter()
+            .filter(|task| filter.matches(task))
+            .map(|task| task.id)
+            .collect();
+        ids.sort_unstable();
+        Ok(ids)
+    }
+}
+
+/// Open the storage for a backend at the given path
+pub fn open(backend: Backend, path: PathBuf) -> Box<dyn Storage> {
+    match backend {
+        Backend::Json => Box::new(JsonStorage::new(path)),
+        Backend::Sqlite => Box::new(SqliteStorage::new(path)),
+    }
+}
+
+/// Exclusive advisory lock on a storage file, released when dropped
+#[derive(Debug)]
+pub struct StorageLock {
+    lock_path: PathBuf,
+    file: RefCell<Option<File>>,
+}
+
+impl StorageLock {
+    /// Let other processes in, e.g. while waiting for the user; `reacquire` takes the lock back
+    pub fn release(&self) {
+        self.file.borrow_mut().take();
+    }
+
+    /// Take the lock back after `release`, waiting up to `LOCK_TIMEOUT`; does nothing while it is held
+    pub fn reacquire(&self) -> Result<()> {
+        if self.file.borrow().is_none() {
+            *self.file.borrow_mut() = Some(acquire(&self.lock_path, LOCK_TIMEOUT)?);
+        }
+        Ok(())
+    }
+}
+
+/// Lock the storage file at `path` for the rest of the command
+///
+/// Waits up to `LOCK_TIMEOUT` for another rtodo process to finish.
+pub fn lock(path: &Path) -> Result<StorageLock> {
+    lock_with_timeout(path, LOCK_TIMEOUT)
+}
+
+fn lock_with_timeout(path: &Path, timeout: std::time::Duration) -> Result<StorageLock> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("Invalid storage path {}", path.display()))?;
+    let lock_path = path.with_file_name(format!("{}.lock", file_name.to_string_lossy()));
+
+    if let Some(parent) = lock_path.parent() {
+        if !parent.as_os_str().is_empty() && !parent.exists() {
+            fs::create_dir_all(parent)
+                .map_err(|e| anyhow!("Failed to create directory {}: {}", parent.display(), e))?;
+        }
+    }
+
+    let file = acquire(&lock_path, timeout)?;
+    Ok(StorageLock { lock_path, file: RefCell::new(Some(file)) })
+}
+
+/// Open and lock a lock file, waiting up to `timeout` for another process to release it
+fn acquire(lock_path: &Path, timeout: std::time::Duration) -> Result<File> {
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(lock_path)
+        .map_err(|e| anyhow!("Failed to open lock file {}: {}", lock_path.display(), e))?;
+
+    let started = Instant::now();
+    loop {
+        match file.try_lock_exclusive() {
+            Ok(()) => return Ok(file),
+            Err(_) if started.elapsed() < timeout => thread::sleep(LOCK_RETRY_INTERVAL),
+            Err(e) => {
+                return Err(anyhow!(
+                    "Timed out waiting for another rtodo process to release {}: {}",
+                    lock_path.display(),
+                    e
+                ))
+            }
+        }
+    }
+}
+
+/// State of a storage file, used to notice writes by other programs
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Fingerprint {
+    Missing,
+    Present {
+        len: u64,
+        modified: Option<SystemTime>,
+        hash: u64,
+    },
+}
+
+impl Fingerprint {
+    fn of(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Fingerprint::Missing);
+        }
+
+        let contents = fs::read(path)
+            .map_err(|e| anyhow!("Failed to read file {}: {}", path.display(), e))?;
+        let mut hasher = DefaultHasher::new();
+        contents.hash(&mut hasher);
+
+        Ok(Fingerprint::Present {
+            len: contents.len() as u64,
+            modified: fs::metadata(path).and_then(|meta| meta.modified()).ok(),
+            hash: hasher.finish(),
+        })
+    }
+}
+
+/// Fingerprint of a storage file as it was last loaded or saved
+#[derive(Default)]
+struct ChangeGuard {
+    seen: RefCell<Option<Fingerprint>>,
+}
+
+impl ChangeGuard {
+    /// Remember the current state of the file
+    fn record(&self, path: &Path) -> Result<()> {
+        *self.seen.borrow_mut() = Some(Fingerprint::of(path)?);
+        Ok(())
+    }
+
+    /// Fail with `ExternalChange` if the file changed since it was recorded
+    ///
+    /// Passes if nothing was recorded, e.g. when saving to a new destination.
+    fn check(&self, path: &Path) -> Result<()> {
+        if let Some(seen) = self.seen.borrow().as_ref() {
+            if *seen != Fingerprint::of(path)? {
+                return Err(ExternalChange { path: path.to_path_buf() }.into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Error for a save refused because the file changed after it was loaded
+#[derive(Debug)]
+pub struct ExternalChange {
+    pub path: PathBuf,
+}
+
+impl fmt::Display for ExternalChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} was modified by another program after it was loaded. Your changes were not saved; run the command again.",
+            self.path.display()
+        )
+    }
+}
+
+impl std::error::Error for ExternalChange {}
+
+/// Whole-file JSON storage
+pub struct JsonStorage {
+    path: PathBuf,
+    guard: ChangeGuard,
+}
+
+impl JsonStorage {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            guard: ChangeGuard::default(),
+        }
+    }
+}
+
+impl Storage for JsonStorage {
+    fn backend(&self) -> Backend {
+        Backend::Json
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn load(&self) -> Result<TodoList> {
+        let todo_list = TodoList::load_from_file(&self.path)?;
+        self.guard.record(&self.path)?;
+        Ok(todo_list)
+    }
+
+    fn save(&self, todo_list: &TodoList) -> Result<()> {
+        self.guard.check(&self.path)?;
+        todo_list.save_to_file(&self.path)?;
+        self.guard.record(&self.path)
+    }
+}
+
+/// Tables and indexes of the SQLite backend
+///
+/// Each task is stored as its JSON representation in `data`, with the fields
+/// used by list filters copied into indexed columns. Due dates are kept as
+/// Unix timestamps so they compare correctly across time zones.
+const SQLITE_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS meta (
+        key TEXT PRIMARY KEY,
+        value INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS tasks (
+        id INTEGER PRIMARY KEY,
+        completed INTEGER NOT NULL,
+        category TEXT,
+        priority TEXT NOT NULL,
+        due_ts INTEGER,
+        data TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS task_tags (
+        task_id INTEGER NOT NULL,
+        tag TEXT NOT NULL,
+        PRIMARY KEY (tag, task_id)
+    );
+    CREATE TABLE IF NOT EXISTS trash (
+        position INTEGER PRIMARY KEY,
+        data TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_tasks_completed ON tasks (completed, due_ts);
+    CREATE INDEX IF NOT EXISTS idx_tasks_category ON tasks (category);
+    CREATE INDEX IF NOT EXISTS idx_tasks_priority ON tasks (priority);
+    CREATE INDEX IF NOT EXISTS idx_task_tags_task ON task_tags (task_id);
+";
+
+/// SQLite database storage
+pub struct SqliteStorage {
+    path: PathBuf,
+    /// Serialized tasks as last loaded or saved, used to write only what changed
+    snapshot: RefCell<Option<HashMap<u32, String>>>,
+    guard: ChangeGuard,
+}
+
+impl SqliteStorage {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            snapshot: RefCell::new(None),
+            guard: ChangeGuard::default(),
+        }
+    }
+
+    fn connect(&self) -> Result<Connection> {
+        if let Some(parent) = self.path.parent() {
+            if !parent.as_os_str().is_empty() && !parent.exists() {
+                fs::create_dir_all(parent)
+                    .map_err(|e| anyhow!("Failed to create directory {}: {}", parent.display(), e))?;
+            }
+        }
+
+        let conn = Connection::open(&self.path)
+            .map_err(|e| anyhow!("Failed to open database {}: {}", self.path.display(), e))?;
+        conn.execute_batch(SQLITE_SCHEMA)
+            .map_err(|e| anyhow!("Failed to initialize database {}: {}", self.path.display(), e))?;
+        Ok(conn)
+    }
+
+    fn read_rows(conn: &Connection) -> Result<Vec<(u32, String)>> {
+        let mut stmt = conn.prepare("SELECT id, data FROM tasks ORDER BY id")?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<(u32, String)>>>()?;
+        Ok(rows)
+    }
+
+    fn read_trash(conn: &Connection) -> Result<Vec<String>> {
+        let mut stmt = conn.prepare("SELECT data FROM trash ORDER BY position")?;
+        let rows = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        Ok(rows)
+    }
+
+    fn read_meta(conn: &Connection, key: &str) -> Result<Option<u32>> {
+        Ok(conn
+            .query_row("SELECT value FROM meta WHERE key = ?1", [key], |row| row.get(0))
+            .optional()?)
+    }
+}
+
+fn priority_key(priority: &Priority) -> &'static str {
+    match priority {
+        Priority::Low => "Low",
+        Priority::Medium => "Medium",
+        Priority::High => "High",
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn backend(&self) -> Backend {
+        Backend::Sqlite
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn load(&self) -> Result<TodoList> {
+        if !self.path.exists() {
+            *self.snapshot.borrow_mut() = Some(HashMap::new());
+            self.guard.record(&self.path)?;
+            return Ok(TodoList::new());
+        }
+
+        let conn = self.connect()?;
+        let rows = Self::read_rows(&conn)?;
+        let version = Self::read_meta(&conn, "version")?.unwrap_or(schema::CURRENT_VERSION);
+        let next_id = Self::read_meta(&conn, "next_id")?;
+
+        let tasks = rows
+            .iter()
+            .map(|(id, data)| {
+                serde_json::from_str::<Value>(data)
+                    .map_err(|e| anyhow!("Task {} in {} is corrupted: {}", id, self.path.display(), e))
+            })
+            .collect::<Result<Vec<Value>>>()?;
+
+        let trash = Self::read_trash(&conn)?
+            .iter()
+            .map(|data| {
+                serde_json::from_str::<Value>(data)
+                    .map_err(|e| anyhow!("Trash in {} is corrupted: {}", self.path.display(), e))
+            })
+            .collect::<Result<Vec<Value>>>()?;
+
+        // Reuse the JSON migration pipeline so both backends share one schema history
+        let mut document = serde_json::json!({ "version": version, "tasks": tasks, "trash": trash });
+        if let Some(next_id) = next_id {
+            document["next_id"] = Value::from(next_id);
+        }
+        let (todo_list, _) = schema::from_value(document).map_err(|e| {
+            if e.is::<schema::UnsupportedVersion>() {
+                e
+            } else {
+                anyhow!("Failed to load {}: {}", self.path.display(), e)
+            }
+        })?;
+
+        drop(conn);
+        self.guard.record(&self.path)?;
+        *self.snapshot.borrow_mut() = Some(rows.into_iter().collect());
+        Ok(todo_list)
+    }
+
+    fn save(&self, todo_list: &TodoList) -> Result<()> {
+        self.guard.check(&self.path)?;
+        let mut conn = self.connect()?;
+
+        let mut snapshot = self.snapshot.borrow_mut();
+        if snapshot.is_none() {
+            *snapshot = Some(Self::read_rows(&conn)?.into_iter().collect());
+        }
+        let snapshot = snapshot.as_mut().expect("snapshot was just populated");
+
+        let tx = conn.transaction()?;
+        let mut current = HashMap::new();
+        for task in todo_list.get_all_tasks() {
+            let data = serde_json::to_string(task)
+                .map_err(|e| anyhow!("Failed to serialize task {}: {}", task.id, e))?;
+            if snapshot.get(&task.id) != Some(&data) {
+                tx.execute(
+                    "INSERT INTO tasks (id, completed, category, priority, due_ts, data)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+                     ON CONFLICT (id) DO UPDATE SET
+                         completed = excluded.completed,
+                         category = excluded.category,
+                         priority = excluded.priority,
+                         due_ts = excluded.due_ts,
+                         data = excluded.data",
+                    params![
+                        task.id,
+                        task.completed,
+                        task.category,
+                        priority_key(&task.priority),
+                        task.due_date.map(|due| due.timestamp()),
+                        data,
+                    ],
+                )?;
+                tx.execute("DELETE FROM task_tags WHERE task_id = ?1", [task.id])?;
+                for tag in &task.tags {
+                    tx.execute(
+                        "INSERT INTO task_tags (task_id, tag) VALUES (?1, ?2)",
+                        params![task.id, tag],
+                    )?;
+                }
+            }
+            current.insert(task.id, data);
+        }
+
+        for id in snapshot.keys().filter(|id| !current.contains_key(id)) {
+            tx.execute("DELETE FROM tasks WHERE id = ?1", [id])?;
+            tx.execute("DELETE FROM task_tags WHERE task_id = ?1", [id])?;
+        }
+
+        // The trash is small, so it is rewritten as a whole
+        tx.execute("DELETE FROM trash", [])?;
+        for (position, trashed) in todo_list.trash().iter().enumerate() {
+            let data = serde_json::to_string(trashed)
+                .map_err(|e| anyhow!("Failed to serialize trashed task {}: {}", trashed.task.id, e))?;
+            tx.execute("INSERT INTO trash (position, data) VALUES (?1, ?2)", params![position, data])?;
+        }
+
+        for (key, value) in [("version", schema::CURRENT_VERSION), ("next_id", todo_list.next_id())] {
+            tx.execute(
+                "INSERT INTO meta (key, value) VALUES (?1, ?2)
+                 ON CONFLICT (key) DO UPDATE SET value = excluded.value",
+                params![key, value],
+            )?;
+        }
+
+        tx.commit()
+            .map_err(|e| anyhow!("Failed to save to {}: {}", self.path.display(), e))?;
+        drop(conn);
+        *snapshot = current;
+        self.guard.record(&self.path)
+    }
+
+    fn query(&self, todo_list: &TodoList, filter: &TaskFilter) -> Result<Vec<u32>> {
+        if !self.path.exists() {
+            return Ok(todo_list.get_all_tasks().iter().filter(|task| filter.matches(task)).map(|task| task.id).collect());
+        }
+
+        let conn = self.connect()?;
+        let now = Local::now();
+
+        let mut clauses: Vec<&str> = Vec::new();
+        let mut values: Vec<rusqlite::types::Value> = Vec::new();
+
+        match filter.status {
+            StatusFilter::All => {}
+            StatusFilter::Completed => clauses.push("completed = 1"),
+            StatusFilter::Pending => clauses.push("completed = 0"),
+            StatusFilter::Overdue => {
+                clauses.push("completed = 0 AND due_ts < ?");
+                values.push(now.timestamp().into());
+            }
+            StatusFilter::DueSoon(window) => {
+                clauses.push("completed = 0 AND due_ts BETWEEN ? AND ?");
+                values.push(now.timestamp().into());
+                values.push((now + window).timestamp().into());
+            }
+        }
+        if let Some(category) = &filter.category {
+            clauses.push("category = ?");
+            values.push(category.clone().into());
+        }
+        if let Some(priority) = &filter.priority {
+            clauses.push("priority = ?");
+            values.push(priority_key(priority).to_string().into());
+        }
+        for tag in &filter.tags {
+            clauses.push("id IN (SELECT task_id FROM task_tags WHERE tag = ?)");
+            values.push(tag.clone().into());
+        }
+        for tag in &filter.exclude_tags {
+            clauses.push("id NOT IN (SELECT task_id FROM task_tags WHERE tag = ?)");
+            values.push(tag.clone().into());
+        }
+
+        let mut sql = "SELECT id FROM tasks".to_string();
+        if !clauses.is_empty() {
+            sql.push_str(" WHERE ");
+            sql.push_str(&clauses.join(" AND "));
+        }
+        sql.push_str(" ORDER BY id");
+
+        let mut stmt = conn.prepare(&sql)?;
+        let ids = stmt
+            .query_map(rusqlite::params_from_iter(values), |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<u32>>>()?;
+        Ok(ids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TaskUpdate;
+    use tempfile::TempDir;
+
+    fn sample_list() -> TodoList {
+        let mut todo_list = TodoList::new();
+        let report = todo_list.add_task_with_details(
+            "Write report".to_string(),
+            None,
+            Some(Local::now() - Duration::days(2)),
+            Some("work".to_string()),
+            Priority::High,
+        );
+        let groceries = todo_list.add_task_with_details(
+            "Buy groceries".to_string(),
+            None,
+            Some(Local::now() + Duration::days(3)),
+            Some("home".to_string()),
+            Priority::Low,
+        );
+        let call = todo_list.add_task("Call plumber".to_string());
+        todo_list
+            .update_task(report, TaskUpdate::new().tags(["urgent", "q3"]))
+            .unwrap();
+        todo_list
+            .update_task(groceries, TaskUpdate::new().tags(["errand"]))
+            .unwrap();
+        todo_list.complete_task(call);
+        todo_list
+    }
+
+    #[test]
+    fn test_backend_from_path() {
+        assert_eq!(Backend::from_path(Path::new("tasks.json")), Backend::Json);
+        assert_eq!(Backend::from_path(Path::new("tasks.db")), Backend::Sqlite);
+        assert_eq!(Backend::from_path(Path::new("tasks.sqlite3")), Backend::Sqlite);
+        assert_eq!(Backend::from_path(Path::new("tasks")), Backend::Json);
+    }
+
+    #[test]
+    fn test_sqlite_round_trip() {
+        let dir = TempDir::new().unwrap();
+        let storage = SqliteStorage::new(dir.path().join("tasks.db"));
+
+        let empty = storage.load().unwrap();
+        assert!(empty.is_empty());
+
+        let mut todo_list = sample_list();
+        todo_list.add_task("Old idea".to_string());
+        todo_list.trash_task(4);
+        storage.save(&todo_list).unwrap();
+
+        let loaded = SqliteStorage::new(dir.path().join("tasks.db")).load().unwrap();
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded.next_id(), todo_list.next_id());
+        assert_eq!(loaded.trash(), todo_list.trash());
+        let report = loaded.get_task(1).unwrap();
+        assert_eq!(report.category, Some("work".to_string()));
+        assert!(report.has_tag("urgent"));
+        assert!(loaded.get_task(3).unwrap().completed);
+    }
+
+    #[test]
+    fn test_sqlite_save_writes_only_changes() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tasks.db");
+        let storage = SqliteStorage::new(path.clone());
+        storage.save(&sample_list()).unwrap();
+
+        // Record every row written to the tasks table from here on
+        let conn = Connection::open(&path).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE writes (id INTEGER);
+             CREATE TRIGGER count_updates AFTER UPDATE ON tasks BEGIN INSERT INTO writes VALUES (new.id); END;
+             CREATE TRIGGER count_inserts AFTER INSERT ON tasks BEGIN INSERT INTO writes VALUES (new.id); END;",
+        )
+        .unwrap();
+
+        let mut todo_list = storage.load().unwrap();
+        todo_list.update_task(2, TaskUpdate::new().title("Buy milk")).unwrap();
+        todo_list.remove_task(3);
+        storage.save(&todo_list).unwrap();
+
+        let written: Vec<u32> = conn
+            .prepare("SELECT id FROM writes")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        assert_eq!(written, vec![2]);
+        assert_eq!(SqliteStorage::read_rows(&conn).unwrap().len(), 2);
+
+        let loaded = SqliteStorage::new(path).load().unwrap();
+        assert_eq!(loaded.get_task(2).unwrap().title, "Buy milk");
+        assert!(loaded.get_task(3).is_none());
+    }
+
+    #[test]
+    fn test_sqlite_query_matches_in_memory_filter() {
+        let dir = TempDir::new().unwrap();
+        let sqlite = SqliteStorage::new(dir.path().join("tasks.db"));
+        let json = JsonStorage::new(dir.path().join("tasks.json"));
+        let todo_list = sample_list();
+        sqlite.save(&todo_list).unwrap();
+
+        let filters = [
+            TaskFilter::default(),
+            TaskFilter { status: StatusFilter::Completed, ..Default::default() },
+            TaskFilter { status: StatusFilter::Pending, ..Default::default() },
+            TaskFilter { status: StatusFilter::Overdue, ..Default::default() },
+            TaskFilter { status: StatusFilter::DueSoon(Duration::days(7)), ..Default::default() },
+            TaskFilter { category: Some("work".to_string()), ..Default::default() },
+            TaskFilter { priority: Some(Priority::Low), ..Default::default() },
+            TaskFilter { tags: vec!["urgent".to_string()], ..Default::default() },
+            TaskFilter { exclude_tags: vec!["urgent".to_string()], ..Default::default() },
+        ];
+        for filter in &filters {
+            assert_eq!(
+                sqlite.query(&todo_list, filter).unwrap(),
+                json.query(&todo_list, filter).unwrap(),
+                "filter {:?}",
+                filter
+            );
+        }
+        assert_eq!(sqlite.query(&todo_list, &filters[3]).unwrap(), vec![1]);
+        assert_eq!(sqlite.query(&todo_list, &filters[4]).unwrap(), vec![2]);
+    }
+
+    #[test]
+    fn test_sqlite_rejects_newer_version() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tasks.db");
+        SqliteStorage::new(path.clone()).save(&sample_list()).unwrap();
+
+        let conn = Connection::open(&path).unwrap();
+        conn.execute("UPDATE meta SET value = 99 WHERE key = 'version'", [])
+            .unwrap();
+
+        let error = SqliteStorage::new(path).load().unwrap_err();
+        assert!(error.is::<schema::UnsupportedVersion>());
+    }
+
+    #[test]
+    fn test_lock_is_exclusive() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("tasks.json");
+
+        let held = lock(&path).unwrap();
+        let error = lock_with_timeout(&path, std::time::Duration::from_millis(100)).unwrap_err();
+        assert!(error.to_string().contains("Timed out"));
+
+        // Released while waiting for the user, and taken back to save
+        held.release();
+        let other = lock_with_timeout(&path, std::time::Duration::from_millis(100)).unwrap();
+        drop(other);
+        held.reacquire().unwrap();
+        held.reacquire().unwrap();
+        assert!(lock_with_timeout(&path, std::time::Duration::from_millis(100)).is_err());
+
+        drop(held);
+        assert!(lock_with_timeout(&path, std::time::Duration::from_millis(100)).is_ok());
+    }
+
+    #[test]
+    fn test_save_refuses_external_modification() {
+        let dir = TempDir::new().unwrap();
+        for name in ["tasks.json", "tasks.db"] {
+            let path = dir.path().join(name);
+            open(Backend::from_path(&path), path.clone()).save(&sample_list()).unwrap();
+
+            let storage = open(Backend::from_path(&path), path.clone());
+            let mut todo_list = storage.load().unwrap();
+
+            // Another program rewrites the file between load and save
+            let other = open(Backend::from_path(&path), path.clone());
+            let mut external = other.load().unwrap();
+            external.add_task("Added elsewhere".to_string());
+            other.save(&external).unwrap();
+
+            todo_list.add_task("Added here".to_string());
+            let error = storage.save(&todo_list).unwrap_err();
+            assert!(error.is::<ExternalChange>(), "{}", name);
+            assert!(error.to_string().contains("modified by another program"), "{}", name);
+
+            let reloaded = open(Backend::from_path(&path), path).load().unwrap();
+            assert_eq!(reloaded.len(), 4, "{}", name);
+            assert_eq!(reloaded.get_task(4).unwrap().title, "Added elsewhere");
+        }
+    }
+
+    #[test]
+    fn test_save_after_own_save_is_allowed() {
+        let dir = TempDir::new().unwrap();
+        let storage = JsonStorage::new(dir.path().join("tasks.json"));
+        let mut todo_list = storage.load().unwrap();
+
+        todo_list.add_task("First".to_string());
+        storage.save(&todo_list).unwrap();
+        todo_list.add_task("Second".to_string());
+        storage.save(&todo_list).unwrap();
+
+        assert_eq!(JsonStorage::new(dir.path().join("tasks.json")).load().unwrap().len(), 2);
+    }
+}
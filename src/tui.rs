@@ -0,0 +1,622 @@
+//! Interactive full-screen task list (`rtodo tui`)
+//!
+//! The screen has a category sidebar, a filter box and the task list, with a
+//! line of key hints at the bottom. Tasks are shown with the same colours as
+//! `list`. Every change is saved right away through the callback given to
+//! [`run`], which `main` points at the storage backend and the undo journal,
+//! so each change can be undone with `rtodo undo` afterwards. If another
+//! process changed the file since it was loaded, the change is dropped and the
+//! list is replaced by the file's current contents.
+//!
+//! Keys:
+//!
+//! - `↑`/`k`, `↓`/`j`, `PgUp`, `PgDn`, `g`, `G`: move the selection
+//! - `Tab`: switch between the category sidebar and the task list
+//! - `/`: type a filter (`Enter` keeps it, `Esc` clears it)
+//! - `Space`/`x`: complete or uncomplete the selected task
+//! - `e`: edit the title, `d`: delete, `+`/`-`: raise or lower the priority
+//! - `c`: show or hide completed tasks
+//! - `q`/`Esc`: quit
+
+use anyhow::{anyhow, Result};
+use chrono::{Duration, Local};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Position, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph};
+use ratatui::Frame;
+
+use crate::config::{Config, Theme};
+use crate::models::{Priority, Task, TaskUpdate, TodoList};
+
+/// What became of a change handed to the `save` callback of [`run`]
+pub enum Saved {
+    /// The change was saved
+    Done,
+    /// The change was saved, but something went wrong afterwards (such as
+    /// recording it for undo); the message is shown as a warning
+    DoneWithWarning(String),
+    /// The file had been changed by another process, so the change was not
+    /// saved; this is the todo list now in the file
+    Reloaded(TodoList),
+}
+
+/// Rows moved by `PgUp` and `PgDn`
+const PAGE: usize = 10;
+
+/// What keys currently do
+#[derive(Debug, Clone, PartialEq)]
+enum Mode {
+    Normal,
+    /// Typing in the filter box
+    Filter,
+    /// Editing the title of a task
+    Edit { id: u32, title: String },
+    /// Waiting for `y` to delete a task
+    ConfirmDelete { id: u32 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Categories,
+    Tasks,
+}
+
+/// State of the interactive task list
+pub struct App {
+    todo_list: TodoList,
+    /// The todo list as last saved, to describe the next change
+    saved: TodoList,
+    filter: String,
+    /// Index into `categories()`; 0 is "All"
+    category: usize,
+    show_completed: bool,
+    focus: Focus,
+    mode: Mode,
+    tasks: ListState,
+    status: Option<String>,
+    /// How far ahead due dates are highlighted as due soon
+    due_soon_window: Duration,
+    theme: Theme,
+    quit: bool,
+}
+
+impl App {
+    pub fn new(todo_list: TodoList, config: &Config) -> Self {
+        Self {
+            saved: todo_list.clone(),
+            todo_list,
+            filter: String::new(),
+            category: 0,
+            show_completed: false,
+            focus: Focus::Tasks,
+            mode: Mode::Normal,
+            tasks: ListState::default().with_selected(Some(0)),
+            status: None,
+            due_soon_window: config.due_soon_window(),
+            theme: config.theme(),
+            quit: false,
+        }
+    }
+
+    /// Sidebar entries: `None` for all tasks, then each category with its task count
+    fn categories(&self) -> Vec<(Option<String>, usize)> {
+        let mut categories: Vec<(String, usize)> = self.todo_list.get_all_categories().into_iter().collect();
+        categories.sort();
+        std::iter::once((None, self.todo_list.len()))
+            .chain(categories.into_iter().map(|(name, count)| (Some(name), count)))
+            .collect()
+    }
+
+    /// Tasks passing the filter box, the selected category and the completed toggle
+    fn visible_tasks(&self) -> Vec<&Task> {
+        let matching = if self.filter.is_empty() {
+            self.todo_list.get_all_tasks().iter().collect()
+        } else {
+            self.todo_list.search_tasks(&self.filter, true, false).unwrap_or_default()
+        };
+        let category = self.categories().into_iter().nth(self.category).and_then(|(name, _)| name);
+        matching
+            .into_iter()
+            .filter(|task| self.show_completed || !task.completed)
+            .filter(|task| category.is_none() || task.category == category)
+            .collect()
+    }
+
+    fn selected_task(&self) -> Option<&Task> {
+        self.tasks.selected().and_then(|index| self.visible_tasks().get(index).copied())
+    }
+
+    /// Replace the todo list with the one in the file, dropping unsaved changes
+    fn reload(&mut self, todo_list: TodoList) {
+        self.saved = todo_list.clone();
+        self.todo_list = todo_list;
+        self.clamp_selection();
+        self.status = Some("The task file was changed elsewhere; reloaded it and dropped the last change".to_string());
+    }
+
+    /// Keep the selections inside the lists after they changed
+    fn clamp_selection(&mut self) {
+        self.category = self.category.min(self.categories().len() - 1);
+        let count = self.visible_tasks().len();
+        let index = self.tasks.selected().unwrap_or(0).min(count.saturating_sub(1));
+        self.tasks.select(Some(index));
+    }
+
+    fn move_selection(&mut self, offset: isize) {
+        match self.focus {
+            Focus::Categories => {
+                let last = self.categories().len() - 1;
+                self.category = self.category.saturating_add_signed(offset).min(last);
+                self.tasks.select(Some(0));
+            }
+            Focus::Tasks => {
+                let last = self.visible_tasks().len().saturating_sub(1);
+                let index = self.tasks.selected().unwrap_or(0).saturating_add_signed(offset).min(last);
+                self.tasks.select(Some(index));
+            }
+        }
+    }
+
+    /// Handle a key press, returning a description of the change if the todo list changed
+    fn handle_key(&mut self, key: KeyEvent) -> Option<String> {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit = true;
+            return None;
+        }
+
+        let change = match self.mode.clone() {
+            Mode::Normal => self.handle_normal_key(key),
+            Mode::Filter => {
+                match key.code {
+                    KeyCode::Enter => self.mode = Mode::Normal,
+                    KeyCode::Esc => {
+                        self.filter.clear();
+                        self.mode = Mode::Normal;
+                    }
+                    KeyCode::Backspace => {
+                        self.filter.pop();
+                    }
+                    KeyCode::Char(ch) => self.filter.push(ch),
+                    _ => {}
+                }
+                None
+            }
+            Mode::Edit { id, mut title } => match key.code {
+                KeyCode::Enter => {
+                    self.mode = Mode::Normal;
+                    let title = title.trim().to_string();
+                    if title.is_empty() {
+                        self.status = Some("Title cannot be empty".to_string());
+                        return None;
+                    }
+                    self.apply(id, format!("edit {}", id), |todo_list| {
+                        todo_list.update_task(id, TaskUpdate::new().title(title))
+                    })
+                }
+                KeyCode::Esc => {
+                    self.mode = Mode::Normal;
+                    None
+                }
+                KeyCode::Backspace => {
+                    title.pop();
+                    self.mode = Mode::Edit { id, title };
+                    None
+                }
+                KeyCode::Char(ch) => {
+                    title.push(ch);
+                    self.mode = Mode::Edit { id, title };
+                    None
+                }
+                _ => None,
+            },
+            Mode::ConfirmDelete { id } => {
+                self.mode = Mode::Normal;
+                if key.code == KeyCode::Char('y') {
+                    self.apply(id, format!("remove {}", id), |todo_list| {
+                        todo_list.trash_task(id).map(|_| ()).ok_or_else(|| anyhow!("Task with ID {} not found", id))
+                    })
+                } else {
+                    self.status = Some("Delete cancelled".to_string());
+                    None
+                }
+            }
+        };
+        self.clamp_selection();
+        change
+    }
+
+    fn handle_normal_key(&mut self, key: KeyEvent) -> Option<String> {
+        self.status = None;
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::PageUp => self.move_selection(-(PAGE as isize)),
+            KeyCode::PageDown => self.move_selection(PAGE as isize),
+            KeyCode::Home | KeyCode::Char('g') => self.move_selection(isize::MIN),
+            KeyCode::End | KeyCode::Char('G') => self.move_selection(isize::MAX),
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.focus = match self.focus {
+                    Focus::Categories => Focus::Tasks,
+                    Focus::Tasks => Focus::Categories,
+                };
+            }
+            KeyCode::Char('/') => self.mode = Mode::Filter,
+            KeyCode::Char('c') => self.show_completed = !self.show_completed,
+            KeyCode::Char(' ') | KeyCode::Char('x') => {
+                let task = self.selected_task()?;
+                let id = task.id;
+                return if task.completed {
+                    self.apply(id, format!("incomplete {}", id), |todo_list| todo_list.mark_incomplete(id))
+                } else {
+                    self.apply(id, format!("complete {}", id), |todo_list| todo_list.mark_complete(id).map(|_| ()))
+                };
+            }
+            KeyCode::Char('e') => {
+                let task = self.selected_task()?;
+                self.mode = Mode::Edit { id: task.id, title: task.title.clone() };
+            }
+            KeyCode::Char('d') => {
+                let task = self.selected_task()?;
+                self.mode = Mode::ConfirmDelete { id: task.id };
+            }
+            KeyCode::Char('+') | KeyCode::Char('-') => {
+                let task = self.selected_task()?;
+                let id = task.id;
+                let priority = match (&task.priority, key.code == KeyCode::Char('+')) {
+                    (Priority::Low, true) | (Priority::High, false) => Priority::Medium,
+                    (Priority::Medium, true) | (Priority::High, true) => Priority::High,
+                    (Priority::Medium, false) | (Priority::Low, false) => Priority::Low,
+                };
+                if priority == task.priority {
+                    return None;
+                }
+                let label = format!("priority {} {:?}", id, priority).to_lowercase();
+                return self.apply(id, label, |todo_list| todo_list.update_task(id, TaskUpdate::new().priority(priority)));
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// Change the todo list, reporting failures in the status line
+    fn apply<F>(&mut self, id: u32, label: String, change: F) -> Option<String>
+    where
+        F: FnOnce(&mut TodoList) -> Result<()>,
+    {
+        let title_before = self.todo_list.get_task(id).map(|task| task.title.clone()).unwrap_or_default();
+        match change(&mut self.todo_list) {
+            Ok(()) => {
+                let title = self.todo_list.get_task(id).map_or(title_before, |task| task.title.clone());
+                self.status = Some(format!("{} [{}] {}", capitalize(label.split(' ').next().unwrap_or_default()), id, title));
+                Some(format!("tui {}", label))
+            }
+            Err(e) => {
+                self.status = Some(e.to_string());
+                None
+            }
+        }
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let [main, footer] = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [sidebar, content] = Layout::horizontal([Constraint::Length(24), Constraint::Min(0)]).areas(main);
+        let [filter_box, task_area] = Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(content);
+
+        let focused = |focus: Focus| {
+            if self.focus == focus {
+                Style::new().fg(Color::Cyan)
+            } else {
+                Style::new()
+            }
+        };
+
+        let categories: Vec<ListItem> = self
+            .categories()
+            .into_iter()
+            .map(|(name, count)| {
+                let name = match name {
+                    Some(name) => Span::styled(format!("#{}", name), Style::new().fg(tui_color(self.theme.category))),
+                    None => Span::raw("All"),
+                };
+                ListItem::new(Line::from(vec![name, Span::styled(format!(" ({})", count), Style::new().fg(Color::Cyan))]))
+            })
+            .collect();
+        let mut category_state = ListState::default().with_selected(Some(self.category));
+        frame.render_stateful_widget(
+            List::new(categories)
+                .block(Block::bordered().title("Categories").border_style(focused(Focus::Categories)))
+                .highlight_style(Style::new().add_modifier(Modifier::REVERSED)),
+            sidebar,
+            &mut category_state,
+        );
+
+        let filter_style = if self.mode == Mode::Filter { Style::new().fg(Color::Yellow) } else { Style::new() };
+        frame.render_widget(
+            Paragraph::new(self.filter.as_str()).block(Block::bordered().title("Filter (/)").border_style(filter_style)),
+            filter_box,
+        );
+        if self.mode == Mode::Filter {
+            frame.set_cursor_position(cursor_after(filter_box, &self.filter));
+        }
+
+        let visible = self.visible_tasks();
+        let title = format!("Tasks ({}){}", visible.len(), if self.show_completed { "" } else { " - pending" });
+        let items: Vec<ListItem> = visible.iter().map(|task| ListItem::new(task_line(task, &self.todo_list, self.due_soon_window, &self.theme))).collect();
+        let list = List::new(items)
+            .block(Block::bordered().title(title).border_style(focused(Focus::Tasks)))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+        // The list state also keeps the scroll offset between frames
+        let mut state = self.tasks.clone();
+        frame.render_stateful_widget(list, task_area, &mut state);
+        self.tasks = state;
+
+        let footer_text = match &self.mode {
+            Mode::Edit { title, .. } => {
+                let prompt = format!("Title: {}", title);
+                frame.set_cursor_position(Position::new(footer.x + prompt.chars().count() as u16, footer.y));
+                Line::from(prompt)
+            }
+            Mode::ConfirmDelete { id } => {
+                let title = self.todo_list.get_task(*id).map(|task| task.title.as_str()).unwrap_or_default();
+                Line::styled(format!("Delete [{}] {}? (y/n)", id, title), Style::new().fg(Color::Red))
+            }
+            _ => match &self.status {
+                Some(status) => Line::from(status.as_str()),
+                None => Line::styled(
+                    "q quit  ↑↓ move  Tab categories  / filter  space complete  e edit  d delete  +/- priority  c completed",
+                    Style::new().add_modifier(Modifier::DIM),
+                ),
+            },
+        };
+        frame.render_widget(Paragraph::new(footer_text), footer);
+    }
+}
+
+/// A task as one line, coloured like `list` prints it
+fn task_line<'a>(task: &'a Task, todo_list: &TodoList, due_soon_window: Duration, theme: &Theme) -> Line<'a> {
+    let mut spans = vec![
+        if task.completed {
+            Span::styled("✓", Style::new().fg(Color::Green))
+        } else {
+            Span::styled("○", Style::new().fg(Color::Yellow))
+        },
+        Span::raw(" ["),
+        Span::styled(task.id.to_string(), Style::new().fg(Color::Cyan)),
+        Span::raw("] "),
+        Span::styled(task.title.as_str(), Style::new().add_modifier(Modifier::BOLD)),
+    ];
+
+    if let Some(category) = &task.category {
+        spans.push(Span::styled(format!(" #{}", category), Style::new().fg(tui_color(theme.category))));
+    }
+    for tag in &task.tags {
+        spans.push(Span::styled(format!(" +{}", tag), Style::new().fg(Color::LightBlue)));
+    }
+    if task.is_recurring() {
+        spans.push(Span::styled(" ↻", Style::new().fg(Color::Magenta)));
+    }
+    if let Some((done, total)) = todo_list.subtask_progress(task.id) {
+        spans.push(Span::styled(format!(" ({}/{} subtasks done)", done, total), Style::new().fg(Color::Cyan)));
+    }
+    if task.is_blocked(todo_list) {
+        let open_ids: Vec<String> = todo_list.get_open_dependencies(task.id).iter().map(|dep| dep.id.to_string()).collect();
+        spans.push(Span::styled(format!(" (blocked by {})", open_ids.join(", ")), Style::new().fg(Color::Red)));
+    }
+    if let Some(due_date) = task.due_date {
+        let style = if task.is_overdue() {
+            Style::new().fg(tui_color(theme.overdue))
+        } else if task.is_due_today() {
+            Style::new().fg(tui_color(theme.due_soon)).add_modifier(Modifier::BOLD)
+        } else if task.is_due_soon(due_soon_window) {
+            Style::new().fg(tui_color(theme.due_soon))
+        } else {
+            Style::new().fg(tui_color(theme.due))
+        };
+        spans.push(Span::raw(" ("));
+        spans.push(Span::styled(crate::format_time_until_due(due_date, task.due_has_time), style));
+        spans.push(Span::raw(")"));
+    }
+
+    let priority = format!("{:?}", task.priority).to_lowercase();
+    spans.push(Span::styled(format!(" [{}]", priority), Style::new().fg(tui_color(theme.priority(&task.priority)))));
+    Line::from(spans)
+}
+
+/// The terminal colour of a theme colour
+fn tui_color(color: colored::Color) -> Color {
+    match color {
+        colored::Color::Black => Color::Black,
+        colored::Color::Red => Color::Red,
+        colored::Color::Green => Color::Green,
+        colored::Color::Yellow => Color::Yellow,
+        colored::Color::Blue => Color::Blue,
+        colored::Color::Magenta => Color::Magenta,
+        colored::Color::Cyan => Color::Cyan,
+        colored::Color::White => Color::Gray,
+        colored::Color::BrightBlack => Color::DarkGray,
+        colored::Color::BrightRed => Color::LightRed,
+        colored::Color::BrightGreen => Color::LightGreen,
+        colored::Color::BrightYellow => Color::LightYellow,
+        colored::Color::BrightBlue => Color::LightBlue,
+        colored::Color::BrightMagenta => Color::LightMagenta,
+        colored::Color::BrightCyan => Color::LightCyan,
+        colored::Color::BrightWhite => Color::White,
+        colored::Color::TrueColor { r, g, b } => Color::Rgb(r, g, b),
+    }
+}
+
+/// Cursor position after `text` inside a bordered box
+fn cursor_after(area: Rect, text: &str) -> Position {
+    Position::new(area.x + 1 + text.chars().count() as u16, area.y + 1)
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars.next().map(|first| first.to_uppercase().chain(chars).collect()).unwrap_or_default()
+}
+
+/// Run the task list until the user quits, coloured by the `config` theme
+///
+/// `save` is called after every change with a description of the change and
+/// the todo list before and after it. A failed save is shown in the status
+/// line and the change is retried with the next one.
+pub fn run<F>(todo_list: TodoList, config: &Config, mut save: F) -> Result<()>
+where
+    F: FnMut(&str, &TodoList, &TodoList) -> Result<Saved>,
+{
+    let mut terminal = ratatui::try_init()?;
+    let mut app = App::new(todo_list, config);
+
+    let result = (|| -> Result<()> {
+        while !app.quit {
+            terminal.draw(|frame| app.render(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            if let Some(label) = app.handle_key(key) {
+                app.todo_list.stamp_changes(&app.saved, Local::now());
+                match save(&label, &app.saved, &app.todo_list) {
+                    Ok(Saved::Done) => app.saved = app.todo_list.clone(),
+                    Ok(Saved::DoneWithWarning(warning)) => {
+                        app.saved = app.todo_list.clone();
+                        app.status = Some(format!("Warning: {}", warning));
+                    }
+                    Ok(Saved::Reloaded(todo_list)) => app.reload(todo_list),
+                    Err(e) => app.status = Some(format!("Failed to save: {}", e)),
+                }
+            }
+        }
+        Ok(())
+    })();
+
+    ratatui::restore();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+
+    fn sample_app() -> App {
+        let mut todo_list = TodoList::new();
+        todo_list.add_task_with_details("Write report".to_string(), None, None, Some("work".to_string()), Priority::High);
+        todo_list.add_task_with_details("Buy milk".to_string(), None, None, Some("home".to_string()), Priority::Low);
+        todo_list.add_task_with_details("Review budget".to_string(), Some("Q3 report".to_string()), None, Some("work".to_string()), Priority::Medium);
+        App::new(todo_list, &Config::default())
+    }
+
+    fn press(app: &mut App, keys: &str) -> Vec<String> {
+        keys.chars()
+            .filter_map(|ch| app.handle_key(KeyEvent::from(KeyCode::Char(ch))))
+            .collect()
+    }
+
+    fn key(app: &mut App, code: KeyCode) -> Option<String> {
+        app.handle_key(KeyEvent::from(code))
+    }
+
+    fn visible_ids(app: &App) -> Vec<u32> {
+        app.visible_tasks().iter().map(|task| task.id).collect()
+    }
+
+    #[test]
+    fn test_filter_box_and_category_sidebar() {
+        let mut app = sample_app();
+        assert_eq!(visible_ids(&app), vec![1, 2, 3]);
+
+        press(&mut app, "/REPORT");
+        assert_eq!(visible_ids(&app), vec![1, 3]);
+        key(&mut app, KeyCode::Enter);
+        assert_eq!(app.mode, Mode::Normal);
+
+        // Categories are sorted after "All": home, work
+        key(&mut app, KeyCode::Tab);
+        press(&mut app, "j");
+        assert_eq!(visible_ids(&app), Vec::<u32>::new());
+        press(&mut app, "j");
+        assert_eq!(visible_ids(&app), vec![1, 3]);
+
+        press(&mut app, "/");
+        key(&mut app, KeyCode::Esc);
+        assert_eq!(visible_ids(&app), vec![1, 3]);
+        press(&mut app, "gj");
+        assert_eq!(visible_ids(&app), vec![2]);
+    }
+
+    #[test]
+    fn test_actions_change_the_todo_list() {
+        let mut app = sample_app();
+
+        assert_eq!(press(&mut app, "j "), vec!["tui complete 2"]);
+        assert!(app.todo_list.get_task(2).unwrap().completed);
+        assert_eq!(visible_ids(&app), vec![1, 3]);
+        assert_eq!(app.selected_task().unwrap().id, 3);
+
+        press(&mut app, "c");
+        assert_eq!(app.selected_task().unwrap().id, 2);
+        assert_eq!(press(&mut app, " "), vec!["tui incomplete 2"]);
+        assert!(!app.todo_list.get_task(2).unwrap().completed);
+
+        assert_eq!(press(&mut app, "++-"), vec!["tui priority 2 medium", "tui priority 2 high", "tui priority 2 medium"]);
+        assert_eq!(press(&mut app, "g-"), vec!["tui priority 1 medium"]);
+
+        press(&mut app, "e");
+        for _ in 0.."report".len() {
+            key(&mut app, KeyCode::Backspace);
+        }
+        press(&mut app, "summary");
+        assert_eq!(key(&mut app, KeyCode::Enter).as_deref(), Some("tui edit 1"));
+        assert_eq!(app.todo_list.get_task(1).unwrap().title, "Write summary");
+
+        assert!(press(&mut app, "dn").is_empty());
+        assert_eq!(app.todo_list.len(), 3);
+        assert_eq!(press(&mut app, "dy"), vec!["tui remove 1"]);
+        assert!(app.todo_list.get_task(1).is_none());
+        assert_eq!(app.saved.len(), 3);
+    }
+
+    #[test]
+    fn test_reload_drops_unsaved_changes() {
+        let mut app = sample_app();
+        press(&mut app, "G ");
+        assert!(app.todo_list.get_task(3).unwrap().completed);
+
+        let mut external = app.saved.clone();
+        external.remove_task(3).unwrap();
+        app.reload(external);
+        assert_eq!(visible_ids(&app), vec![1, 2]);
+        assert_eq!(app.selected_task().unwrap().id, 2);
+        assert_eq!(app.saved.len(), 2);
+        assert!(app.status.as_deref().unwrap().contains("changed elsewhere"));
+    }
+
+    #[test]
+    fn test_render() {
+        let mut app = sample_app();
+        app.todo_list.mark_complete(2).unwrap();
+        let mut terminal = Terminal::new(TestBackend::new(100, 12)).unwrap();
+        terminal.draw(|frame| app.render(frame)).unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let screen: Vec<String> = (0..buffer.area.height)
+            .map(|y| (0..buffer.area.width).map(|x| buffer[(x, y)].symbol()).collect())
+            .collect();
+        let screen = screen.join("\n");
+        assert!(screen.contains("All (3)"));
+        assert!(screen.contains("#home (1)"));
+        assert!(screen.contains("Tasks (2) - pending"));
+        assert!(screen.contains("> ○ [1] Write report #work [high]"));
+        assert!(screen.contains("○ [3] Review budget #work [medium]"));
+        assert!(!screen.contains("Buy milk"));
+        assert!(screen.contains("q quit"));
+    }
+}
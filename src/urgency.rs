@@ -0,0 +1,183 @@
+//! Urgency: one score answering "what should I work on now?"
+//!
+//! The score of a pending task is the sum of these components:
+//!
+//! | Component | Contribution                                                       |
+//! |-----------|--------------------------------------------------------------------|
+//! | priority  | 6.0 for high, 3.9 for medium, 1.8 for low                          |
+//! | due       | 2.4 when due 14 or more days out, rising to 12.0 when due now       |
+//! | overdue   | 5.0 once the due date has passed                                   |
+//! | age       | up to 2.0, growing over the first year since the task was created  |
+//! | category  | the weight set for the task's category with `--category-weight`    |
+//!
+//! Completed tasks have no urgency. Category weights may be negative, to push
+//! a category down instead of up.
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+use std::collections::BTreeMap;
+
+use crate::models::{Priority, Task};
+
+const DUE_WEIGHT: f64 = 12.0;
+const OVERDUE_WEIGHT: f64 = 5.0;
+const AGE_WEIGHT: f64 = 2.0;
+/// Due dates this many days out or further count the least
+const DUE_HORIZON_DAYS: f64 = 14.0;
+/// Tasks reach the full age weight after this many days
+const MAX_AGE_DAYS: f64 = 365.0;
+
+/// Extra urgency for tasks in particular categories
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Weights {
+    categories: BTreeMap<String, f64>,
+}
+
+impl Weights {
+    /// Parse `CATEGORY=WEIGHT` pairs, e.g. `work=2` or `someday=-3`
+    pub fn parse<S: AsRef<str>>(specs: &[S]) -> Result<Self> {
+        let mut categories = BTreeMap::new();
+        for spec in specs {
+            let spec = spec.as_ref();
+            let (category, weight) = spec
+                .split_once('=')
+                .ok_or_else(|| anyhow!("Invalid category weight '{}' (expected CATEGORY=WEIGHT)", spec))?;
+            let weight: f64 = weight
+                .trim()
+                .parse()
+                .map_err(|_| anyhow!("Invalid weight '{}' for category '{}'", weight.trim(), category.trim()))?;
+            categories.insert(category.trim().to_lowercase(), weight);
+        }
+        Ok(Self { categories })
+    }
+
+    /// Weights from a table of categories, such as the config file's `category_weights`
+    pub fn from_table(table: &BTreeMap<String, f64>) -> Self {
+        let categories = table.iter().map(|(category, weight)| (category.trim().to_lowercase(), *weight)).collect();
+        Self { categories }
+    }
+
+    /// These weights, with those in `overrides` replacing any for the same category
+    pub fn overridden_by(mut self, overrides: Weights) -> Self {
+        self.categories.extend(overrides.categories);
+        self
+    }
+
+    /// The weights by lowercase category name
+    pub fn table(&self) -> &BTreeMap<String, f64> {
+        &self.categories
+    }
+
+    /// The weight of a category, ignoring case; categories without one weigh nothing
+    pub fn category(&self, category: &str) -> f64 {
+        self.categories.get(&category.to_lowercase()).copied().unwrap_or(0.0)
+    }
+}
+
+/// The components of a task's urgency
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Urgency {
+    pub priority: f64,
+    pub due: f64,
+    pub overdue: f64,
+    pub age: f64,
+    pub category: f64,
+}
+
+impl Urgency {
+    /// Compute the urgency of a task at `now`
+    pub fn of(task: &Task, weights: &Weights, now: DateTime<Local>) -> Self {
+        if task.completed {
+            return Self::default();
+        }
+
+        let priority = match task.priority {
+            Priority::High => 6.0,
+            Priority::Medium => 3.9,
+            Priority::Low => 1.8,
+        };
+        let (due, overdue) = match task.due_date {
+            Some(due_date) => {
+                let days_left = (due_date - now).num_seconds() as f64 / 86_400.0;
+                let closeness = 1.0 - 0.8 * days_left.clamp(0.0, DUE_HORIZON_DAYS) / DUE_HORIZON_DAYS;
+                (DUE_WEIGHT * closeness, if days_left < 0.0 { OVERDUE_WEIGHT } else { 0.0 })
+            }
+            None => (0.0, 0.0),
+        };
+        let age_days = (now - task.created_at).num_seconds().max(0) as f64 / 86_400.0;
+        let age = AGE_WEIGHT * (age_days / MAX_AGE_DAYS).min(1.0);
+        let category = task.category.as_deref().map_or(0.0, |category| weights.category(category));
+
+        Self { priority, due, overdue, age, category }
+    }
+
+    /// The urgency score
+    pub fn total(&self) -> f64 {
+        self.priority + self.due + self.overdue + self.age + self.category
+    }
+
+    /// The components by name, in the order they are shown
+    pub fn components(&self) -> [(&'static str, f64); 5] {
+        [
+            ("priority", self.priority),
+            ("due", self.due),
+            ("overdue", self.overdue),
+            ("age", self.age),
+            ("category", self.category),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn task(priority: Priority, due_in_days: Option<i64>) -> Task {
+        let now = Local::now();
+        Task::with_details(1, "Task".to_string(), None, due_in_days.map(|days| now + Duration::days(days)), None, priority)
+    }
+
+    #[test]
+    fn test_components() {
+        let now = Local::now();
+        let weights = Weights::default();
+
+        let urgency = Urgency::of(&task(Priority::Medium, None), &weights, now);
+        assert_eq!(urgency.priority, 3.9);
+        assert_eq!(urgency.due + urgency.overdue + urgency.category, 0.0);
+
+        let far = Urgency::of(&task(Priority::Low, Some(30)), &weights, now);
+        assert!((far.due - 2.4).abs() < 1e-9);
+        let week = Urgency::of(&task(Priority::Low, Some(7)), &weights, now);
+        assert!((week.due - 7.2).abs() < 0.01);
+        let overdue = Urgency::of(&task(Priority::Low, Some(-2)), &weights, now);
+        assert_eq!((overdue.due, overdue.overdue), (12.0, 5.0));
+
+        let mut old = task(Priority::High, None);
+        old.created_at = now - Duration::days(730);
+        assert_eq!(Urgency::of(&old, &weights, now).age, 2.0);
+        old.complete();
+        assert_eq!(Urgency::of(&old, &weights, now).total(), 0.0);
+    }
+
+    #[test]
+    fn test_category_weights() {
+        let weights = Weights::parse(&["work=2.5", " Someday = -3 "]).unwrap();
+        assert_eq!(weights.category("WORK"), 2.5);
+        assert_eq!(weights.category("someday"), -3.0);
+        assert_eq!(weights.category("home"), 0.0);
+
+        let mut task = task(Priority::Medium, None);
+        task.category = Some("Work".to_string());
+        let urgency = Urgency::of(&task, &weights, Local::now());
+        assert!((urgency.total() - 6.4).abs() < 0.01);
+
+        assert!(Weights::parse(&["work"]).unwrap_err().to_string().contains("CATEGORY=WEIGHT"));
+        assert!(Weights::parse(&["work=high"]).unwrap_err().to_string().contains("Invalid weight 'high'"));
+
+        let table = BTreeMap::from([("Work".to_string(), 1.0), ("home".to_string(), 2.0)]);
+        let merged = Weights::from_table(&table).overridden_by(Weights::parse(&["work=4"]).unwrap());
+        assert_eq!((merged.category("work"), merged.category("home")), (4.0, 2.0));
+    }
+}
@@ -0,0 +1,122 @@
+//! Saved views: named `list` filters
+//!
+//! A view stores the arguments of a `list` command (filter flags, sort field,
+//! reverse flag and query) under a name, so `rtodo view <name>` can run the
+//! same listing again. The arguments are kept as written and parsed each time
+//! the view is shown, so views keep working as `list` gains options.
+//!
+//! Views are kept next to the data file as `<data file>.views`, so they work
+//! the same way for every storage backend.
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Named `list` arguments, sorted by name
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Views {
+    views: BTreeMap<String, Vec<String>>,
+}
+
+impl Views {
+    /// Location of the saved views for a data file
+    pub fn path_for(data_path: &Path) -> PathBuf {
+        let file_name = data_path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        data_path.with_file_name(format!("{}.views", file_name))
+    }
+
+    /// Load saved views, returning none if the file doesn't exist yet
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read views {}: {}", path.display(), e))?;
+        serde_json::from_str(&contents)
+            .map_err(|e| anyhow!("Failed to parse views {}: {}", path.display(), e))
+    }
+
+    /// Save the views, replacing the file atomically
+    pub fn save_to_file<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let json_data = serde_json::to_string_pretty(self)
+            .map_err(|e| anyhow!("Failed to serialize views: {}", e))?;
+
+        let temp_path = path.with_extension("views.tmp");
+        fs::write(&temp_path, json_data)
+            .map_err(|e| anyhow!("Failed to write to temporary file {}: {}", temp_path.display(), e))?;
+        fs::rename(&temp_path, path)
+            .map_err(|e| anyhow!("Failed to move temporary file to final location {}: {}", path.display(), e))?;
+        Ok(())
+    }
+
+    /// The `list` arguments saved under a name
+    pub fn get(&self, name: &str) -> Option<&[String]> {
+        self.views.get(name).map(Vec::as_slice)
+    }
+
+    /// Save arguments under a name, returning `true` if it replaced a view
+    pub fn insert(&mut self, name: String, args: Vec<String>) -> bool {
+        self.views.insert(name, args).is_some()
+    }
+
+    /// Delete a view, returning `false` if there was none with that name
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.views.remove(name).is_some()
+    }
+
+    /// All views with their arguments, sorted by name
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.views.iter().map(|(name, args)| (name.as_str(), args.as_slice()))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.views.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_path_for() {
+        assert_eq!(Views::path_for(Path::new("/data/todos.json")), Path::new("/data/todos.json.views"));
+        assert_eq!(Views::path_for(Path::new("tasks.db")), Path::new("tasks.db.views"));
+    }
+
+    #[test]
+    fn test_save_load_and_remove() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join("todos.json.views");
+        assert!(Views::load_from_file(&path).unwrap().is_empty());
+
+        let mut views = Views::default();
+        assert!(!views.insert("work".to_string(), args(&["--pending", "-C", "work"])));
+        assert!(!views.insert("next".to_string(), args(&["status:ready", "--sort-by", "due"])));
+        assert!(views.insert("work".to_string(), args(&["-C", "work", "-P", "high"])));
+        views.save_to_file(&path).unwrap();
+
+        let mut loaded = Views::load_from_file(&path).unwrap();
+        assert_eq!(loaded, views);
+        assert_eq!(loaded.get("work"), Some(&args(&["-C", "work", "-P", "high"])[..]));
+        let names: Vec<&str> = loaded.iter().map(|(name, _)| name).collect();
+        assert_eq!(names, ["next", "work"]);
+
+        assert!(loaded.remove("next"));
+        assert!(!loaded.remove("next"));
+        assert_eq!(loaded.get("next"), None);
+    }
+}
@@ -2,34 +2,45 @@ use std::process::Command;
 use std::fs;
 use std::path::PathBuf;
 use tempfile::TempDir;
-use serde_json;
+use chrono::{Duration, Local};
 
 struct TestEnv {
-    _temp_dir: TempDir,
+    temp_dir: TempDir,
     config_file: PathBuf,
 }
 
 impl TestEnv {
     fn new() -> Self {
+        Self::with_file_name("test_todos.json")
+    }
+
+    fn with_file_name(name: &str) -> Self {
         let temp_dir = TempDir::new().expect("Failed to create temp directory");
-        let config_file = temp_dir.path().join("test_todos.json");
+        let config_file = temp_dir.path().join(name);
 
         TestEnv {
-            _temp_dir: temp_dir,
+            temp_dir,
             config_file,
         }
     }
 
     fn run_rtodo(&self, args: &[&str]) -> Command {
         let mut cmd = Command::new("cargo");
+        // Point at a config file in the temp directory so the user's own settings don't apply
         cmd.arg("run")
             .arg("--")
             .arg("--file")
             .arg(&self.config_file)
+            .arg("--config")
+            .arg(self.settings_file())
             .args(args);
         cmd
     }
 
+    fn settings_file(&self) -> PathBuf {
+        self.temp_dir.path().join("config.toml")
+    }
+
     fn get_todos_json(&self) -> serde_json::Value {
         if self.config_file.exists() {
             let content = fs::read_to_string(&self.config_file).unwrap_or_default();
@@ -40,6 +51,11 @@ impl TestEnv {
     }
 }
 
+/// Format a date `days` from today as YYYY-MM-DD
+fn date_in_days(days: i64) -> String {
+    (Local::now() + Duration::days(days)).format("%Y-%m-%d").to_string()
+}
+
 #[test]
 fn test_add_basic_todo() {
     let env = TestEnv::new();
@@ -134,7 +150,7 @@ fn test_complete_nonexistent_todo() {
         .output()
         .expect("Failed to execute command");
 
-    assert!(output.status.success()); // Command succeeds but shows error
+    assert_eq!(output.status.code(), Some(1));
     let stderr = String::from_utf8(output.stderr).unwrap();
     assert!(stderr.contains("Error") || stderr.contains("not found"));
 }
@@ -451,12 +467,12 @@ fn test_list_due_soon_filter() {
     let env = TestEnv::new();
 
     // Add a task due soon (5 days from now)
-    env.run_rtodo(&["add", "Due soon task", "--due", "2025-09-25"])
+    env.run_rtodo(&["add", "Due soon task", "--due", &date_in_days(5)])
         .output()
         .expect("Failed to add task");
 
     // Add a task due far in the future
-    env.run_rtodo(&["add", "Future task", "--due", "2025-12-31"])
+    env.run_rtodo(&["add", "Future task", "--due", &date_in_days(90)])
         .output()
         .expect("Failed to add task");
 
@@ -707,7 +723,8 @@ fn test_due_soon_color_highlighting() {
     let env = TestEnv::new();
 
     // Add a task due soon
-    env.run_rtodo(&["add", "Due soon task", "--due", "2025-09-25"])
+    let due = date_in_days(3);
+    env.run_rtodo(&["add", "Due soon task", "--due", &due])
         .output()
         .expect("Failed to add task");
 
@@ -721,7 +738,7 @@ fn test_due_soon_color_highlighting() {
 
     // Should show the task with due date
     assert!(stdout.contains("Due soon task"));
-    assert!(stdout.contains("Due: 2025-09-25"));
+    assert!(stdout.contains(&format!("Due: {}", due)));
 }
 
 #[test]
@@ -794,7 +811,7 @@ fn test_incomplete_nonexistent_task() {
         .output()
         .expect("Failed to execute command");
 
-    assert!(output.status.success()); // Command succeeds but shows error
+    assert_eq!(output.status.code(), Some(1));
     let stderr = String::from_utf8(output.stderr).unwrap();
     assert!(stderr.contains("Error") || stderr.contains("not found"));
 }
@@ -902,7 +919,7 @@ fn test_rename_category_not_found() {
         .output()
         .expect("Failed to execute command");
 
-    assert!(output.status.success()); // Command runs but shows error
+    assert_eq!(output.status.code(), Some(1));
     let stderr = String::from_utf8(output.stderr).unwrap();
     assert!(stderr.contains("Error: No tasks found with category 'nonexistent'"));
 }
@@ -920,7 +937,7 @@ fn test_rename_category_same_names() {
         .output()
         .expect("Failed to execute command");
 
-    assert!(output.status.success()); // Command runs but shows error
+    assert_eq!(output.status.code(), Some(1));
     let stderr = String::from_utf8(output.stderr).unwrap();
     assert!(stderr.contains("Error: Old and new category names are the same"));
 }
@@ -1098,6 +1115,24 @@ fn test_search_with_filters() {
     assert!(!stdout.contains("API testing"));
 }
 
+#[test]
+fn test_search_category_and_tag_flags_match_like_list() {
+    let env = TestEnv::new();
+    env.run_rtodo(&["add", "Write report +Urgent", "--category", "Work"]).output().unwrap();
+
+    let count = |args: &[&str]| {
+        let output = env.run_rtodo(args).args(["--output", "json"]).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap().as_array().unwrap().len()
+    };
+    assert_eq!(count(&["list", "-C", "work"]), 0);
+    assert_eq!(count(&["search", "report", "-C", "work"]), 0);
+    assert_eq!(count(&["search", "report", "-C", "Work", "--tag", "Urgent"]), 1);
+    assert_eq!(count(&["search", "report", "--tag", "urgent"]), 0);
+    // The filter expression ignores case
+    assert_eq!(count(&["search", "report", "--where", "category:work"]), 1);
+}
+
 #[test]
 fn test_search_no_results() {
     let env = TestEnv::new();
@@ -1758,4 +1793,1471 @@ fn test_category_edge_cases() {
     assert!(stdout.contains("work-urgent!"));
     assert!(stdout.contains("personal life"));
     assert!(stdout.contains(&long_category));
-}
\ No newline at end of file
+}
+#[test]
+fn test_recurring_task_spawns_next_instance() {
+    let env = TestEnv::new();
+
+    env.run_rtodo(&["add", "Standup prep", "--due", &date_in_days(1), "--repeat", "daily"])
+        .output()
+        .expect("Failed to add task");
+
+    let output = env.run_rtodo(&["complete", "1"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Completed: Standup prep"));
+    assert!(stdout.contains("Next occurrence:"));
+    assert!(stdout.contains(&date_in_days(2)));
+
+    let json = env.get_todos_json();
+    let tasks = json["tasks"].as_array().unwrap();
+    assert_eq!(tasks.len(), 2);
+    assert!(tasks[0].get("recurrence").is_none());
+    assert_eq!(tasks[1]["recurrence"], "Daily");
+    assert_eq!(tasks[1]["series_id"], 1);
+}
+
+#[test]
+fn test_recurring_list_and_stop() {
+    let env = TestEnv::new();
+
+    env.run_rtodo(&["add", "Expense report", "--repeat", "monthly:28"])
+        .output()
+        .expect("Failed to add task");
+    env.run_rtodo(&["add", "One-off task"])
+        .output()
+        .expect("Failed to add task");
+
+    let output = env.run_rtodo(&["recurring"])
+        .output()
+        .expect("Failed to execute command");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Recurring Tasks (1 series)"));
+
+    let output = env.run_rtodo(&["recurring", "--output", "jsonl"]).output().unwrap();
+    let record: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(record["title"], "Expense report");
+    assert_eq!(record["recurrence"], "monthly:28");
+    assert_eq!(record["completed_count"], 0);
+    assert!(stdout.contains("Expense report"));
+    assert!(stdout.contains("monthly:28"));
+    assert!(!stdout.contains("One-off task"));
+
+    let output = env.run_rtodo(&["recurring", "-v"]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.matches("Repeats").count(), 1);
+    assert!(stdout.contains("Completed: 0"));
+
+    let output = env.run_rtodo(&["stop-recurring", "1"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Stopped recurrence: Expense report"));
+
+    let output = env.run_rtodo(&["recurring"])
+        .output()
+        .expect("Failed to execute command");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("No recurring tasks found"));
+}
+
+#[test]
+fn test_edit_repeat_and_invalid_rule() {
+    let env = TestEnv::new();
+
+    env.run_rtodo(&["add", "Water plants"])
+        .output()
+        .expect("Failed to add task");
+
+    let output = env.run_rtodo(&["edit", "1", "--repeat", "weekly:mon,thu"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Repeats: (none) → weekly:mon,thu"));
+
+    let output = env.run_rtodo(&["edit", "1", "--repeat", "hourly"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Invalid recurrence rule"));
+}
+
+#[test]
+fn test_subtasks_tree_and_progress() {
+    let env = TestEnv::new();
+
+    env.run_rtodo(&["add", "Release 1.0"])
+        .output()
+        .expect("Failed to add task");
+    env.run_rtodo(&["add", "Write changelog", "--parent", "1"])
+        .output()
+        .expect("Failed to add task");
+    env.run_rtodo(&["add", "Tag release", "--parent", "1"])
+        .output()
+        .expect("Failed to add task");
+    env.run_rtodo(&["complete", "2"])
+        .output()
+        .expect("Failed to complete task");
+
+    let output = env.run_rtodo(&["list"])
+        .output()
+        .expect("Failed to execute command");
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Release 1.0 (1/2 subtasks done)"));
+    assert!(stdout.contains("    ✓ [2] Write changelog"));
+    assert!(stdout.contains("    ○ [3] Tag release"));
+
+    // Completing the parent completes the remaining subtask
+    let output = env.run_rtodo(&["complete", "1"])
+        .output()
+        .expect("Failed to execute command");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Also completed: 1 subtask(s)"));
+
+    let json = env.get_todos_json();
+    assert_eq!(json["tasks"][2]["completed"], true);
+}
+
+#[test]
+fn test_subtask_parent_validation_and_removal() {
+    let env = TestEnv::new();
+
+    let output = env.run_rtodo(&["add", "Orphan", "--parent", "42"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Parent task with ID 42 not found"));
+
+    env.run_rtodo(&["add", "Parent"])
+        .output()
+        .expect("Failed to add task");
+    env.run_rtodo(&["add", "Child", "--parent", "1"])
+        .output()
+        .expect("Failed to add task");
+
+    let output = env.run_rtodo(&["edit", "1", "--parent", "2"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+
+    let output = env.run_rtodo(&["remove", "1", "--confirm"])
+        .output()
+        .expect("Failed to execute command");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Moved 1 subtask(s) up one level"));
+
+    let json = env.get_todos_json();
+    assert!(json["tasks"][0].get("parent_id").is_none());
+}
+
+#[test]
+fn test_dependencies_blocked_and_ready_filters() {
+    let env = TestEnv::new();
+
+    env.run_rtodo(&["add", "Review PR"])
+        .output()
+        .expect("Failed to add task");
+    env.run_rtodo(&["add", "Deploy", "--depends-on", "1"])
+        .output()
+        .expect("Failed to add task");
+
+    let output = env.run_rtodo(&["list", "--blocked"])
+        .output()
+        .expect("Failed to execute command");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Deploy"));
+    assert!(stdout.contains("(blocked by 1)"));
+    assert!(!stdout.contains("Review PR"));
+
+    let output = env.run_rtodo(&["list", "--ready"])
+        .output()
+        .expect("Failed to execute command");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Review PR"));
+    assert!(!stdout.contains("Deploy"));
+
+    // Completing a task with open dependencies warns but still completes it
+    let output = env.run_rtodo(&["complete", "2"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Warning"));
+    assert!(stderr.contains("[1] Review PR"));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Completed: Deploy"));
+
+    // So does completing several tasks at once
+    env.run_rtodo(&["add", "Announce", "--depends-on", "1"]).output().unwrap();
+    env.run_rtodo(&["add", "Tidy up"]).output().unwrap();
+    let output = env.run_rtodo(&["complete", "3,4", "--confirm"]).output().unwrap();
+    assert!(output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("task 3 depends on tasks that are still open: [1] Review PR"));
+}
+
+#[test]
+fn test_edit_dependencies_rejects_cycles() {
+    let env = TestEnv::new();
+
+    env.run_rtodo(&["add", "Review"])
+        .output()
+        .expect("Failed to add task");
+    env.run_rtodo(&["add", "Deploy", "--depends-on", "1"])
+        .output()
+        .expect("Failed to add task");
+
+    let output = env.run_rtodo(&["edit", "1", "--depends-on", "2"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("dependency cycle"));
+
+    let output = env.run_rtodo(&["edit", "2", "--remove-depends-on", "1"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Depends on: [1] → (none)"));
+}
+
+#[test]
+fn test_add_with_tags_and_filter() {
+    let env = TestEnv::new();
+
+    env.run_rtodo(&["add", "Call customer +urgent", "+work"])
+        .output()
+        .expect("Failed to add task");
+    env.run_rtodo(&["add", "Plan offsite", "+work", "+someday"])
+        .output()
+        .expect("Failed to add task");
+    env.run_rtodo(&["add", "Buy milk"])
+        .output()
+        .expect("Failed to add task");
+
+    let json = env.get_todos_json();
+    assert_eq!(json["tasks"][0]["title"], "Call customer");
+    assert_eq!(json["tasks"][0]["tags"], serde_json::json!(["urgent", "work"]));
+
+    let output = env.run_rtodo(&["list", "--tag", "work", "--exclude-tag", "someday"])
+        .output()
+        .expect("Failed to execute command");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Call customer +urgent +work"));
+    assert!(!stdout.contains("Plan offsite"));
+    assert!(!stdout.contains("Buy milk"));
+
+    let output = env.run_rtodo(&["search", "l", "--exclude-tag", "work"])
+        .output()
+        .expect("Failed to execute command");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("1 matching tasks"));
+
+    let output = env.run_rtodo(&["add", "Bad", "notatag"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_edit_tags_and_tags_commands() {
+    let env = TestEnv::new();
+
+    env.run_rtodo(&["add", "Call customer", "+urgent"])
+        .output()
+        .expect("Failed to add task");
+    env.run_rtodo(&["add", "Write report", "+work"])
+        .output()
+        .expect("Failed to add task");
+
+    let output = env.run_rtodo(&["edit", "1", "+work", "--untag", "urgent"])
+        .output()
+        .expect("Failed to execute command");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Tags: +urgent → +work"));
+
+    let output = env.run_rtodo(&["tags"])
+        .output()
+        .expect("Failed to execute command");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("+work (2 tasks)"));
+    assert!(!stdout.contains("+urgent"));
+
+    let output = env.run_rtodo(&["rename-tag", "work", "job"])
+        .output()
+        .expect("Failed to execute command");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Renamed tag 'work' to 'job' for 2 tasks"));
+
+    let output = env.run_rtodo(&["rename-tag", "missing", "other"])
+        .output()
+        .expect("Failed to execute command");
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("No tasks found with tag 'missing'"));
+}
+
+#[test]
+fn test_completed_at_recorded_and_cleared() {
+    let env = TestEnv::new();
+
+    env.run_rtodo(&["add", "Finish report"])
+        .output()
+        .expect("Failed to add task");
+    env.run_rtodo(&["complete", "1"])
+        .output()
+        .expect("Failed to complete task");
+
+    let json = env.get_todos_json();
+    assert!(json["tasks"][0]["completed_at"].is_string());
+
+    let output = env.run_rtodo(&["--verbose", "list"])
+        .output()
+        .expect("Failed to execute command");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(&format!("Completed: {}", date_in_days(0))));
+
+    env.run_rtodo(&["incomplete", "1"])
+        .output()
+        .expect("Failed to mark incomplete");
+    let json = env.get_todos_json();
+    assert!(json["tasks"][0].get("completed_at").is_none());
+}
+
+#[test]
+fn test_done_report_and_sort_by_completed() {
+    let env = TestEnv::new();
+
+    env.run_rtodo(&["add", "Second done"])
+        .output()
+        .expect("Failed to add task");
+    env.run_rtodo(&["add", "First done"])
+        .output()
+        .expect("Failed to add task");
+    env.run_rtodo(&["add", "Still pending"])
+        .output()
+        .expect("Failed to add task");
+    env.run_rtodo(&["complete", "2"])
+        .output()
+        .expect("Failed to complete task");
+    env.run_rtodo(&["complete", "1"])
+        .output()
+        .expect("Failed to complete task");
+
+    let output = env.run_rtodo(&["done", "--since", "yesterday"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("(2 tasks)"));
+    assert!(stdout.find("First done").unwrap() < stdout.find("Second done").unwrap());
+    assert!(!stdout.contains("Still pending"));
+
+    let output = env.run_rtodo(&["done", "--since", "yesterday", "--output", "json"]).output().unwrap();
+    let tasks: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let titles: Vec<&str> = tasks.as_array().unwrap().iter().map(|task| task["title"].as_str().unwrap()).collect();
+    assert_eq!(titles, ["First done", "Second done"]);
+
+    let output = env.run_rtodo(&["done", "--until", "yesterday"])
+        .output()
+        .expect("Failed to execute command");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("No tasks completed"));
+
+    let output = env.run_rtodo(&["list", "--sort-by", "completed"])
+        .output()
+        .expect("Failed to execute command");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let first = stdout.find("First done").unwrap();
+    let second = stdout.find("Second done").unwrap();
+    let pending = stdout.find("Still pending").unwrap();
+    assert!(first < second && second < pending);
+}
+
+#[test]
+fn test_load_v1_fixture_and_migrate_on_save() {
+    let env = TestEnv::new();
+    let fixture = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/v1_tasks.json");
+    fs::copy(&fixture, &env.config_file).expect("Failed to copy fixture");
+
+    let output = env.run_rtodo(&["list"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Todo List (3 tasks)"));
+    assert!(stdout.contains("Finish quarterly report"));
+
+    // The pre-migration file is kept next to the task file
+    let backup = env.config_file.with_extension("json.v1.backup");
+    assert_eq!(fs::read_to_string(&backup).unwrap(), fs::read_to_string(&fixture).unwrap());
+
+    // IDs continue from the legacy next_id and the file is rewritten as the current version
+    let output = env.run_rtodo(&["add", "New task"])
+        .output()
+        .expect("Failed to execute command");
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Added task 5"));
+
+    let json = env.get_todos_json();
+    assert_eq!(json["version"], 7);
+    assert_eq!(json["tasks"].as_array().unwrap().len(), 4);
+}
+
+#[test]
+fn test_load_v2_fixture() {
+    let env = TestEnv::new();
+    let fixture = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/v2_tasks.json");
+    fs::copy(&fixture, &env.config_file).expect("Failed to copy fixture");
+
+    let output = env.run_rtodo(&["list", "--blocked"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Tag release"));
+    assert!(env.config_file.with_extension("json.v2.backup").exists());
+
+    // Files already in the current version are not backed up again
+    env.run_rtodo(&["add", "Another task"]).output().unwrap();
+    env.run_rtodo(&["list"]).output().unwrap();
+    assert!(!env.config_file.with_extension("json.v3.backup").exists());
+}
+
+#[test]
+fn test_newer_schema_version_is_not_overwritten() {
+    let env = TestEnv::new();
+    let contents = r#"{"version": 99, "tasks": [], "next_id": 1}"#;
+    fs::write(&env.config_file, contents).expect("Failed to write file");
+
+    let output = env.run_rtodo(&["add", "Should not be saved"])
+        .output()
+        .expect("Failed to execute command");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Please upgrade rtodo"));
+    assert_eq!(fs::read_to_string(&env.config_file).unwrap(), contents);
+}
+
+#[test]
+fn test_sqlite_storage_add_and_filter() {
+    let env = TestEnv::with_file_name("test_todos.db");
+
+    env.run_rtodo(&["add", "Write report +urgent", "--category", "work", "--priority", "high"]).output().unwrap();
+    env.run_rtodo(&["add", "Buy milk", "--category", "home", "--priority", "low"]).output().unwrap();
+    env.run_rtodo(&["add", "Call plumber", "--category", "home"]).output().unwrap();
+    let output = env.run_rtodo(&["complete", "3"]).output().unwrap();
+    assert!(output.status.success());
+
+    let header = fs::read(&env.config_file).unwrap();
+    assert!(header.starts_with(b"SQLite format 3"));
+
+    let output = env.run_rtodo(&["list", "--category", "home", "--pending"]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Buy milk"));
+    assert!(!stdout.contains("Call plumber"));
+    assert!(!stdout.contains("Write report"));
+
+    let output = env.run_rtodo(&["list", "--tag", "urgent"]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Write report"));
+    assert!(stdout.contains("(1 tasks)"));
+
+    let output = env.run_rtodo(&["list", "--completed"]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Call plumber"));
+    assert!(stdout.contains("(1 tasks)"));
+
+    env.run_rtodo(&["remove", "2", "--confirm"]).output().unwrap();
+    let output = env.run_rtodo(&["list"]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(!stdout.contains("Buy milk"));
+    assert!(stdout.contains("(2 tasks)"));
+}
+
+#[test]
+fn test_migrate_storage_round_trip() {
+    let env = TestEnv::new();
+    env.run_rtodo(&["add", "First task +home", "--due", &date_in_days(3)]).output().unwrap();
+    env.run_rtodo(&["add", "Second task", "--parent", "1"]).output().unwrap();
+    env.run_rtodo(&["complete", "2"]).output().unwrap();
+
+    let output = env.run_rtodo(&["migrate-storage", "--to", "sqlite"]).output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Migrated"));
+    assert!(stdout.contains("2 tasks"));
+
+    let db_file = env.config_file.with_extension("db");
+    assert!(db_file.exists());
+
+    // The database can be used directly and migrated back to JSON
+    let json_file = env.config_file.with_file_name("roundtrip.json");
+    let output = Command::new("cargo")
+        .args(["run", "--", "--file"])
+        .arg(&db_file)
+        .args(["migrate-storage", "--to", "json", "--dest"])
+        .arg(&json_file)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let original = env.get_todos_json();
+    let roundtrip: serde_json::Value = serde_json::from_str(&fs::read_to_string(&json_file).unwrap()).unwrap();
+    assert_eq!(original, roundtrip);
+
+    // Existing destinations are only replaced with --force
+    let output = env.run_rtodo(&["migrate-storage", "--to", "sqlite"]).output().unwrap();
+    assert!(!output.status.success());
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("--force"));
+
+    let output = env.run_rtodo(&["migrate-storage", "--to", "sqlite", "--force"]).output().unwrap();
+    assert!(output.status.success());
+}
+
+#[test]
+fn test_concurrent_adds_do_not_lose_tasks() {
+    let env = TestEnv::new();
+
+    // Run the built binary directly so the processes really overlap
+    let handles: Vec<_> = (0..16)
+        .map(|i| {
+            let file = env.config_file.clone();
+            std::thread::spawn(move || {
+                Command::new(env!("CARGO_BIN_EXE_rtodo"))
+                    .arg("--file")
+                    .arg(&file)
+                    .args(["add", &format!("Parallel task {}", i)])
+                    .output()
+                    .expect("Failed to execute command")
+            })
+        })
+        .collect();
+    for handle in handles {
+        assert!(handle.join().unwrap().status.success());
+    }
+
+    let json = env.get_todos_json();
+    let tasks = json["tasks"].as_array().unwrap();
+    assert_eq!(tasks.len(), 16);
+    let ids: std::collections::HashSet<u64> = tasks.iter().map(|t| t["id"].as_u64().unwrap()).collect();
+    assert_eq!(ids.len(), 16);
+    assert_eq!(json["next_id"], 17);
+}
+
+#[test]
+fn test_undo_and_redo() {
+    let env = TestEnv::new();
+    env.run_rtodo(&["add", "Buy milk", "--category", "home"]).output().unwrap();
+    env.run_rtodo(&["add", "Pay rent", "--category", "home"]).output().unwrap();
+    env.run_rtodo(&["complete", "1"]).output().unwrap();
+    env.run_rtodo(&["rename-category", "home", "house"]).output().unwrap();
+    env.run_rtodo(&["remove", "2", "--confirm"]).output().unwrap();
+
+    // Commands that change nothing are not recorded
+    env.run_rtodo(&["list"]).output().unwrap();
+    env.run_rtodo(&["complete", "99"]).output().unwrap();
+
+    let output = env.run_rtodo(&["history"]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("1. remove 2 --confirm"));
+    assert!(stdout.contains("2. rename-category home house"));
+    assert!(stdout.contains("5. add \"Buy milk\" --category home"));
+    assert!(!stdout.contains("6."));
+
+    // A dry run shows the changes without applying them
+    let output = env.run_rtodo(&["undo", "2", "--dry-run"]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Would undo: remove 2 --confirm"));
+    assert!(stdout.contains("Restored [2] Pay rent"));
+    assert!(stdout.contains("house → home"));
+    assert_eq!(env.get_todos_json()["tasks"].as_array().unwrap().len(), 1);
+
+    let output = env.run_rtodo(&["undo", "3"]).output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Undid: complete 1"));
+    let json = env.get_todos_json();
+    let tasks = json["tasks"].as_array().unwrap();
+    assert_eq!(tasks.len(), 2);
+    assert_eq!(tasks[0]["completed"], false);
+    assert_eq!(tasks[1]["category"], "home");
+
+    let output = env.run_rtodo(&["redo"]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Redid: complete 1"));
+    assert_eq!(env.get_todos_json()["tasks"][0]["completed"], true);
+
+    // A new change discards what is left to redo
+    env.run_rtodo(&["add", "Walk dog"]).output().unwrap();
+    let output = env.run_rtodo(&["redo"]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Nothing to redo"));
+
+    let output = env.run_rtodo(&["undo", "10"]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Only 4 operation(s) could be undone"));
+    assert!(env.get_todos_json()["tasks"].as_array().unwrap().is_empty());
+    assert_eq!(env.get_todos_json()["next_id"], 1);
+}
+
+#[test]
+fn test_json_output_for_listing_commands() {
+    let env = TestEnv::new();
+    env.run_rtodo(&["add", "Write report +urgent", "--category", "work", "--priority", "high", "--due", &date_in_days(-1)]).output().unwrap();
+    env.run_rtodo(&["add", "Buy milk", "--category", "home"]).output().unwrap();
+    env.run_rtodo(&["add", "Send report", "--depends-on", "1"]).output().unwrap();
+
+    let output = env.run_rtodo(&["list", "--output", "json"]).output().unwrap();
+    assert!(output.status.success());
+    let tasks: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let tasks = tasks.as_array().unwrap();
+    assert_eq!(tasks.len(), 3);
+    assert_eq!(tasks[0]["title"], "Write report");
+    assert_eq!(tasks[0]["priority"], "high");
+    assert_eq!(tasks[0]["tags"], serde_json::json!(["urgent"]));
+    assert_eq!(tasks[0]["overdue"], true);
+    assert!(tasks[1]["due_date"].is_null());
+    assert_eq!(tasks[2]["blocked"], true);
+    assert_eq!(tasks[2]["depends_on"], serde_json::json!([1]));
+
+    let output = env.run_rtodo(&["search", "report", "--output", "jsonl"]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<serde_json::Value> = stdout.lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1]["id"], 3);
+
+    let output = env.run_rtodo(&["overdue", "--output", "json"]).output().unwrap();
+    let overdue: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(overdue.as_array().unwrap().len(), 1);
+
+    let output = env.run_rtodo(&["due-today", "--output", "json"]).output().unwrap();
+    let due_today: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(due_today, serde_json::json!([]));
+
+    let output = env.run_rtodo(&["categories", "--output", "json"]).output().unwrap();
+    let categories: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(categories, serde_json::json!([{"name": "home", "count": 1}, {"name": "work", "count": 1}]));
+
+    let output = env.run_rtodo(&["--output", "json", "tags"]).output().unwrap();
+    let tags: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(tags, serde_json::json!([{"name": "urgent", "count": 1}]));
+
+    let output = env.run_rtodo(&["--output", "json", "history"]).output().unwrap();
+    let history: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(history.as_array().unwrap().len(), 3);
+    assert_eq!(history[0]["step"], 1);
+    assert_eq!(history[0]["tasks"], 1);
+    assert!(history[0]["command"].as_str().unwrap().contains("Send report"));
+
+    let output = env.run_rtodo(&["--output", "json", "done"]).output().unwrap();
+    assert_eq!(serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap(), serde_json::json!([]));
+    let output = env.run_rtodo(&["--output", "json", "recurring"]).output().unwrap();
+    assert_eq!(serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap(), serde_json::json!([]));
+
+    let output = env.run_rtodo(&["--output", "jsonl"]).output().unwrap();
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary, serde_json::json!({"total": 3, "completed": 0, "pending": 3, "overdue": 1, "blocked": 1}));
+}
+
+#[test]
+fn test_errors_exit_non_zero() {
+    let env = TestEnv::new();
+
+    let output = env.run_rtodo(&["edit", "42", "--title", "Nope"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Task with ID 42 not found"));
+
+    // In JSON modes the error is reported as JSON too
+    let output = env.run_rtodo(&["complete", "42", "--output", "json"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    let error: serde_json::Value = serde_json::from_str(stderr.lines().last().unwrap()).unwrap();
+    assert!(error["error"].as_str().unwrap().contains("not found"));
+}
+
+#[test]
+fn test_todotxt_import_and_export() {
+    let env = TestEnv::new();
+    let todo_file = env.config_file.with_file_name("todo.txt");
+    fs::write(
+        &todo_file,
+        "(A) 2024-04-30 Call the plumber @home +house due:2030-05-03\n\
+         x 2024-05-02 2024-04-28 File taxes @admin pri:B\n",
+    )
+    .unwrap();
+
+    let output = env.run_rtodo(&["import", "--format", "todotxt"]).arg(&todo_file).output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Imported 2 tasks"));
+
+    let json = env.get_todos_json();
+    let tasks = json["tasks"].as_array().unwrap();
+    assert_eq!(tasks[0]["title"], "Call the plumber");
+    assert_eq!(tasks[0]["priority"], "High");
+    assert_eq!(tasks[0]["category"], "home");
+    assert_eq!(tasks[0]["tags"], serde_json::json!(["house"]));
+    assert_eq!(tasks[1]["completed"], true);
+
+    let output = env.run_rtodo(&["export", "--format", "todotxt"]).output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(
+        stdout,
+        "(A) 2024-04-30 Call the plumber @home +house due:2030-05-03\n\
+         x 2024-05-02 2024-04-28 File taxes @admin pri:B\n"
+    );
+
+    // Imports are recorded like any other change
+    let output = env.run_rtodo(&["undo"]).output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(env.get_todos_json()["tasks"].as_array().unwrap().len(), 0);
+
+    // Malformed files are rejected without importing anything
+    fs::write(&todo_file, "Fine\nBroken due:someday\n").unwrap();
+    let output = env.run_rtodo(&["import", "--format", "todotxt"]).arg(&todo_file).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Line 2"));
+    assert_eq!(env.get_todos_json()["tasks"].as_array().unwrap().len(), 0);
+}
+
+#[test]
+fn test_ics_export_and_import() {
+    let env = TestEnv::new();
+    env.run_rtodo(&["add", "Dentist appointment", "--due", "2030-02-14", "--category", "health", "--priority", "high"])
+        .output()
+        .unwrap();
+
+    let ics_file = env.config_file.with_file_name("tasks.ics");
+    let output = env.run_rtodo(&["export", "--format", "ics"]).arg(&ics_file).output().unwrap();
+    assert!(output.status.success());
+    let exported = fs::read_to_string(&ics_file).unwrap();
+    assert!(exported.contains("BEGIN:VTODO\r\n"));
+    assert!(exported.contains("SUMMARY:Dentist appointment\r\n"));
+    assert!(exported.contains("PRIORITY:1\r\n"));
+    assert!(exported.contains("CATEGORIES:health\r\n"));
+    assert!(exported.contains("STATUS:NEEDS-ACTION\r\n"));
+
+    // Tasks that came from this list are recognised by their UID
+    let output = env.run_rtodo(&["import", "--format", "ics"]).arg(&ics_file).output().unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout).unwrap().contains("Imported 0 tasks"));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("already in the list"));
+
+    let other = TestEnv::new();
+    let output = other.run_rtodo(&["import", "--format", "ics"]).arg(&ics_file).output().unwrap();
+    assert!(output.status.success());
+    let json = other.get_todos_json();
+    let tasks = json["tasks"].as_array().unwrap();
+    assert_eq!(tasks.len(), 1);
+    assert_eq!(tasks[0]["title"], "Dentist appointment");
+    assert_eq!(tasks[0]["category"], "health");
+}
+
+#[test]
+fn test_ics_export_reports_edits() {
+    let env = TestEnv::new();
+    env.run_rtodo(&["add", "Dentist appointment"]).output().unwrap();
+    let ics_file = env.config_file.with_file_name("tasks.ics");
+
+    env.run_rtodo(&["export", "--format", "ics"]).arg(&ics_file).output().unwrap();
+    let exported = fs::read_to_string(&ics_file).unwrap();
+    assert!(exported.contains("SEQUENCE:0\r\n"));
+    assert!(!exported.contains("LAST-MODIFIED:"));
+
+    env.run_rtodo(&["edit", "1", "--priority", "high"]).output().unwrap();
+    env.run_rtodo(&["export", "--format", "ics"]).arg(&ics_file).output().unwrap();
+    let exported = fs::read_to_string(&ics_file).unwrap();
+    assert!(exported.contains("SEQUENCE:1\r\n"));
+    assert!(exported.contains("LAST-MODIFIED:"));
+
+    // Undoing the edit is another change, so the sequence keeps going up
+    env.run_rtodo(&["undo"]).output().unwrap();
+    env.run_rtodo(&["export", "--format", "ics"]).arg(&ics_file).output().unwrap();
+    assert!(fs::read_to_string(&ics_file).unwrap().contains("SEQUENCE:2\r\n"));
+    assert_eq!(env.get_todos_json()["tasks"][0]["priority"], "Medium");
+}
+
+#[test]
+fn test_taskwarrior_import_is_idempotent() {
+    let env = TestEnv::new();
+    let tw_file = env.config_file.with_file_name("tw.json");
+    fs::write(
+        &tw_file,
+        r#"[
+{"id":1,"description":"Review pull request","entry":"20240428T080000Z","project":"work","status":"pending","tags":["code"],"priority":"H","due":"20300503T160000Z","uuid":"2c6f5b8e-4d3a-4f6e-9a1b-0c2d3e4f5a6b","annotations":[{"entry":"20240428T080500Z","description":"Check the migration"}]},
+{"id":0,"description":"Old idea","entry":"20240101T080000Z","status":"deleted","uuid":"0b1c2d3e-4f50-4617-8293-a4b5c6d7e8f9"}
+]"#,
+    )
+    .unwrap();
+
+    let output = env.run_rtodo(&["import", "--format", "taskwarrior"]).arg(&tw_file).output().unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout).unwrap().contains("Imported 1 tasks"));
+
+    let json = env.get_todos_json();
+    let task = &json["tasks"][0];
+    assert_eq!(task["title"], "Review pull request");
+    assert_eq!(task["description"], "Check the migration");
+    assert_eq!(task["category"], "work");
+    assert_eq!(task["priority"], "High");
+    assert_eq!(task["uuid"], "2c6f5b8e-4d3a-4f6e-9a1b-0c2d3e4f5a6b");
+
+    // Importing the same export again adds nothing
+    let output = env.run_rtodo(&["import", "--format", "taskwarrior"]).arg(&tw_file).output().unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout).unwrap().contains("Imported 0 tasks"));
+    assert_eq!(env.get_todos_json()["tasks"].as_array().unwrap().len(), 1);
+
+    // Exported tasks keep their Taskwarrior UUID
+    let output = env.run_rtodo(&["export", "--format", "taskwarrior"]).output().unwrap();
+    assert!(output.status.success());
+    let exported: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(exported[0]["uuid"], "2c6f5b8e-4d3a-4f6e-9a1b-0c2d3e4f5a6b");
+    assert_eq!(exported[0]["project"], "work");
+    assert_eq!(exported[0]["status"], "pending");
+}
+
+#[test]
+fn test_csv_and_markdown_export_use_list_filters() {
+    let env = TestEnv::new();
+    env.run_rtodo(&["add", "Write report +q1", "--category", "work", "--priority", "high"]).output().unwrap();
+    env.run_rtodo(&["add", "Buy milk", "--category", "home"]).output().unwrap();
+    env.run_rtodo(&["add", "Plan offsite", "--category", "work", "--priority", "low"]).output().unwrap();
+    env.run_rtodo(&["complete", "3"]).output().unwrap();
+
+    let output = env.run_rtodo(&["export", "--format", "csv", "--category", "work", "--sort-by", "title"]).output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = stdout.lines().collect();
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("id,title,description,completed,priority,category,tags,due_date"));
+    assert!(lines[1].starts_with("3,Plan offsite,,true,low,work,"));
+    assert!(lines[2].starts_with("1,Write report,,false,high,work,q1,"));
+
+    let output = env.run_rtodo(&["export", "--format", "markdown", "--pending"]).output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "## home\n\n- [ ] Buy milk\n\n## work\n\n- [ ] Write report (high priority) +q1\n"
+    );
+
+    let output = env.run_rtodo(&["import", "--format", "markdown", "tasks.md"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn test_csv_import_with_column_mapping() {
+    let env = TestEnv::new();
+    let csv_file = env.config_file.with_file_name("plan.csv");
+    fs::write(
+        &csv_file,
+        "Task,Deadline,Team,Priority\nOrder laptops,2030-06-01,it,high\n\"Book venue, catering\",2030-06-15,events,low\n",
+    )
+    .unwrap();
+
+    // Without a title column the file is rejected
+    let output = env.run_rtodo(&["import", "--format", "csv"]).arg(&csv_file).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("--column title=HEADER"));
+
+    let output = env
+        .run_rtodo(&["import", "--format", "csv", "--column", "title=Task", "--column", "due=Deadline", "--column", "category=Team"])
+        .arg(&csv_file)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8(output.stdout).unwrap().contains("Imported 2 tasks"));
+
+    let json = env.get_todos_json();
+    let tasks = json["tasks"].as_array().unwrap();
+    assert_eq!(tasks[1]["title"], "Book venue, catering");
+    assert_eq!(tasks[1]["category"], "events");
+    assert_eq!(tasks[1]["priority"], "Low");
+    assert!(tasks[0]["due_date"].as_str().unwrap().starts_with("2030-06-01"));
+}
+
+#[test]
+fn test_query_filters_list_search_and_export() {
+    let env = TestEnv::new();
+    let yesterday = date_in_days(-1);
+    env.run_rtodo(&["add", "Write report", "--category", "work", "--priority", "high"]).output().unwrap();
+    env.run_rtodo(&["add", "Review budget", "--category", "work", "--priority", "low", "--due", &yesterday]).output().unwrap();
+    env.run_rtodo(&["add", "Learn piano +someday", "--category", "work", "--priority", "high"]).output().unwrap();
+    env.run_rtodo(&["add", "Buy milk", "--category", "home"]).output().unwrap();
+
+    let output = env
+        .run_rtodo(&["--output", "jsonl", "list", "category:work and (priority:high or due<friday) and not tag:someday"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let ids: Vec<u64> = stdout
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["id"].as_u64().unwrap())
+        .collect();
+    assert_eq!(ids, vec![1, 2]);
+
+    // Words of an unquoted query are joined, and flags still apply
+    let output = env.run_rtodo(&["list", "priority:high", "--category", "work"]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Write report") && stdout.contains("Learn piano"));
+    assert!(!stdout.contains("Buy milk"));
+
+    let output = env.run_rtodo(&["search", "r", "--where", "+someday or cat:home"]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Search Results (1 matching tasks)"));
+    assert!(stdout.contains("Learn piano"));
+
+    let output = env.run_rtodo(&["export", "--format", "csv", "--where", "priority<medium"]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert_eq!(stdout.lines().count(), 2);
+    assert!(stdout.contains("Review budget"));
+
+    let output = env.run_rtodo(&["list", "category:work and (pri:urgent"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("unknown priority 'urgent'"));
+    assert!(stderr.contains("  category:work and (pri:urgent\n                         ^^^^^^"));
+}
+
+#[test]
+fn test_saved_views() {
+    let env = TestEnv::new();
+    env.run_rtodo(&["add", "Write report", "--category", "work", "--priority", "high", "--due", &date_in_days(3)]).output().unwrap();
+    env.run_rtodo(&["add", "Fix printer", "--category", "work", "--priority", "high", "--due", &date_in_days(1)]).output().unwrap();
+    env.run_rtodo(&["add", "Plan offsite", "--category", "work"]).output().unwrap();
+    env.run_rtodo(&["add", "Buy milk", "--category", "home", "--priority", "high"]).output().unwrap();
+
+    let output = env.run_rtodo(&["view", "save", "hot", "--pending", "-C", "work", "-P", "high", "--sort-by", "due"]).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8(output.stdout).unwrap().contains("Saved view hot"));
+    assert!(env.config_file.with_file_name("test_todos.json.views").exists());
+
+    // Invalid options and queries are rejected when saving
+    let output = env.run_rtodo(&["view", "save", "broken", "--sort-by", "size"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let output = env.run_rtodo(&["view", "save", "broken", "priority:"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    let output = env.run_rtodo(&["--output", "jsonl", "view", "hot"]).output().unwrap();
+    let ids: Vec<u64> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["id"].as_u64().unwrap())
+        .collect();
+    assert_eq!(ids, vec![2, 1]);
+
+    env.run_rtodo(&["view", "save", "home", "category:home"]).output().unwrap();
+    let output = env.run_rtodo(&["view", "list"]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("home category:home"));
+    assert!(stdout.contains("hot --pending -C work -P high --sort-by due"));
+    assert!(!stdout.contains("broken"));
+
+    let output = env.run_rtodo(&["view", "delete", "hot"]).output().unwrap();
+    assert!(output.status.success());
+    let output = env.run_rtodo(&["view", "hot"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("View 'hot' not found"));
+}
+
+#[test]
+fn test_tui_requires_a_terminal() {
+    let env = TestEnv::new();
+    env.run_rtodo(&["add", "Write report"]).output().unwrap();
+
+    let output = env.run_rtodo(&["tui"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("The TUI needs an interactive terminal"));
+    assert_eq!(env.get_todos_json()["tasks"].as_array().unwrap().len(), 1);
+}
+
+#[cfg(unix)]
+#[test]
+fn test_add_and_edit_in_editor() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let env = TestEnv::new();
+    let script = env.config_file.with_file_name("editor.sh");
+    let write_editor = |body: &str| {
+        fs::write(&script, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+    };
+
+    write_editor("printf 'title: Plan offsite\\ncategory: team\\ntags: q3 travel\\n---\\nBook venue\\nInvite everyone\\n' > \"$1\"");
+    let output = env.run_rtodo(&["add", "--editor", "--priority", "high"])
+        .env_remove("VISUAL")
+        .env("EDITOR", &script)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8(output.stdout).unwrap().contains("Added task 1 Plan offsite"));
+
+    let json = env.get_todos_json();
+    let task = &json["tasks"][0];
+    assert_eq!(task["description"], "Book venue\nInvite everyone");
+    assert_eq!(task["category"], "team");
+    assert_eq!(task["priority"], "High");
+    assert_eq!(task["tags"], serde_json::json!(["q3", "travel"]));
+
+    // The editor sees the current task and its changes are shown as a diff
+    write_editor("grep -q '^title: Plan offsite$' \"$1\" && sed -i -e 's/^due:.*/due: 2030-05-01/' -e 's/^scheduled:.*/scheduled: 2030-04-20/' -e 's/^priority:.*/priority: low/' \"$1\"");
+    let output = env.run_rtodo(&["edit", "1", "--editor"]).env_remove("VISUAL").env("EDITOR", &script).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Updated task [1]"));
+    assert!(stdout.contains("Priority: high → low"));
+    assert!(stdout.contains("Due date: (none) → 2030-05-01"));
+    assert!(stdout.contains("Scheduled: (none) → 2030-04-20"));
+
+    write_editor("true");
+    let output = env.run_rtodo(&["edit", "1", "--editor"]).env_remove("VISUAL").env("EDITOR", &script).output().unwrap();
+    assert!(String::from_utf8(output.stdout).unwrap().contains("No changes made."));
+
+    // Invalid documents and failing editors leave the task alone
+    write_editor("sed -i 's/^due:.*/due: someday soon/' \"$1\"");
+    let output = env.run_rtodo(&["edit", "1", "--editor"]).env_remove("VISUAL").env("EDITOR", &script).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Line 4: invalid due date 'someday soon'"));
+    let kept = stderr.trim_end().rsplit_once("Your edit was saved to ").expect("kept edit").1;
+    assert!(fs::read_to_string(kept).unwrap().contains("due: someday soon"));
+    fs::remove_file(kept).unwrap();
+    write_editor("exit 3");
+    let output = env.run_rtodo(&["edit", "1", "--editor"]).env_remove("VISUAL").env("EDITOR", &script).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(env.get_todos_json()["tasks"][0]["priority"], "Low");
+}
+
+#[test]
+fn test_bulk_operations() {
+    let env = TestEnv::new();
+    for title in ["Write report", "Fix printer", "Plan offsite", "Book flights", "Order chairs"] {
+        env.run_rtodo(&["add", title, "--category", "work"]).output().unwrap();
+    }
+    env.run_rtodo(&["add", "Buy milk", "--category", "home"]).output().unwrap();
+    let completed = |env: &TestEnv| -> Vec<u64> {
+        env.get_todos_json()["tasks"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|task| task["completed"] == true)
+            .map(|task| task["id"].as_u64().unwrap())
+            .collect()
+    };
+
+    // Lists and ranges
+    let output = env.run_rtodo(&["complete", "1,3-4", "--confirm"]).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Found 3 task(s)") && stdout.contains("[3] Plan offsite"));
+    assert_eq!(completed(&env), vec![1, 3, 4]);
+
+    // Without --confirm the prompt gets no answer and nothing changes
+    let output = env.run_rtodo(&["incomplete", "1-4"]).output().unwrap();
+    assert!(String::from_utf8(output.stdout).unwrap().contains("Reopen 3 task(s)?"));
+    assert_eq!(completed(&env), vec![1, 3, 4]);
+
+    // A missing ID given on its own is an error, nothing is completed
+    let output = env.run_rtodo(&["complete", "2,99", "--confirm"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("Task with ID 99 not found"));
+    assert_eq!(completed(&env), vec![1, 3, 4]);
+
+    // Filter queries
+    let output = env.run_rtodo(&["edit", "--where", "category:work and status:pending", "+urgent", "--priority", "high", "--confirm"]).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let todos = env.get_todos_json();
+    let tasks = todos["tasks"].as_array().unwrap();
+    for task in tasks {
+        let edited = matches!(task["id"].as_u64(), Some(2 | 5));
+        assert_eq!(task["priority"] == "High", edited, "{}", task);
+        assert_eq!(task["tags"].as_array().is_some_and(|tags| tags.contains(&"urgent".into())), edited);
+    }
+
+    let output = env.run_rtodo(&["edit", "2,5", "--title", "Same title"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    let output = env.run_rtodo(&["remove", "--where", "status:completed", "--confirm"]).output().unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout).unwrap().contains("Removed: 3 task(s)"));
+    let todos = env.get_todos_json();
+    let ids: Vec<u64> = todos["tasks"].as_array().unwrap().iter().map(|task| task["id"].as_u64().unwrap()).collect();
+    assert_eq!(ids, vec![2, 5, 6]);
+
+    let output = env.run_rtodo(&["complete", "--where", "category:home and status:completed"]).output().unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout).unwrap().contains("No tasks to complete"));
+}
+
+#[test]
+fn test_bulk_operations_with_huge_range() {
+    let env = TestEnv::new();
+    env.run_rtodo(&["add", "Write report"]).output().unwrap();
+    env.run_rtodo(&["add", "Fix printer"]).output().unwrap();
+
+    // Only the tasks in the list are visited, not every ID in the range
+    let mut child = env.run_rtodo(&["complete", "1-4000000000", "--confirm"])
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    let started = std::time::Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+        if started.elapsed() > std::time::Duration::from_secs(60) {
+            child.kill().unwrap();
+            panic!("completing a huge range did not finish");
+        }
+        std::thread::sleep(std::time::Duration::from_millis(50));
+    };
+    assert!(status.success());
+    let todos = env.get_todos_json();
+    assert!(todos["tasks"].as_array().unwrap().iter().all(|task| task["completed"] == true));
+}
+
+#[test]
+fn test_archive_unarchive_and_purge() {
+    let env = TestEnv::new();
+    let archive_file = env.config_file.with_file_name("test_todos.json.archive.json");
+    for title in ["Renew domain", "Write changelog", "Plan offsite"] {
+        env.run_rtodo(&["add", title]).output().unwrap();
+    }
+    env.run_rtodo(&["complete", "1"]).output().unwrap();
+    env.run_rtodo(&["complete", "2"]).output().unwrap();
+
+    // Pretend the first task was completed two months ago
+    let mut todos = env.get_todos_json();
+    todos["tasks"][0]["completed_at"] = (Local::now() - Duration::days(60)).to_rfc3339().into();
+    fs::write(&env.config_file, serde_json::to_string(&todos).unwrap()).unwrap();
+
+    let ids = |env: &TestEnv, args: &[&str]| -> Vec<u64> {
+        let output = env.run_rtodo(&[&["--output", "json"], args].concat()).output().unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        json.as_array().unwrap().iter().map(|task| task["id"].as_u64().unwrap()).collect()
+    };
+
+    let output = env.run_rtodo(&["archive", "--older-than", "30", "--dry-run"]).output().unwrap();
+    assert!(String::from_utf8(output.stdout).unwrap().contains("Would archive 1 task(s)"));
+    assert!(!archive_file.exists());
+
+    let output = env.run_rtodo(&["archive", "--older-than", "30"]).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(ids(&env, &["list"]), vec![2, 3]);
+    assert_eq!(ids(&env, &["list", "--archived"]), vec![1]);
+
+    env.run_rtodo(&["archive"]).output().unwrap();
+    assert_eq!(ids(&env, &["list"]), vec![3]);
+    assert_eq!(ids(&env, &["search", "changelog", "-i", "--archived"]), vec![2]);
+    assert_eq!(ids(&env, &["search", "changelog", "-i"]), Vec::<u64>::new());
+
+    // Tasks keep their IDs when moved back, and new tasks never reuse archived IDs
+    let output = env.run_rtodo(&["unarchive", "2"]).output().unwrap();
+    assert!(String::from_utf8(output.stdout).unwrap().contains("Unarchived: Write changelog"));
+    env.run_rtodo(&["add", "Book venue"]).output().unwrap();
+    assert_eq!(ids(&env, &["list"]), vec![2, 3, 4]);
+    let output = env.run_rtodo(&["unarchive", "2"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    let output = env.run_rtodo(&["purge", "--before", &date_in_days(-30), "--confirm"]).output().unwrap();
+    assert!(output.status.success());
+    assert!(String::from_utf8(output.stdout).unwrap().contains("Purged: 1 archived task(s)"));
+    assert_eq!(ids(&env, &["list", "--archived"]), Vec::<u64>::new());
+}
+
+#[test]
+fn test_undo_and_redo_archive_moves() {
+    let env = TestEnv::new();
+    env.run_rtodo(&["add", "A"]).output().unwrap();
+    env.run_rtodo(&["add", "B"]).output().unwrap();
+    env.run_rtodo(&["complete", "1"]).output().unwrap();
+    env.run_rtodo(&["archive"]).output().unwrap();
+
+    let archived = |env: &TestEnv| -> usize {
+        let output = env.run_rtodo(&["--output", "json", "list", "--archived"]).output().unwrap();
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap().as_array().unwrap().len()
+    };
+    let undo = |env: &TestEnv, command: &str| {
+        let output = env.run_rtodo(&[command]).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    // Undoing the archive moves the task back, and older operations can be undone after it
+    assert!(undo(&env, "undo").contains("Unarchived [1] A"));
+    assert_eq!(env.get_todos_json()["tasks"].as_array().unwrap().len(), 2);
+    assert_eq!(archived(&env), 0);
+    undo(&env, "undo");
+    assert_eq!(env.get_todos_json()["tasks"][0]["completed"], false);
+
+    undo(&env, "redo");
+    assert!(undo(&env, "redo").contains("Archived [1] A"));
+    assert_eq!(env.get_todos_json()["tasks"].as_array().unwrap().len(), 1);
+    assert_eq!(archived(&env), 1);
+
+    // Purging can be undone too
+    env.run_rtodo(&["purge", "--before", &date_in_days(1), "--confirm"]).output().unwrap();
+    assert_eq!(archived(&env), 0);
+    assert!(undo(&env, "undo").contains("Restored to the archive [1] A"));
+    assert_eq!(archived(&env), 1);
+
+    // A task changed by hand since then is reported without blaming anyone
+    let archive_file = env.config_file.with_file_name("test_todos.json.archive.json");
+    fs::write(&archive_file, r#"{"version": 7, "tasks": [], "next_id": 1}"#).unwrap();
+    let output = env.run_rtodo(&["undo"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8(output.stderr).unwrap().contains("task 1 is no longer in the archive"));
+}
+
+#[test]
+fn test_trash_restore_and_expiry() {
+    let env = TestEnv::new();
+    for title in ["Old idea", "Write report"] {
+        env.run_rtodo(&["add", title]).output().unwrap();
+    }
+    env.run_rtodo(&["remove", "1", "--confirm"]).output().unwrap();
+    env.run_rtodo(&["remove", "2", "--confirm"]).output().unwrap();
+    let todos = env.get_todos_json();
+    assert!(todos["tasks"].as_array().unwrap().is_empty());
+    assert_eq!(todos["trash"].as_array().unwrap().len(), 2);
+
+    let output = env.run_rtodo(&["trash"]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.find("[2] Write report").unwrap() < stdout.find("[1] Old idea").unwrap());
+
+    let output = env.run_rtodo(&["trash", "restore", "2"]).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(env.get_todos_json()["tasks"][0]["title"], "Write report");
+    let output = env.run_rtodo(&["trash", "restore", "2"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    // Pretend the other task was removed 40 days ago
+    let mut todos = env.get_todos_json();
+    todos["trash"][0]["deleted_at"] = (Local::now() - Duration::days(40)).to_rfc3339().into();
+    fs::write(&env.config_file, serde_json::to_string(&todos).unwrap()).unwrap();
+
+    let output = env.run_rtodo(&["trash"]).env("RTODO_TRASH_DAYS", "0").output().unwrap();
+    assert!(String::from_utf8(output.stdout).unwrap().contains("[1] Old idea"));
+    env.run_rtodo(&["config", "set", "trash_days", "0"]).output().unwrap();
+    let output = env.run_rtodo(&["trash"]).env_remove("RTODO_TRASH_DAYS").output().unwrap();
+    assert!(String::from_utf8(output.stdout).unwrap().contains("[1] Old idea"));
+    let output = env.run_rtodo(&["trash", "--trash-days", "30"]).env_remove("RTODO_TRASH_DAYS").output().unwrap();
+    assert!(String::from_utf8(output.stdout).unwrap().contains("The trash is empty"));
+
+    env.run_rtodo(&["add", "Plan offsite", "--trash-days", "30"]).env_remove("RTODO_TRASH_DAYS").output().unwrap();
+    assert!(env.get_todos_json().get("trash").is_none());
+
+    env.run_rtodo(&["remove", "3", "--confirm"]).output().unwrap();
+    let output = env.run_rtodo(&["trash", "empty", "--confirm"]).output().unwrap();
+    assert!(String::from_utf8(output.stdout).unwrap().contains("Deleted: 1 task(s)"));
+    assert!(env.get_todos_json().get("trash").is_none());
+}
+
+#[test]
+fn test_due_times() {
+    let env = TestEnv::new();
+    env.run_rtodo(&["add", "Dentist", "--due", "2026-11-02T09:30"]).output().unwrap();
+    env.run_rtodo(&["add", "Pay rent", "--due", "2026-11-02"]).output().unwrap();
+    env.run_rtodo(&["add", "Call back", "--due", "in 3 hours"]).output().unwrap();
+
+    let todos = env.get_todos_json();
+    assert!(todos["tasks"][0]["due_date"].as_str().unwrap().starts_with("2026-11-02T09:30:00"));
+    assert_eq!(todos["tasks"][0]["due_has_time"], true);
+    assert!(todos["tasks"][1]["due_date"].as_str().unwrap().starts_with("2026-11-02T23:59:59"));
+    assert!(todos["tasks"][1].get("due_has_time").is_none());
+
+    let output = env.run_rtodo(&["list", "--verbose"]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Due: 2026-11-02 09:30"));
+    assert!(stdout.contains("Due: 2026-11-02 ("));
+    assert!(stdout.contains("(due in 3 hours)"));
+
+    // A date without a time makes the task due by the end of the day again
+    env.run_rtodo(&["edit", "1", "--due", "2026-11-03"]).output().unwrap();
+    assert!(env.get_todos_json()["tasks"][0].get("due_has_time").is_none());
+    let output = env.run_rtodo(&["edit", "2", "--due", "tomorrow at 8am"]).output().unwrap();
+    assert!(String::from_utf8(output.stdout).unwrap().contains("2026-11-02 → "));
+    assert_eq!(env.get_todos_json()["tasks"][1]["due_has_time"], true);
+}
+
+#[test]
+fn test_scheduled_and_waiting_tasks() {
+    let env = TestEnv::new();
+    env.run_rtodo(&["add", "Renew certificate", "--wait", &date_in_days(14)]).output().unwrap();
+    env.run_rtodo(&["add", "Plan offsite", "--scheduled", &date_in_days(3)]).output().unwrap();
+    env.run_rtodo(&["add", "Water plants"]).output().unwrap();
+
+    let titles = |args: &[&str]| {
+        let output = env.run_rtodo(args).args(["--output", "json"]).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        json.as_array().unwrap().iter().map(|task| task["title"].as_str().unwrap().to_string()).collect::<Vec<_>>()
+    };
+    assert_eq!(titles(&["list"]), ["Plan offsite", "Water plants"]);
+    assert_eq!(titles(&["list", "--waiting"]), ["Renew certificate"]);
+    assert_eq!(titles(&["list", "status:waiting"]), ["Renew certificate"]);
+    assert_eq!(titles(&["available"]), ["Water plants"]);
+    assert_eq!(titles(&["list", "--sort-by", "scheduled"]), ["Plan offsite", "Water plants"]);
+
+    let output = env.run_rtodo(&["edit", "1", "--wait", "none", "--scheduled", "today"]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Wait: ") && stdout.contains("→ (none)"));
+    let todos = env.get_todos_json();
+    assert!(todos["tasks"][0].get("wait").is_none());
+    assert!(todos["tasks"][0]["scheduled"].is_string());
+    assert_eq!(titles(&["available"]), ["Renew certificate", "Water plants"]);
+}
+
+#[test]
+fn test_next_by_urgency() {
+    let env = TestEnv::new();
+    env.run_rtodo(&["add", "Someday idea", "--priority", "low", "--category", "someday"]).output().unwrap();
+    env.run_rtodo(&["add", "Pay rent", "--due", &date_in_days(-2)]).output().unwrap();
+    env.run_rtodo(&["add", "Fix outage", "--priority", "high", "--due", &date_in_days(1)]).output().unwrap();
+    env.run_rtodo(&["add", "Renew certificate", "--priority", "high", "--wait", &date_in_days(7)]).output().unwrap();
+    env.run_rtodo(&["add", "Review notes", "--category", "work"]).output().unwrap();
+
+    let titles = |args: &[&str]| {
+        let output = env.run_rtodo(args).args(["--output", "json"]).env_remove("RTODO_CATEGORY_WEIGHTS").output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        json.as_array().unwrap().iter().map(|task| task["title"].as_str().unwrap().to_string()).collect::<Vec<_>>()
+    };
+    assert_eq!(titles(&["next"]), ["Pay rent", "Fix outage", "Review notes", "Someday idea"]);
+    assert_eq!(titles(&["next", "2"]), ["Pay rent", "Fix outage"]);
+    assert_eq!(titles(&["next", "3", "--category-weight", "work=20,someday=-5"]), ["Review notes", "Pay rent", "Fix outage"]);
+    assert_eq!(
+        titles(&["list", "--sort-by", "urgency", "--reverse"]),
+        ["Someday idea", "Review notes", "Fix outage", "Pay rent"]
+    );
+
+    let output = env.run_rtodo(&["next", "1", "--verbose"]).output().unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Urgency: 20.9 = priority 3.9 + due 12.0 + overdue 5.0"), "{}", stdout);
+
+    let output = env.run_rtodo(&["next", "--category-weight", "work"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+
+    // The flag overrides the config file's weight for its category only
+    let output = env.run_rtodo(&["config", "set", "category_weights", "work=20,someday=-5"]).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(titles(&["next", "3"]), ["Review notes", "Pay rent", "Fix outage"]);
+    assert_eq!(titles(&["next", "3", "--category-weight", "someday=30"]), ["Someday idea", "Review notes", "Pay rent"]);
+}
+
+#[test]
+fn test_config_file() {
+    let env = TestEnv::new();
+    let config = |args: &[&str]| {
+        let output = env.run_rtodo(&[&["config"], args].concat()).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        String::from_utf8(output.stdout).unwrap()
+    };
+
+    assert_eq!(config(&["get", "due_soon_days"]), "7\n");
+    config(&["set", "default_priority", "high"]);
+    config(&["set", "default_category", "inbox"]);
+    config(&["set", "due_soon_days", "2"]);
+    config(&["set", "date_dialect", "uk"]);
+    config(&["set", "default_sort", "title"]);
+    let saved = fs::read_to_string(env.settings_file()).unwrap();
+    assert!(saved.contains("default_priority = \"high\"") && saved.contains("date_dialect = \"uk\""));
+    let listing = config(&["list"]);
+    assert!(listing.contains("due_soon_days = 2") && listing.contains("color = auto (default)"));
+
+    env.run_rtodo(&["add", "Water plants", "--due", &date_in_days(4)]).output().unwrap();
+    env.run_rtodo(&["add", "Book flights", "--priority", "low", "--category", "travel", "--due", "02/11/2030"]).output().unwrap();
+    let todos = env.get_todos_json();
+    assert_eq!(todos["tasks"][0]["priority"], "High");
+    assert_eq!(todos["tasks"][0]["category"], "inbox");
+    assert_eq!(todos["tasks"][1]["category"], "travel");
+    assert!(todos["tasks"][1]["due_date"].as_str().unwrap().starts_with("2030-11-02"));
+
+    let titles = |args: &[&str]| {
+        let output = env.run_rtodo(args).args(["--output", "json"]).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        json.as_array().unwrap().iter().map(|task| task["title"].as_str().unwrap().to_string()).collect::<Vec<_>>()
+    };
+    assert_eq!(titles(&["list"]), ["Book flights", "Water plants"]);
+    assert_eq!(titles(&["list", "--sort-by", "created"]), ["Water plants", "Book flights"]);
+    assert!(titles(&["list", "--due-soon"]).is_empty());
+    config(&["unset", "due_soon_days"]);
+    assert_eq!(titles(&["list", "--due-soon"]), ["Water plants"]);
+
+    let output = env.run_rtodo(&["config", "set", "default_sort", "size"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid default_sort 'size'"));
+    let output = env.run_rtodo(&["config", "get", "theme"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn test_config_command_repairs_invalid_values() {
+    let env = TestEnv::new();
+    fs::write(env.settings_file(), "default_priority = \"urgent\"\ndefault_sort = \"size\"\n").unwrap();
+
+    let output = env.run_rtodo(&["list"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid config"));
+
+    let output = env.run_rtodo(&["config", "get", "default_priority"]).output().unwrap();
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "urgent\n");
+    for args in [["config", "set", "default_priority", "high"].as_slice(), &["config", "unset", "default_sort"]] {
+        let output = env.run_rtodo(args).output().unwrap();
+        assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    }
+
+    let output = env.run_rtodo(&["add", "Repaired"]).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(env.get_todos_json()["tasks"][0]["priority"], "High");
+}
+
+#[test]
+fn test_config_color_theme() {
+    let env = TestEnv::new();
+    fs::write(env.settings_file(), "color = \"always\"\ncolor_high = \"magenta\"\ncolor_category = \"bright cyan\"\n").unwrap();
+    env.run_rtodo(&["add", "Write report", "--priority", "high", "--category", "work"]).output().unwrap();
+
+    let output = env.run_rtodo(&["list"]).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("\x1b[35m[high]\x1b[0m"), "{}", stdout);
+    assert!(stdout.contains("\x1b[96m#work\x1b[0m"), "{}", stdout);
+
+    let output = env.run_rtodo(&["config", "set", "color_low", "mauve"]).output().unwrap();
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Invalid value 'mauve' for color_low"));
+}
+
+#[test]
+fn test_corrupt_data_file_is_not_overwritten() {
+    let env = TestEnv::new();
+    env.run_rtodo(&["add", "First task"]).output().unwrap();
+    env.run_rtodo(&["add", "Second task"]).output().unwrap();
+    let contents = fs::read_to_string(&env.config_file).unwrap();
+    let truncated = &contents[..contents.len() / 2];
+    fs::write(&env.config_file, truncated).unwrap();
+
+    for args in [["list"].as_slice(), &["add", "Third task"]] {
+        let output = env.run_rtodo(args).output().unwrap();
+        assert_eq!(output.status.code(), Some(1));
+        assert!(String::from_utf8_lossy(&output.stderr).contains("File may be corrupted"));
+    }
+    assert_eq!(fs::read_to_string(&env.config_file).unwrap(), truncated);
+}